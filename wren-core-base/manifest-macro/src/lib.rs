@@ -146,6 +146,42 @@ pub fn model(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream
             pub refresh_time: Option<String>,
             #[serde(default)]
             pub row_level_access_controls: Vec<Arc<RowLevelAccessControl>>,
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub hierarchies: Vec<Arc<Hierarchy>>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub description: Option<String>,
+            /// The other names of the model used by the business
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub synonyms: Vec<String>,
+            /// The data source storing the model. It's the data source of the manifest if absent.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub data_source: Option<DataSource>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Hierarchy`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn hierarchy(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Hierarchy {
+            pub name: String,
+            /// The columns of the model from the coarsest level to the finest level
+            pub levels: Vec<String>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -189,6 +225,23 @@ pub fn column(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStrea
             #[deprecated]
             pub cls: Option<ColumnLevelSecurity>,
             pub column_level_access_control: Option<Arc<ColumnLevelAccessControl>>,
+            /// How the measure of a metric can be aggregated again
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub additivity: Option<Additivity>,
+            /// The time grain which a semi-additive measure can't be aggregated across
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub non_additive_dimension: Option<NonAdditiveDimension>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub description: Option<String>,
+            /// The only values the column can have, e.g. the values of an enumeration
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub allowed_values: Vec<String>,
+            /// Some typical values of the column
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub sample_values: Vec<String>,
+            /// The other names of the column used by the business
+            #[serde(default, skip_serializing_if = "Vec::is_empty")]
+            pub synonyms: Vec<String>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -277,6 +330,10 @@ pub fn metric(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStrea
             pub dimension: Vec<Arc<Column>>,
             pub measure: Vec<Arc<Column>>,
             pub time_grain: Vec<TimeGrain>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub cumulative: Option<CumulativeWindow>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub period_offset: Option<PeriodOffset>,
             #[serde(default, with = "bool_from_int")]
             pub cached: bool,
             pub refresh_time: Option<String>,
@@ -306,6 +363,138 @@ pub fn time_grain(python_binding: proc_macro::TokenStream) -> proc_macro::TokenS
             pub name: String,
             pub ref_column: String,
             pub date_parts: Vec<TimeUnit>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub fiscal_year_start_month: Option<u32>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `CumulativeWindow`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn cumulative_window(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct CumulativeWindow {
+            pub time_grain: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub reset: Option<TimeUnit>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `PeriodOffset`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn period_offset(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct PeriodOffset {
+            pub time_grain: String,
+            pub offset: u32,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `Additivity`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn additivity(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        pub enum Additivity {
+            #[serde(alias = "ADDITIVE", alias = "additive")]
+            Additive,
+            #[serde(alias = "SEMI_ADDITIVE", alias = "semi_additive")]
+            SemiAdditive,
+            #[serde(alias = "NON_ADDITIVE", alias = "non_additive")]
+            NonAdditive,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `NonAdditiveDimension`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn non_additive_dimension(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct NonAdditiveDimension {
+            pub time_grain: String,
+            pub window: NonAdditiveWindow,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `NonAdditiveWindow`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn non_additive_window(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        pub enum NonAdditiveWindow {
+            #[serde(alias = "FIRST", alias = "first")]
+            First,
+            #[serde(alias = "LAST", alias = "last")]
+            Last,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -326,13 +515,27 @@ pub fn time_unit(python_binding: proc_macro::TokenStream) -> proc_macro::TokenSt
 
     let expanded = quote! {
         #python_binding
-        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
         pub enum TimeUnit {
+            #[serde(alias = "YEAR", alias = "year")]
             Year,
+            #[serde(alias = "FISCAL_YEAR", alias = "fiscal_year")]
+            FiscalYear,
+            #[serde(alias = "QUARTER", alias = "quarter")]
+            Quarter,
+            #[serde(alias = "MONTH", alias = "month")]
             Month,
+            #[serde(alias = "WEEK", alias = "week")]
+            Week,
+            #[serde(alias = "ISO_WEEK", alias = "iso_week")]
+            IsoWeek,
+            #[serde(alias = "DAY", alias = "day")]
             Day,
+            #[serde(alias = "HOUR", alias = "hour")]
             Hour,
+            #[serde(alias = "MINUTE", alias = "minute")]
             Minute,
+            #[serde(alias = "SECOND", alias = "second")]
             Second,
         }
     };
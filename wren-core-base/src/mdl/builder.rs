@@ -20,7 +20,8 @@
 #![allow(dead_code)]
 
 use crate::mdl::manifest::{
-    Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeGrain, TimeUnit, View,
+    Additivity, Column, CumulativeWindow, DataSource, Hierarchy, JoinType, Manifest, Metric, Model,
+    NonAdditiveDimension, NonAdditiveWindow, PeriodOffset, Relationship, TimeGrain, TimeUnit, View,
 };
 #[allow(deprecated)]
 use crate::mdl::{
@@ -114,6 +115,10 @@ impl ModelBuilder {
                 cached: false,
                 refresh_time: None,
                 row_level_access_controls: vec![],
+                hierarchies: vec![],
+                description: None,
+                synonyms: vec![],
+                data_source: None,
             },
         }
     }
@@ -168,6 +173,29 @@ impl ModelBuilder {
         self
     }
 
+    pub fn hierarchy(mut self, name: &str, levels: &[&str]) -> Self {
+        self.model.hierarchies.push(Arc::new(Hierarchy {
+            name: name.to_string(),
+            levels: to_strings(levels),
+        }));
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.model.description = Some(description.to_string());
+        self
+    }
+
+    pub fn synonyms(mut self, synonyms: &[&str]) -> Self {
+        self.model.synonyms = to_strings(synonyms);
+        self
+    }
+
+    pub fn data_source(mut self, data_source: DataSource) -> Self {
+        self.model.data_source = Some(data_source);
+        self
+    }
+
     pub fn build(self) -> Arc<Model> {
         Arc::new(self.model)
     }
@@ -208,6 +236,12 @@ impl ColumnBuilder {
                 rls: None,
                 cls: None,
                 column_level_access_control: None,
+                additivity: None,
+                non_additive_dimension: None,
+                description: None,
+                allowed_values: vec![],
+                sample_values: vec![],
+                synonyms: vec![],
             },
         }
     }
@@ -284,11 +318,50 @@ impl ColumnBuilder {
         self
     }
 
+    pub fn additivity(mut self, additivity: Additivity) -> Self {
+        self.column.additivity = Some(additivity);
+        self
+    }
+
+    /// Declare the measure is semi-additive across the time grain
+    pub fn non_additive_dimension(mut self, time_grain: &str, window: NonAdditiveWindow) -> Self {
+        self.column.additivity = Some(Additivity::SemiAdditive);
+        self.column.non_additive_dimension = Some(NonAdditiveDimension {
+            time_grain: time_grain.to_string(),
+            window,
+        });
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.column.description = Some(description.to_string());
+        self
+    }
+
+    pub fn allowed_values(mut self, values: &[&str]) -> Self {
+        self.column.allowed_values = to_strings(values);
+        self
+    }
+
+    pub fn sample_values(mut self, values: &[&str]) -> Self {
+        self.column.sample_values = to_strings(values);
+        self
+    }
+
+    pub fn synonyms(mut self, synonyms: &[&str]) -> Self {
+        self.column.synonyms = to_strings(synonyms);
+        self
+    }
+
     pub fn build(self) -> Arc<Column> {
         Arc::new(self.column)
     }
 }
 
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
 pub struct RelationshipBuilder {
     pub relationship: Relationship,
 }
@@ -338,12 +411,19 @@ impl MetricBuilder {
                 dimension: vec![],
                 measure: vec![],
                 time_grain: vec![],
+                cumulative: None,
+                period_offset: None,
                 cached: false,
                 refresh_time: None,
             },
         }
     }
 
+    pub fn base_object(mut self, base_object: &str) -> Self {
+        self.metric.base_object = base_object.to_string();
+        self
+    }
+
     pub fn dimension(mut self, dimension: Arc<Column>) -> Self {
         self.metric.dimension.push(dimension);
         self
@@ -359,6 +439,24 @@ impl MetricBuilder {
         self
     }
 
+    /// Accumulate the measures along the time grain. The accumulation restarts every `reset` period if specified.
+    pub fn cumulative(mut self, time_grain: &str, reset: Option<TimeUnit>) -> Self {
+        self.metric.cumulative = Some(CumulativeWindow {
+            time_grain: time_grain.to_string(),
+            reset,
+        });
+        self
+    }
+
+    /// Compare with the measures of the `offset` periods before along the time grain
+    pub fn period_offset(mut self, time_grain: &str, offset: u32) -> Self {
+        self.metric.period_offset = Some(PeriodOffset {
+            time_grain: time_grain.to_string(),
+            offset,
+        });
+        self
+    }
+
     pub fn cached(mut self, cached: bool) -> Self {
         self.metric.cached = cached;
         self
@@ -385,6 +483,7 @@ impl TimeGrainBuilder {
                 name: name.to_string(),
                 ref_column: "".to_string(),
                 date_parts: vec![],
+                fiscal_year_start_month: None,
             },
         }
     }
@@ -399,6 +498,11 @@ impl TimeGrainBuilder {
         self
     }
 
+    pub fn fiscal_year_start_month(mut self, month: u32) -> Self {
+        self.time_grain.fiscal_year_start_month = Some(month);
+        self
+    }
+
     pub fn build(self) -> TimeGrain {
         self.time_grain
     }
@@ -436,7 +540,8 @@ mod test {
     };
     use crate::mdl::manifest::DataSource::MySQL;
     use crate::mdl::manifest::{
-        Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeUnit, View,
+        Additivity, Column, DataSource, JoinType, Manifest, Metric, Model, NonAdditiveWindow,
+        Relationship, TimeUnit, View,
     };
     use crate::mdl::ColumnLevelOperator;
     #[allow(deprecated)]
@@ -463,6 +568,11 @@ mod test {
                 ColumnLevelOperator::Equals,
                 "'NORMAL'",
             )
+            .non_additive_dimension("balance_date", NonAdditiveWindow::Last)
+            .description("the status of the order")
+            .allowed_values(&["OPEN", "SHIPPED", "CLOSED"])
+            .sample_values(&["OPEN"])
+            .synonyms(&["state"])
             .build();
 
         let json_str = serde_json::to_string(&expected).unwrap();
@@ -536,12 +646,22 @@ mod test {
                 )],
                 "id = @session_id_default",
             )
+            .hierarchy("geo", &["country", "region", "city"])
+            .description("the test model")
+            .synonyms(&["exam"])
+            .data_source(DataSource::Postgres)
             .build();
 
         let json_str = serde_json::to_string(&model).unwrap();
         let actual: Arc<Model> = serde_json::from_str(&json_str).unwrap();
         assert_eq!(actual, model);
 
+        let geo = model.get_hierarchy("geo").unwrap();
+        assert_eq!(geo.drill_down("country"), Some("region"));
+        assert_eq!(geo.drill_down("city"), None);
+        assert_eq!(geo.drill_up("region"), Some("country"));
+        assert_eq!(geo.drill_up("country"), None);
+
         // test table_reference is null
         let model = ModelBuilder::new("test")
             .ref_sql("SELECT * FROM test")
@@ -631,15 +751,58 @@ mod test {
                 TimeGrainBuilder::new("tg")
                     .ref_column("tg")
                     .date_part(TimeUnit::Day)
+                    .date_part(TimeUnit::FiscalYear)
+                    .fiscal_year_start_month(4)
                     .build(),
             )
+            .cumulative("tg", Some(TimeUnit::Month))
             .cached(true)
             .refresh_time("1h")
             .build();
 
         let json_str = serde_json::to_string(&model).unwrap();
         let actual: Arc<Metric> = serde_json::from_str(&json_str).unwrap();
-        assert_eq!(actual, model)
+        assert_eq!(actual, model);
+
+        // the base object is required even if the metric is derived
+        let json_str = r#"{"name": "test", "dimension": [], "measure": [], "timeGrain": []}"#;
+        let error = serde_json::from_str::<Metric>(json_str).unwrap_err();
+        assert!(error.to_string().contains("missing field `baseObject`"));
+    }
+
+    #[test]
+    fn test_time_unit_serde() {
+        for (json_str, expected) in [
+            ("\"Month\"", TimeUnit::Month),
+            ("\"MONTH\"", TimeUnit::Month),
+            ("\"month\"", TimeUnit::Month),
+            ("\"FiscalYear\"", TimeUnit::FiscalYear),
+            ("\"FISCAL_YEAR\"", TimeUnit::FiscalYear),
+            ("\"iso_week\"", TimeUnit::IsoWeek),
+        ] {
+            let actual: TimeUnit = serde_json::from_str(json_str).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_additivity_serde() {
+        let json_str = r#"{
+            "name": "balance",
+            "type": "integer",
+            "expression": "sum(balance)",
+            "additivity": "SEMI_ADDITIVE",
+            "nonAdditiveDimension": {"timeGrain": "balance_date", "window": "last"}
+        }"#;
+        let actual: Arc<Column> = serde_json::from_str(json_str).unwrap();
+        let expected = ColumnBuilder::new("balance", "integer")
+            .expression("sum(balance)")
+            .non_additive_dimension("balance_date", NonAdditiveWindow::Last)
+            .build();
+        assert_eq!(actual, expected);
+
+        let actual: Additivity = serde_json::from_str("\"non_additive\"").unwrap();
+        assert_eq!(actual, Additivity::NonAdditive);
     }
 
     #[test]
@@ -25,10 +25,11 @@ mod manifest_impl {
     use crate::mdl::manifest::bool_from_int;
     use crate::mdl::manifest::table_reference;
     use manifest_macro::{
-        column, column_level_access_control, column_level_operator, column_level_security,
-        data_source, join_type, manifest, metric, model, normalized_expr, normalized_expr_type,
-        relationship, row_level_access_control, row_level_operator, row_level_security,
-        session_property, time_grain, time_unit, view,
+        additivity, column, column_level_access_control, column_level_operator,
+        column_level_security, cumulative_window, data_source, hierarchy, join_type, manifest,
+        metric, model, non_additive_dimension, non_additive_window, normalized_expr,
+        normalized_expr_type, period_offset, relationship, row_level_access_control,
+        row_level_operator, row_level_security, session_property, time_grain, time_unit, view,
     };
     use serde::{Deserialize, Serialize};
     use serde_with::serde_as;
@@ -39,6 +40,7 @@ mod manifest_impl {
     manifest!(false);
     data_source!(false);
     model!(false);
+    hierarchy!(false);
     column!(false);
     relationship!(false);
     metric!(false);
@@ -46,6 +48,11 @@ mod manifest_impl {
     join_type!(false);
     time_grain!(false);
     time_unit!(false);
+    cumulative_window!(false);
+    period_offset!(false);
+    additivity!(false);
+    non_additive_dimension!(false);
+    non_additive_window!(false);
     row_level_access_control!(false);
     column_level_access_control!(false);
     session_property!(false);
@@ -63,10 +70,11 @@ mod manifest_impl {
     use crate::mdl::manifest::bool_from_int;
     use crate::mdl::manifest::table_reference;
     use manifest_macro::{
-        column, column_level_access_control, column_level_operator, column_level_security,
-        data_source, join_type, manifest, metric, model, normalized_expr, normalized_expr_type,
-        relationship, row_level_access_control, row_level_operator, row_level_security,
-        session_property, time_grain, time_unit, view,
+        additivity, column, column_level_access_control, column_level_operator,
+        column_level_security, cumulative_window, data_source, hierarchy, join_type, manifest,
+        metric, model, non_additive_dimension, non_additive_window, normalized_expr,
+        normalized_expr_type, period_offset, relationship, row_level_access_control,
+        row_level_operator, row_level_security, session_property, time_grain, time_unit, view,
     };
     use pyo3::pyclass;
     use serde::{Deserialize, Serialize};
@@ -78,6 +86,7 @@ mod manifest_impl {
 
     data_source!(true);
     model!(true);
+    hierarchy!(true);
     column!(true);
     relationship!(true);
     metric!(true);
@@ -85,6 +94,11 @@ mod manifest_impl {
     join_type!(true);
     time_grain!(true);
     time_unit!(true);
+    cumulative_window!(true);
+    period_offset!(true);
+    additivity!(true);
+    non_additive_dimension!(true);
+    non_additive_window!(true);
     manifest!(true);
     row_level_access_control!(true);
     column_level_access_control!(true);
@@ -285,6 +299,46 @@ impl Model {
     pub fn row_level_access_controls(&self) -> &[Arc<RowLevelAccessControl>] {
         &self.row_level_access_controls
     }
+
+    /// Return the data source declared on the model
+    pub fn data_source(&self) -> Option<DataSource> {
+        self.data_source
+    }
+
+    /// Return the hierarchies of the columns declared on the model
+    pub fn hierarchies(&self) -> &[Arc<Hierarchy>] {
+        &self.hierarchies
+    }
+
+    /// Get the specified hierarchy by name
+    pub fn get_hierarchy(&self, name: &str) -> Option<Arc<Hierarchy>> {
+        self.hierarchies.iter().find(|h| h.name == name).cloned()
+    }
+}
+
+impl Hierarchy {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the position of the level in the hierarchy. The coarsest level is 0.
+    pub fn level_index(&self, level: &str) -> Option<usize> {
+        self.levels.iter().position(|l| l == level)
+    }
+
+    /// Return the finer level right below the given level
+    pub fn drill_down(&self, level: &str) -> Option<&str> {
+        self.level_index(level)
+            .and_then(|index| self.levels.get(index + 1))
+            .map(String::as_str)
+    }
+
+    /// Return the coarser level right above the given level
+    pub fn drill_up(&self, level: &str) -> Option<&str> {
+        self.level_index(level)
+            .and_then(|index| index.checked_sub(1))
+            .map(|index| self.levels[index].as_str())
+    }
 }
 
 impl Column {
@@ -311,6 +365,87 @@ impl Metric {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Return the name of the model or metric the metric is based on
+    pub fn base_object(&self) -> &str {
+        &self.base_object
+    }
+
+    /// A derived metric has an empty base object, e.g. `"baseObject": ""`. Its measures are computed from the measures of other metrics.
+    /// The base object is required, so a missing one isn't regarded as derived.
+    pub fn is_derived(&self) -> bool {
+        self.base_object.is_empty()
+    }
+
+    /// Get the specified dimension by name
+    pub fn get_dimension(&self, name: &str) -> Option<Arc<Column>> {
+        self.dimension.iter().find(|c| c.name == name).cloned()
+    }
+
+    /// Get the specified measure by name
+    pub fn get_measure(&self, name: &str) -> Option<Arc<Column>> {
+        self.measure.iter().find(|c| c.name == name).cloned()
+    }
+
+    /// Get the specified time grain by name
+    pub fn get_time_grain(&self, name: &str) -> Option<&TimeGrain> {
+        self.time_grain.iter().find(|t| t.name == name)
+    }
+
+    /// Return the time grain which the window of the cumulative or period-over-period metric is ordered by
+    pub fn window_time_grain(&self) -> Option<&str> {
+        self.cumulative
+            .as_ref()
+            .map(|c| c.time_grain.as_str())
+            .or_else(|| self.period_offset.as_ref().map(|p| p.time_grain.as_str()))
+    }
+}
+
+impl TimeUnit {
+    /// Return the time units which can be truncated
+    pub fn all() -> [TimeUnit; 10] {
+        [
+            TimeUnit::Year,
+            TimeUnit::FiscalYear,
+            TimeUnit::Quarter,
+            TimeUnit::Month,
+            TimeUnit::Week,
+            TimeUnit::IsoWeek,
+            TimeUnit::Day,
+            TimeUnit::Hour,
+            TimeUnit::Minute,
+            TimeUnit::Second,
+        ]
+    }
+}
+
+impl Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeUnit::Year => write!(f, "year"),
+            TimeUnit::FiscalYear => write!(f, "fiscal_year"),
+            TimeUnit::Quarter => write!(f, "quarter"),
+            TimeUnit::Month => write!(f, "month"),
+            TimeUnit::Week => write!(f, "week"),
+            TimeUnit::IsoWeek => write!(f, "iso_week"),
+            TimeUnit::Day => write!(f, "day"),
+            TimeUnit::Hour => write!(f, "hour"),
+            TimeUnit::Minute => write!(f, "minute"),
+            TimeUnit::Second => write!(f, "second"),
+        }
+    }
+}
+
+impl TimeGrain {
+    /// Return the name of the time grain
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The first month of the fiscal year. Default is January.
+    pub fn fiscal_year_start_month(&self) -> u32 {
+        self.fiscal_year_start_month.unwrap_or(1)
+    }
 }
 
 impl View {
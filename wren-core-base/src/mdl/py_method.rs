@@ -67,6 +67,21 @@ mod manifest_python_impl {
         fn get_name(&self) -> PyResult<String> {
             Ok(self.name.clone())
         }
+
+        #[getter]
+        fn get_description(&self) -> PyResult<Option<String>> {
+            Ok(self.description.clone())
+        }
+
+        #[getter]
+        fn get_synonyms(&self) -> PyResult<Vec<String>> {
+            Ok(self.synonyms.clone())
+        }
+
+        #[getter]
+        fn get_data_source(&self) -> PyResult<Option<DataSource>> {
+            Ok(self.data_source)
+        }
     }
 
     #[pymethods]
@@ -18,8 +18,9 @@
 use crate::errors::CoreError;
 use crate::manifest::to_manifest;
 use crate::remote_functions::PyRemoteFunction;
+use futures::StreamExt;
 use log::debug;
-use pyo3::types::{PyAnyMethods, PyFrozenSet, PyFrozenSetMethods, PyTuple};
+use pyo3::types::{PyAnyMethods, PyBytes, PyFrozenSet, PyFrozenSetMethods, PyTuple};
 use pyo3::{PyObject, Python};
 use pyo3::{pyclass, pymethods, PyErr, PyResult};
 use std::collections::HashMap;
@@ -32,11 +33,13 @@ use tokio::runtime::Runtime;
 use wren_core::array::AsArray;
 use wren_core::ast::{visit_statements_mut, Expr, Statement, Value};
 use wren_core::dialect::GenericDialect;
+use wren_core::ipc::writer::StreamWriter;
 use wren_core::mdl::context::create_ctx_with_mdl;
 use wren_core::mdl::function::{
     ByPassAggregateUDF, ByPassScalarUDF, ByPassWindowFunction, FunctionType,
     RemoteFunction,
 };
+use wren_core::mdl::semantic_query::SemanticQuery;
 use wren_core::{
     mdl, AggregateUDF, AnalyzedWrenMDL, ScalarUDF, SessionConfig, WindowUDF,
 };
@@ -208,6 +211,69 @@ impl PySessionContext {
             .map_err(|e| PyErr::from(CoreError::from(e)))
     }
 
+    /// Transform the given semantic query in JSON to the equivalent Planned SQL.
+    pub fn transform_semantic_query(&self, query: &str) -> PyResult<String> {
+        env_logger::try_init().ok();
+        let query = SemanticQuery::from_json(query).map_err(CoreError::from)?;
+        self.runtime
+            .block_on(mdl::transform_semantic_query_with_ctx(
+                &self.ctx,
+                Arc::clone(&self.mdl),
+                &[],
+                Arc::clone(&self.properties),
+                &query,
+            ))
+            .map_err(|e| PyErr::from(CoreError::from(e)))
+    }
+
+    /// Execute the given Wren SQL in the local runtime and return the result as a `pyarrow.Table`.
+    /// The execution stops after `limit` rows if it's given.
+    ///
+    /// The batches are executed as a stream, but the whole result is buffered into one Arrow IPC
+    /// payload before it's returned, so the result should fit in memory. Set `limit` to bound it.
+    /// A model whose source table can't be read locally fails with an error.
+    #[pyo3(signature = (sql, limit=None))]
+    pub fn execute(
+        &self,
+        py: Python<'_>,
+        sql: &str,
+        limit: Option<usize>,
+    ) -> PyResult<PyObject> {
+        env_logger::try_init().ok();
+        // the remote functions of `self.ctx` can't be executed locally, so start from a new
+        // context with the same config
+        let ctx = wren_core::SessionContext::new_with_config(self.ctx.copied_config());
+        let mut stream = py
+            .allow_threads(|| {
+                self.runtime.block_on(mdl::execute_sql(
+                    &ctx,
+                    Arc::clone(&self.mdl),
+                    Arc::clone(&self.properties),
+                    sql,
+                    limit,
+                ))
+            })
+            .map_err(CoreError::from)?;
+        let mut buffer = vec![];
+        let mut writer = StreamWriter::try_new(&mut buffer, &stream.schema())
+            .map_err(CoreError::from)?;
+        while let Some(batch) = py.allow_threads(|| self.runtime.block_on(stream.next()))
+        {
+            // the signals are only checked between the batches. Dropping the stream on an
+            // error, e.g. KeyboardInterrupt, cancels the rest of the execution.
+            py.check_signals()?;
+            writer
+                .write(&batch.map_err(CoreError::from)?)
+                .map_err(CoreError::from)?;
+        }
+        writer.finish().map_err(CoreError::from)?;
+        drop(writer);
+        let reader = py
+            .import("pyarrow.ipc")?
+            .call_method1("open_stream", (PyBytes::new(py, &buffer),))?;
+        Ok(reader.call_method0("read_all")?.unbind())
+    }
+
     /// Get the available functions in the session context.
     pub fn get_available_functions(&self) -> PyResult<Vec<PyRemoteFunction>> {
         let registered_functions: Vec<PyRemoteFunction> = self
@@ -55,6 +55,12 @@ impl From<wren_core::DataFusionError> for CoreError {
     }
 }
 
+impl From<wren_core::error::ArrowError> for CoreError {
+    fn from(err: wren_core::error::ArrowError) -> Self {
+        CoreError::new(&format!("Arrow error: {}", err))
+    }
+}
+
 impl From<wren_core::parser::ParserError> for CoreError {
     fn from(err: wren_core::parser::ParserError) -> Self {
         CoreError::new(&format!("Parser error: {}", err))
@@ -4,7 +4,7 @@ use pyo3::{pyclass, pymethods};
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use wren_core::mdl::manifest::{Model, Relationship, View};
+use wren_core::mdl::manifest::{Column, Model, Relationship, View};
 use wren_core::mdl::WrenMDL;
 use wren_core_base::mdl::Manifest;
 
@@ -33,6 +33,23 @@ impl PyManifestExtractor {
         resolve_used_table_names(&self.mdl, sql)
     }
 
+    /// Return the metadata of the visible columns of the given models for grounding the
+    /// generated SQL, e.g. the description, the allowed values, the sample values and the
+    /// synonyms. The related models aren't included.
+    pub fn extract_column_metadata(&self, models: Vec<String>) -> Vec<PyColumnMetadata> {
+        models
+            .iter()
+            .filter_map(|name| self.mdl.get_model(name))
+            .flat_map(|model| {
+                model
+                    .get_physical_columns()
+                    .into_iter()
+                    .map(|column| PyColumnMetadata::new(model.name(), &column))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     /// Given a used dataset list, extract manifest by removing unused datasets.
     /// If a model is related to another dataset, both datasets will be kept.
     /// The relationship between of them will be kept as well.
@@ -42,6 +59,41 @@ impl PyManifestExtractor {
     }
 }
 
+/// The metadata of a column for grounding the generated SQL
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+#[pyo3(name = "ColumnMetadata")]
+pub struct PyColumnMetadata {
+    #[pyo3(get)]
+    pub model: String,
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get, name = "type")]
+    pub data_type: String,
+    #[pyo3(get)]
+    pub description: Option<String>,
+    #[pyo3(get)]
+    pub allowed_values: Vec<String>,
+    #[pyo3(get)]
+    pub sample_values: Vec<String>,
+    #[pyo3(get)]
+    pub synonyms: Vec<String>,
+}
+
+impl PyColumnMetadata {
+    fn new(model: &str, column: &Column) -> Self {
+        Self {
+            model: model.to_string(),
+            name: column.name.clone(),
+            data_type: column.r#type.clone(),
+            description: column.description.clone(),
+            allowed_values: column.allowed_values.clone(),
+            sample_values: column.sample_values.clone(),
+            synonyms: column.synonyms.clone(),
+        }
+    }
+}
+
 fn resolve_used_table_names(mdl: &WrenMDL, sql: &str) -> Result<Vec<String>, CoreError> {
     let ctx_state = wren_core::SessionContext::new().state();
     ctx_state
@@ -187,6 +239,13 @@ mod tests {
         let lineitem = ModelBuilder::new("lineitem")
             .table_reference("main.lineitem")
             .column(ColumnBuilder::new("l_orderkey", "integer").build())
+            .column(
+                ColumnBuilder::new("l_returnflag", "varchar")
+                    .description("whether the item is returned")
+                    .allowed_values(&["R", "A", "N"])
+                    .synonyms(&["return status"])
+                    .build(),
+            )
             .build();
         let c_o_relationship = RelationshipBuilder::new("customer_orders")
             .model("customer")
@@ -329,4 +388,28 @@ mod tests {
             expected_views
         );
     }
+
+    #[rstest]
+    fn test_extract_column_metadata(extractor: PyManifestExtractor) {
+        let metadata = extractor.extract_column_metadata(vec!["lineitem".to_string()]);
+        assert_eq!(metadata.len(), 2);
+        let returnflag = &metadata[1];
+        assert_eq!(returnflag.model, "lineitem");
+        assert_eq!(returnflag.name, "l_returnflag");
+        assert_eq!(
+            returnflag.description.as_deref(),
+            Some("whether the item is returned")
+        );
+        assert_eq!(returnflag.allowed_values, vec!["R", "A", "N"]);
+        assert_eq!(returnflag.synonyms, vec!["return status"]);
+        assert!(metadata[0].allowed_values.is_empty());
+
+        // the relationship columns and the unknown models are ignored
+        let metadata = extractor
+            .extract_column_metadata(vec!["orders".to_string(), "unknown".to_string()]);
+        assert_eq!(
+            metadata.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(),
+            vec!["o_orderkey", "o_custkey"]
+        );
+    }
 }
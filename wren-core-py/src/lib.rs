@@ -20,8 +20,11 @@ fn wren_core_wrapper(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<manifest::RowLevelAccessControl>()?;
     m.add_class::<manifest::SessionProperty>()?;
     m.add_class::<extractor::PyManifestExtractor>()?;
+    m.add_class::<extractor::PyColumnMetadata>()?;
+    m.add_class::<validation::PySchemaDrift>()?;
     m.add_function(wrap_pyfunction!(manifest::to_json_base64, m)?)?;
     m.add_function(wrap_pyfunction!(manifest::to_manifest, m)?)?;
     m.add_function(wrap_pyfunction!(validation::validate_rlac_rule, m)?)?;
+    m.add_function(wrap_pyfunction!(validation::detect_schema_drift, m)?)?;
     Ok(())
 }
@@ -45,6 +45,10 @@ mod tests {
                     cached: false,
                     refresh_time: None,
                     row_level_access_controls: vec![],
+                    hierarchies: vec![],
+                    description: None,
+                    synonyms: vec![],
+                    data_source: None,
                 }),
                 Arc::from(Model {
                     name: "model_2".to_string(),
@@ -56,6 +60,10 @@ mod tests {
                     cached: false,
                     refresh_time: None,
                     row_level_access_controls: vec![],
+                    hierarchies: vec![],
+                    description: None,
+                    synonyms: vec![],
+                    data_source: None,
                 }),
             ],
             relationships: vec![],
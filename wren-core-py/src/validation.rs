@@ -1,7 +1,14 @@
-use pyo3::pyfunction;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pyo3::types::{PyAny, PyAnyMethods};
+use pyo3::{pyclass, pyfunction, pymethods, Bound};
+use wren_core::ipc::convert::try_schema_from_ipc_buffer;
+use wren_core::mdl::drift::{detect_schema_drift_by_schemas, SchemaDrift};
 use wren_core_base::mdl::{Model, RowLevelAccessControl};
 
 use crate::errors::CoreError;
+use crate::manifest::to_manifest;
 
 #[pyfunction]
 pub fn validate_rlac_rule(
@@ -11,3 +18,99 @@ pub fn validate_rlac_rule(
     wren_core::logical_plan::analyze::access_control::validate_rlac_rule(rule, model)?;
     Ok(())
 }
+
+/// Compare the models of the manifest with the pyarrow schemas of the physical tables keyed
+/// by their table references.
+#[pyfunction]
+pub fn detect_schema_drift(
+    mdl_base64: &str,
+    schemas: HashMap<String, Bound<'_, PyAny>>,
+) -> Result<Vec<PySchemaDrift>, CoreError> {
+    let manifest = to_manifest(mdl_base64)?;
+    let schemas = schemas
+        .into_iter()
+        .map(|(name, schema)| {
+            // the schema is passed by the IPC format of arrow
+            let buffer = schema
+                .call_method0("serialize")?
+                .call_method0("to_pybytes")?
+                .extract::<Vec<u8>>()?;
+            Ok((name, Arc::new(try_schema_from_ipc_buffer(&buffer)?)))
+        })
+        .collect::<Result<HashMap<_, _>, CoreError>>()?;
+    Ok(detect_schema_drift_by_schemas(&manifest, &schemas)
+        .iter()
+        .map(PySchemaDrift::from)
+        .collect())
+}
+
+/// A model or a column drifted from the physical table
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+#[pyo3(name = "SchemaDrift")]
+pub struct PySchemaDrift {
+    /// One of `missing_table`, `type_mismatch`, `undeclared_column` and `missing_column`
+    #[pyo3(get)]
+    pub kind: String,
+    #[pyo3(get)]
+    pub model: String,
+    #[pyo3(get)]
+    pub column: Option<String>,
+    #[pyo3(get)]
+    pub declared_type: Option<String>,
+    #[pyo3(get)]
+    pub physical_type: Option<String>,
+    #[pyo3(get)]
+    pub message: String,
+}
+
+#[pymethods]
+impl PySchemaDrift {
+    fn __repr__(&self) -> String {
+        format!("SchemaDrift({})", self.message)
+    }
+}
+
+impl From<&SchemaDrift> for PySchemaDrift {
+    fn from(drift: &SchemaDrift) -> Self {
+        let (kind, model, column, declared_type, physical_type) = match drift {
+            SchemaDrift::MissingTable { model, .. } => {
+                ("missing_table", model, None, None, None)
+            }
+            SchemaDrift::TypeMismatch {
+                model,
+                column,
+                declared,
+                physical,
+            } => (
+                "type_mismatch",
+                model,
+                Some(column.clone()),
+                Some(declared.clone()),
+                Some(physical.to_string()),
+            ),
+            SchemaDrift::UndeclaredColumn {
+                model,
+                column,
+                physical,
+            } => (
+                "undeclared_column",
+                model,
+                Some(column.clone()),
+                None,
+                Some(physical.to_string()),
+            ),
+            SchemaDrift::MissingColumn { model, column } => {
+                ("missing_column", model, Some(column.clone()), None, None)
+            }
+        };
+        Self {
+            kind: kind.to_string(),
+            model: model.clone(),
+            column,
+            declared_type,
+            physical_type,
+            message: drift.to_string(),
+        }
+    }
+}
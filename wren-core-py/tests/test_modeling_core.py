@@ -2,12 +2,14 @@ import base64
 import json
 from contextlib import nullcontext as does_not_raise
 
+import pyarrow as pa
 import pytest
 from wren_core import (
     ManifestExtractor,
     RowLevelAccessControl,
     SessionContext,
     SessionProperty,
+    detect_schema_drift,
     to_json_base64,
     to_manifest,
     validate_rlac_rule,
@@ -83,7 +85,13 @@ manifest = {
             },
             "columns": [
                 {"name": "l_orderkey", "type": "integer"},
-                {"name": "l_quantity", "type": "decimal"},
+                {
+                    "name": "l_quantity",
+                    "type": "decimal",
+                    "description": "the number of items ordered",
+                    "sampleValues": ["1", "17", "36"],
+                    "synonyms": ["amount"],
+                },
                 {"name": "l_extendedprice", "type": "decimal"},
             ],
             "primaryKey": "l_orderkey",
@@ -132,6 +140,60 @@ def test_session_context():
     )
 
 
+def test_semantic_query():
+    session_context = SessionContext(manifest_str, None)
+    query = {
+        "dimensions": ["customer.c_name"],
+        "filters": [
+            {"member": "customer.c_custkey", "operator": "in", "values": [1, 2]}
+        ],
+        "order": [{"member": "customer.c_name", "direction": "desc"}],
+        "limit": 10,
+    }
+    rewritten_sql = session_context.transform_semantic_query(json.dumps(query))
+    assert "c_custkey IN (1, 2)" in rewritten_sql
+    assert "LIMIT 10" in rewritten_sql
+
+    with pytest.raises(Exception, match="Dimension customer.unknown not found"):
+        session_context.transform_semantic_query(
+            json.dumps({"dimensions": ["customer.unknown"]})
+        )
+
+
+def test_execute(tmp_path):
+    (tmp_path / "customer.csv").write_text("c_custkey,c_name\n1,Gura\n2,Azki\n3,Ina\n")
+    local_manifest = {
+        "catalog": "my_catalog",
+        "schema": "my_schema",
+        "dataSource": "local_file",
+        "models": [
+            {
+                "name": "customer",
+                "tableReference": {"table": "customer.csv"},
+                "columns": [
+                    {"name": "c_custkey", "type": "integer"},
+                    {"name": "c_name", "type": "varchar"},
+                ],
+                "primaryKey": "c_custkey",
+            },
+        ],
+    }
+    local_manifest_str = base64.b64encode(
+        json.dumps(local_manifest).encode("utf-8")
+    ).decode("utf-8")
+    properties = frozenset({"url": str(tmp_path)}.items())
+    session_context = SessionContext(local_manifest_str, None, properties)
+
+    table = session_context.execute(
+        "SELECT c_custkey, c_name FROM customer ORDER BY c_custkey", 2
+    )
+    assert table.column_names == ["c_custkey", "c_name"]
+    assert table.column("c_name").to_pylist() == ["Gura", "Azki"]
+
+    table = session_context.execute("SELECT count(*) AS total FROM customer")
+    assert table.column("total").to_pylist() == [3]
+
+
 def test_read_function_list():
     path = "tests/functions.csv"
     session_context = SessionContext(manifest_str, path)
@@ -262,6 +324,46 @@ def test_extract_by(dataset, expected_models):
     assert extracted_manifest.data_source.__str__() == "DataSource.BigQuery"
 
 
+def test_extract_column_metadata():
+    metadata = ManifestExtractor(manifest_str).extract_column_metadata(["lineitem"])
+    assert [c.name for c in metadata] == ["l_orderkey", "l_quantity", "l_extendedprice"]
+    quantity = metadata[1]
+    assert quantity.model == "lineitem"
+    assert quantity.type == "decimal"
+    assert quantity.description == "the number of items ordered"
+    assert quantity.allowed_values == []
+    assert quantity.sample_values == ["1", "17", "36"]
+    assert quantity.synonyms == ["amount"]
+
+
+def test_detect_schema_drift():
+    schemas = {
+        "main.customer": pa.schema(
+            [
+                ("c_custkey", pa.int32()),
+                ("c_name", pa.large_string()),
+                ("c_phone", pa.string()),
+            ]
+        ),
+        "main.orders": pa.schema(
+            [("o_orderkey", pa.int32()), ("o_custkey", pa.int64())]
+        ),
+    }
+    drifts = detect_schema_drift(manifest_str, schemas)
+    assert [(d.kind, d.model, d.column) for d in drifts] == [
+        ("undeclared_column", "customer", "c_phone"),
+        ("type_mismatch", "orders", "o_custkey"),
+        ("missing_column", "orders", "o_orderdate"),
+        ("missing_table", "lineitem", None),
+    ]
+    assert drifts[1].declared_type == "integer"
+    assert drifts[1].physical_type == "Int64"
+    assert (
+        drifts[1].message
+        == "orders.o_custkey: declared as integer but the physical type is Int64"
+    )
+
+
 def test_to_json_base64():
     extracted_manifest = ManifestExtractor(manifest_str).extract_by(["customer"])
     base64_str = to_json_base64(extracted_manifest)
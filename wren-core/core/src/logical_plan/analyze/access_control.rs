@@ -21,7 +21,7 @@ use datafusion::{
     },
 };
 use wren_core_base::mdl::RowLevelAccessControl;
-use wren_core_base::mdl::{Column, Model, SessionProperty};
+use wren_core_base::mdl::{Column, DataSource, Model, SessionProperty};
 
 use crate::mdl::{context::SessionPropertiesRef, Dataset, SessionStateRef};
 
@@ -109,10 +109,12 @@ pub fn validate_rlac_rule(rule: &RowLevelAccessControl, model: &Model) -> Result
     Ok(())
 }
 
-/// Build the filter expression for the row level access control rule.
+/// Build the filter expression for the row level access control rule. The types of the columns
+/// are mapped by the type names of the data source of the model.
 pub fn build_filter_expression(
     session_state: &SessionStateRef,
     model: Arc<Model>,
+    data_source: &DataSource,
     properties: &SessionPropertiesRef,
     rule: &RowLevelAccessControl,
 ) -> Result<Expr> {
@@ -186,7 +188,8 @@ pub fn build_filter_expression(
     if let Some(error) = error {
         return error;
     }
-    let df_schema = Dataset::Model(Arc::clone(&model)).to_qualified_schema()?;
+    let df_schema =
+        Dataset::Model(Arc::clone(&model)).to_qualified_schema(data_source)?;
     session_state
         .read()
         .create_logical_expr(&expr.to_string(), &df_schema)
@@ -330,7 +333,7 @@ mod test {
     };
     use insta::assert_snapshot;
     use wren_core_base::mdl::{
-        ColumnBuilder, ModelBuilder, RowLevelAccessControl, SessionProperty,
+        ColumnBuilder, DataSource, ModelBuilder, RowLevelAccessControl, SessionProperty,
     };
 
     use crate::logical_plan::analyze::access_control::{
@@ -570,7 +573,13 @@ mod test {
             name: "test".to_string(),
         };
 
-        let expr = build_filter_expression(&state, Arc::clone(&model), &headers, &rule)?;
+        let expr = build_filter_expression(
+            &state,
+            Arc::clone(&model),
+            &DataSource::default(),
+            &headers,
+            &rule,
+        )?;
         assert_snapshot!(expr_to_sql(&expr)?, @"m1.id = 1 AND m1.\"name\" = 'test'");
 
         let rule = RowLevelAccessControl {
@@ -582,7 +591,13 @@ mod test {
             name: "test".to_string(),
         };
 
-        match build_filter_expression(&state, Arc::clone(&model), &headers, &rule) {
+        match build_filter_expression(
+            &state,
+            Arc::clone(&model),
+            &DataSource::default(),
+            &headers,
+            &rule,
+        ) {
             Err(error) => {
                 assert_snapshot!(error.to_string(), @"Error during planning: The session property not_found is not found in the session properties");
             }
@@ -602,7 +617,13 @@ mod test {
             "session_id".to_string(),
             Some("1".to_string()),
         )]));
-        match build_filter_expression(&state, Arc::clone(&model), &headers, &rule) {
+        match build_filter_expression(
+            &state,
+            Arc::clone(&model),
+            &DataSource::default(),
+            &headers,
+            &rule,
+        ) {
             Err(error) => {
                 assert_snapshot!(error.to_string(), @"Error during planning: The session property session_name is not found in the session properties");
             }
@@ -623,7 +644,13 @@ mod test {
             Some("1".to_string()),
         )]));
 
-        let expr = build_filter_expression(&state, Arc::clone(&model), &headers, &rule)?;
+        let expr = build_filter_expression(
+            &state,
+            Arc::clone(&model),
+            &DataSource::default(),
+            &headers,
+            &rule,
+        )?;
         assert_snapshot!(expr_to_sql(&expr)?, @"m1.id = 1 AND m1.\"name\" = 'test'");
 
         Ok(())
@@ -657,7 +684,13 @@ mod test {
             name: "test".to_string(),
         };
 
-        let expr = build_filter_expression(&state, Arc::clone(&model), &headers, &rule)?;
+        let expr = build_filter_expression(
+            &state,
+            Arc::clone(&model),
+            &DataSource::default(),
+            &headers,
+            &rule,
+        )?;
         assert_snapshot!(expr_to_sql(&expr)?, @"m1.id = 1 AND m1.\"name\" = 'test'");
         Ok(())
     }
@@ -694,8 +727,13 @@ mod test {
                 &[("session_id".to_string(), Some(value.to_string()))],
             ));
 
-            let expr =
-                build_filter_expression(&state, Arc::clone(&model), &headers, &rule)?;
+            let expr = build_filter_expression(
+                &state,
+                Arc::clone(&model),
+                &DataSource::default(),
+                &headers,
+                &rule,
+            )?;
             expr_to_sql(&expr)?;
         }
 
@@ -718,7 +756,13 @@ mod test {
                 &[("session_id".to_string(), Some(value.to_string()))],
             ));
 
-            match build_filter_expression(&state, Arc::clone(&model), &headers, &rule) {
+            match build_filter_expression(
+                &state,
+                Arc::clone(&model),
+                &DataSource::default(),
+                &headers,
+                &rule,
+            ) {
                 Err(_) => {}
                 _ => panic!(
                     "should be error: {}",
@@ -5,4 +5,4 @@ pub mod model_generation;
 pub mod plan;
 mod relation_chain;
 
-pub use relation_chain::RelationChain;
+pub use relation_chain::{RelationChain, RELATION_ALIAS};
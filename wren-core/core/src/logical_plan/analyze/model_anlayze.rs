@@ -9,9 +9,9 @@ use datafusion::config::ConfigOptions;
 use datafusion::error::DataFusionError;
 use datafusion::logical_expr::expr::Alias;
 use datafusion::logical_expr::{
-    col, ident, Aggregate, Distinct, DistinctOn, Expr, Extension, Filter, Join,
-    LogicalPlan, LogicalPlanBuilder, Projection, Subquery, SubqueryAlias, TableScan,
-    Window,
+    col, ident, utils::split_conjunction, Aggregate, Distinct, DistinctOn, Expr,
+    Extension, Filter, Join, LogicalPlan, LogicalPlanBuilder, Projection, Subquery,
+    SubqueryAlias, TableScan, Window,
 };
 use datafusion::optimizer::AnalyzerRule;
 use datafusion::sql::TableReference;
@@ -183,6 +183,7 @@ impl ModelAnalyzeRule {
                 acuum.into_iter().try_for_each(|expr| {
                     self.collect_required_column(Expr::Column(expr), &mut scope_mut)
                 })?;
+                Self::collect_pushdown_filters(filter, &mut scope_mut);
                 Ok(Transformed::no(plan))
             }
             LogicalPlan::Aggregate(aggregate) => {
@@ -249,6 +250,102 @@ impl ModelAnalyzeRule {
         }
     }
 
+    /// Collect the conjunctions of the filter which only reference the physical columns of one model.
+    /// They can be pushed into the to-many calculations of the model.
+    ///
+    /// Only the filter evaluated before any calculation result is used (e.g. a window or an aggregation)
+    /// is collected. Otherwise, pushing it down could change the result. The input of an alias is
+    /// walked, because the alias of a derived table can be the model name.
+    fn collect_pushdown_filters(filter: &Filter, scope: &mut RefMut<Scope>) {
+        if !matches!(
+            filter.input.as_ref(),
+            LogicalPlan::TableScan(_)
+                | LogicalPlan::SubqueryAlias(_)
+                | LogicalPlan::Join(_)
+                | LogicalPlan::Filter(_)
+        ) {
+            return;
+        }
+        let computed = filter
+            .input
+            .exists(|plan| {
+                Ok(matches!(
+                    plan,
+                    LogicalPlan::Window(_)
+                        | LogicalPlan::Aggregate(_)
+                        | LogicalPlan::Limit(_)
+                        | LogicalPlan::Distinct(_)
+                ))
+            })
+            .unwrap_or(true);
+        if computed {
+            return;
+        }
+        for predicate in split_conjunction(&filter.predicate) {
+            let unsupported = predicate.is_volatile()
+                || predicate
+                    .exists(|e| {
+                        Ok(matches!(
+                            e,
+                            Expr::ScalarSubquery(_)
+                                | Expr::Exists(_)
+                                | Expr::InSubquery(_)
+                                | Expr::OuterReferenceColumn(_, _)
+                        ))
+                    })
+                    .unwrap_or(true);
+            if unsupported {
+                continue;
+            }
+            let columns = predicate.column_refs();
+            let mut relations = columns.iter().map(|c| c.relation.as_ref());
+            let Some(Some(relation)) = relations.next() else {
+                continue;
+            };
+            if relations.any(|r| r != Some(relation)) {
+                continue;
+            }
+            let Some(Dataset::Model(model)) = scope.visited_dataset.get(relation) else {
+                continue;
+            };
+            let is_physical = columns.iter().all(|c| {
+                model
+                    .get_column(&c.name)
+                    .map(|column| !column.is_calculated && column.relationship.is_none())
+                    .unwrap_or(false)
+            });
+            if is_physical {
+                scope.add_pushdown_filter(relation.clone(), predicate.clone());
+            }
+        }
+    }
+
+    /// Qualify the columns of the pushdown filters with the model name
+    fn pushdown_filters_for_model(
+        scope: &Scope,
+        table_ref: &TableReference,
+        model_name: &str,
+    ) -> Result<Vec<Expr>> {
+        scope
+            .get_pushdown_filters(table_ref)
+            .into_iter()
+            .map(|filter| {
+                filter
+                    .transform_up(|e| match e {
+                        Expr::Column(c) => Ok(Transformed::yes(Expr::Column(
+                            Column::from_qualified_name(format!(
+                                "{}.{}",
+                                quoted(model_name),
+                                quoted(&c.name)
+                            )),
+                        ))),
+                        _ => Ok(Transformed::no(e)),
+                    })
+                    .data()
+            })
+            .collect()
+    }
+
     /// This function only collects the model required columns
     fn collect_required_column(
         &self,
@@ -446,11 +543,14 @@ impl ModelAnalyzeRule {
                     };
                     vec![]
                 };
+                let pushdown_filters =
+                    Self::pushdown_filters_for_model(&scope, &table_ref, model.name())?;
                 let model_plan = LogicalPlan::Extension(Extension {
                     node: Arc::new(ModelPlanNode::new(
                         Arc::clone(&model),
                         field,
                         Some(LogicalPlan::TableScan(table_scan.clone())),
+                        pushdown_filters,
                         Arc::clone(&self.analyzed_wren_mdl),
                         Arc::clone(&self.session_state),
                         Arc::clone(&self.properties),
@@ -503,11 +603,14 @@ impl ModelAnalyzeRule {
                         };
                         vec![]
                     };
+                    let pushdown_filters =
+                        Self::pushdown_filters_for_model(&scope, &alias, model.name())?;
                     let model_plan = LogicalPlan::Extension(Extension {
                         node: Arc::new(ModelPlanNode::new(
                             Arc::clone(&model),
                             field,
                             None,
+                            pushdown_filters,
                             Arc::clone(&self.analyzed_wren_mdl),
                             Arc::clone(&self.session_state),
                             Arc::clone(&self.properties),
@@ -785,6 +888,10 @@ pub struct Scope {
     visited_dataset: HashMap<TableReference, Dataset>,
     /// The table name visited in the scope (not only the Wren dataset)
     visited_tables: HashSet<TableReference>,
+    /// The filters that only reference the columns of the dataset
+    pushdown_filters: HashMap<TableReference, Vec<Expr>>,
+    /// The dataset visited more than once in the scope (e.g. the both sides of an union)
+    ambiguous_datasets: HashSet<TableReference>,
     /// The parent scope
     parent: Option<Box<RefCell<Scope>>>,
     childs: VecDeque<RefCell<Scope>>,
@@ -796,6 +903,8 @@ impl Scope {
             required_columns: HashMap::new(),
             visited_dataset: HashMap::new(),
             visited_tables: HashSet::new(),
+            pushdown_filters: HashMap::new(),
+            ambiguous_datasets: HashSet::new(),
             parent: None,
             childs: VecDeque::new(),
         }
@@ -806,6 +915,8 @@ impl Scope {
             required_columns: HashMap::new(),
             visited_dataset: HashMap::new(),
             visited_tables: HashSet::new(),
+            pushdown_filters: HashMap::new(),
+            ambiguous_datasets: HashSet::new(),
             parent: Some(Box::new(parent)),
             childs: VecDeque::new(),
         }
@@ -854,13 +965,38 @@ impl Scope {
     }
 
     pub fn add_visited_dataset(&mut self, table_ref: TableReference, dataset: Dataset) {
-        self.visited_dataset.insert(table_ref, dataset);
+        if self
+            .visited_dataset
+            .insert(table_ref.clone(), dataset)
+            .is_some()
+        {
+            self.ambiguous_datasets.insert(table_ref);
+        }
     }
 
     pub fn add_visited_table(&mut self, table_ref: TableReference) {
         self.visited_tables.insert(table_ref);
     }
 
+    pub fn add_pushdown_filter(&mut self, table_ref: TableReference, filter: Expr) {
+        self.pushdown_filters
+            .entry(table_ref)
+            .or_default()
+            .push(filter);
+    }
+
+    /// Get the pushdown filters of the dataset. Only the filters in the current scope are returned.
+    /// If the dataset is visited more than once, the filters can't be identified and nothing is returned.
+    pub fn get_pushdown_filters(&self, table_ref: &TableReference) -> Vec<Expr> {
+        if self.ambiguous_datasets.contains(table_ref) {
+            return vec![];
+        }
+        self.pushdown_filters
+            .get(table_ref)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     pub fn try_get_required_columns(
         &self,
         table_ref: &TableReference,
@@ -16,6 +16,7 @@ use datafusion::common::alias::AliasGenerator;
 use datafusion::common::config::ConfigOptions;
 use datafusion::common::tree_node::{Transformed, TransformedResult};
 use datafusion::common::{plan_err, Result};
+use datafusion::logical_expr::utils::conjunction;
 use datafusion::logical_expr::{col, ident, Extension, UserDefinedLogicalNodeCore};
 use datafusion::logical_expr::{Expr, LogicalPlan, LogicalPlanBuilder};
 use datafusion::optimizer::analyzer::AnalyzerRule;
@@ -175,10 +176,13 @@ impl ModelGenerationRule {
                     if model_plan.required_exprs.is_empty() {
                         return Ok(Transformed::no(table_scan));
                     }
-                    let result = LogicalPlanBuilder::from(table_scan)
-                        .alias(quoted(model.name()))?
-                        .build()?;
-                    Ok(Transformed::yes(result))
+                    let mut builder = LogicalPlanBuilder::from(table_scan)
+                        .alias(quoted(model.name()))?;
+                    // apply the filters pushed down from the outer query
+                    if let Some(filter) = conjunction(model_plan.filters.clone()) {
+                        builder = builder.filter(filter)?;
+                    }
+                    Ok(Transformed::yes(builder.build()?))
                 } else if let Some(calculation_plan) = extension
                     .node
                     .as_any()
@@ -267,9 +271,11 @@ impl ModelGenerationRule {
         rule: &RowLevelAccessControl,
     ) -> Result<Option<Expr>> {
         if validate_rule(&rule.required_properties, &self.properties)? {
+            let data_source = self.analyzed_wren_mdl.wren_mdl().model_type_source(&model);
             let filter = build_filter_expression(
                 &self.session_state,
                 model,
+                &data_source,
                 &self.properties,
                 rule,
             )?;
@@ -21,7 +21,7 @@ use petgraph::Graph;
 
 use crate::logical_plan::analyze::RelationChain;
 use crate::logical_plan::analyze::RelationChain::Start;
-use crate::logical_plan::utils::{from_qualified_name, try_map_data_type};
+use crate::logical_plan::utils::{from_qualified_name, try_map_data_type_by_data_source};
 use crate::mdl;
 use crate::mdl::context::SessionPropertiesRef;
 use crate::mdl::lineage::DatasetLink;
@@ -71,15 +71,14 @@ impl ModelPlanNode {
         model: Arc<Model>,
         required_fields: Vec<Expr>,
         original_table_scan: Option<LogicalPlan>,
+        pushdown_filters: Vec<Expr>,
         analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
         session_state: SessionStateRef,
         properties: SessionPropertiesRef,
     ) -> Result<Self> {
-        ModelPlanNodeBuilder::new(analyzed_wren_mdl, session_state, properties).build(
-            model,
-            required_fields,
-            original_table_scan,
-        )
+        ModelPlanNodeBuilder::new(analyzed_wren_mdl, session_state, properties)
+            .with_pushdown_filters(pushdown_filters)
+            .build(model, required_fields, original_table_scan)
     }
 
     pub fn plan_name(&self) -> &str {
@@ -93,6 +92,7 @@ impl ModelPlanNode {
 /// original_table_scan: The original table scan plan for the target model.
 /// model_required_fields: The required fields for the source models.
 /// required_calculation: The required calculation plan for the target model.
+/// pushdown_filters: The filters on the target model which can be pushed into the calculation.
 /// fields: The fields for the target model to build the schema of this plan.
 /// analyzed_wren_mdl: The analyzed Wren MDL.
 struct ModelPlanNodeBuilder {
@@ -100,6 +100,7 @@ struct ModelPlanNodeBuilder {
     directed_graph: Graph<Dataset, DatasetLink>,
     model_required_fields: HashMap<TableReference, BTreeSet<OrdExpr>>,
     required_calculation: Vec<WrenPlan>,
+    pushdown_filters: Vec<Expr>,
     fields: VecDeque<(Option<TableReference>, Arc<Field>)>,
     analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
     session_state: SessionStateRef,
@@ -117,6 +118,7 @@ impl ModelPlanNodeBuilder {
             directed_graph: Graph::new(),
             model_required_fields: HashMap::new(),
             required_calculation: vec![],
+            pushdown_filters: vec![],
             fields: VecDeque::new(),
             analyzed_wren_mdl,
             session_state,
@@ -124,6 +126,14 @@ impl ModelPlanNodeBuilder {
         }
     }
 
+    /// The filters should only reference the physical columns of the target model.
+    /// They are applied to the source of the to-many calculations, so the aggregation
+    /// only runs over the rows required by the outer query.
+    fn with_pushdown_filters(mut self, pushdown_filters: Vec<Expr>) -> Self {
+        self.pushdown_filters = pushdown_filters;
+        self
+    }
+
     fn build(
         &mut self,
         model: Arc<Model>,
@@ -145,6 +155,7 @@ impl ModelPlanNodeBuilder {
                     .iter()
                     .any(|expr| is_required_column(expr, column.name()))
             });
+        let data_source = self.analyzed_wren_mdl.wren_mdl().model_type_source(&model);
         for column in required_columns {
             if column.is_calculated {
                 let expr = if column.expression.is_some() {
@@ -246,7 +257,7 @@ impl ModelPlanNodeBuilder {
                 Some(TableReference::bare(quoted(model.name()))),
                 Arc::new(Field::new(
                     column.name(),
-                    try_map_data_type(&column.r#type)?,
+                    try_map_data_type_by_data_source(&column.r#type, &data_source)?,
                     column.not_null,
                 )),
             ));
@@ -320,6 +331,7 @@ impl ModelPlanNodeBuilder {
                 RelationChain::source(
                     source,
                     source_required_fields,
+                    vec![],
                     Arc::clone(&self.analyzed_wren_mdl),
                     Arc::clone(&self.session_state),
                 )?
@@ -464,15 +476,27 @@ impl ModelPlanNodeBuilder {
         let mut iter = column_graph.node_indices();
 
         let start = iter.next().unwrap();
-        let source_required_fields = partial_model_required_fields
+        let mut source_required_fields: Vec<Expr> = partial_model_required_fields
             .get(&model_ref)
             .map(|c| c.iter().cloned().map(|c| c.expr).collect())
             .unwrap_or_default();
         let source = column_graph.node_weight(start).unwrap();
 
+        // push the filters of the outer query into the source of the calculation
+        let pushdown_filters = if source.name() == model_ref.table() {
+            self.pushdown_filters.clone()
+        } else {
+            vec![]
+        };
+        for filter in pushdown_filters.iter() {
+            source_required_fields
+                .extend(filter.column_refs().into_iter().cloned().map(Expr::Column));
+        }
+
         let source_chain = RelationChain::source(
             source,
             source_required_fields,
+            pushdown_filters,
             Arc::clone(&self.analyzed_wren_mdl),
             Arc::clone(&self.session_state),
         )?;
@@ -498,6 +522,7 @@ impl ModelPlanNodeBuilder {
             column_rf,
             col_expr,
             partial_chain,
+            Arc::clone(&self.analyzed_wren_mdl),
             Arc::clone(&self.session_state),
         )?)))
     }
@@ -540,9 +565,12 @@ fn collect_partial_model_plan_for_calculation(
         };
 
         if column.is_calculated {
+            let model = dataset.try_as_model().unwrap();
+            let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&model);
             let expr = create_wren_expr_for_model(
                 &c.name,
-                dataset.try_as_model().unwrap(),
+                model,
+                &data_source,
                 Arc::clone(&session_state_ref),
             )?;
             required_fields
@@ -583,11 +611,14 @@ fn collect_partial_model_required_fields(
             return plan_err!("Column reference not found for {}", c);
         };
         if !column.is_calculated {
+            let model = dataset.try_as_model().ok_or_else(|| {
+                internal_datafusion_err!("Only support model as source dataset")
+            })?;
+            let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&model);
             let expr = create_wren_expr_for_model(
                 &c.name,
-                dataset.try_as_model().ok_or_else(|| {
-                    internal_datafusion_err!("Only support model as source dataset")
-                })?,
+                model,
+                &data_source,
                 Arc::clone(&session_state_ref),
             )?;
             required_fields
@@ -627,10 +658,12 @@ fn collect_model_required_fields(
         };
         if let Dataset::Model(m) = dataset {
             if column.is_calculated {
+                let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&m);
                 let expr_plan = if let Some(expression) = &column.expression {
                     let Ok(expr) = create_wren_expr_for_model(
                         expression,
                         Arc::clone(&m),
+                        &data_source,
                         Arc::clone(&session_state_ref),
                     ) else {
                         // skip the semantic expression (e.g. calculated field or relationship column)
@@ -815,6 +848,8 @@ pub struct ModelSourceNode {
     pub required_exprs: Vec<Expr>,
     pub schema_ref: DFSchemaRef,
     pub original_table_scan: Option<LogicalPlan>,
+    /// The filters applied to the model source. They only reference the columns of the model.
+    pub filters: Vec<Expr>,
 }
 
 impl ModelSourceNode {
@@ -824,6 +859,7 @@ impl ModelSourceNode {
         analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
         session_state_ref: SessionStateRef,
         original_table_scan: Option<LogicalPlan>,
+        filters: Vec<Expr>,
     ) -> Result<Self> {
         let mut required_exprs_buffer = BTreeSet::new();
         let mut fields_buffer = BTreeSet::new();
@@ -841,6 +877,7 @@ impl ModelSourceNode {
                 } else {
                     Arc::clone(&model)
                 };
+                let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&model);
                 for column in model.get_physical_columns().into_iter() {
                     // skip the calculated field
                     if column.is_calculated {
@@ -850,7 +887,10 @@ impl ModelSourceNode {
                         Some(TableReference::bare(quoted(model.name()))),
                         Arc::new(Field::new(
                             column.name(),
-                            try_map_data_type(&column.r#type)?,
+                            try_map_data_type_by_data_source(
+                                &column.r#type,
+                                &data_source,
+                            )?,
                             column.not_null,
                         )),
                     ));
@@ -890,7 +930,10 @@ impl ModelSourceNode {
                     Some(TableReference::bare(quoted(model.name()))),
                     Arc::new(Field::new(
                         column.name(),
-                        try_map_data_type(&column.r#type)?,
+                        try_map_data_type_by_data_source(
+                            &column.r#type,
+                            &analyzed_wren_mdl.wren_mdl().model_type_source(&model),
+                        )?,
                         column.not_null,
                     )),
                 ));
@@ -911,6 +954,7 @@ impl ModelSourceNode {
             required_exprs,
             schema_ref,
             original_table_scan,
+            filters,
         })
     }
 }
@@ -952,6 +996,7 @@ impl UserDefinedLogicalNodeCore for ModelSourceNode {
             required_exprs: self.required_exprs.clone(),
             schema_ref: self.schema_ref.clone(),
             original_table_scan: self.original_table_scan.clone(),
+            filters: self.filters.clone(),
         })
     }
 }
@@ -970,11 +1015,13 @@ impl CalculationPlanNode {
         calculation: ColumnReference,
         calculation_expr: Expr,
         relation_chain: RelationChain,
+        analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
         session_state_ref: SessionStateRef,
     ) -> Result<Self> {
         let Some(model) = calculation.dataset.try_as_model() else {
             return plan_err!("Only support model as source dataset");
         };
+        let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&model);
         let Some(pk_column) = model.primary_key().and_then(|pk| model.get_column(pk))
         else {
             return plan_err!("Primary key not found");
@@ -984,12 +1031,15 @@ impl CalculationPlanNode {
         let output_field = vec![
             Arc::new(Field::new(
                 calculation.column.name(),
-                try_map_data_type(&calculation.column.r#type)?,
+                try_map_data_type_by_data_source(
+                    &calculation.column.r#type,
+                    &data_source,
+                )?,
                 calculation.column.not_null,
             )),
             Arc::new(Field::new(
                 pk_column.name(),
-                try_map_data_type(&pk_column.r#type)?,
+                try_map_data_type_by_data_source(&pk_column.r#type, &data_source)?,
                 pk_column.not_null,
             )),
         ]
@@ -999,6 +1049,7 @@ impl CalculationPlanNode {
         let dimensions = vec![create_wren_expr_for_model(
             &pk_column.name,
             Arc::clone(&model),
+            &data_source,
             Arc::clone(&session_state_ref),
         )?
         .alias(pk_column.name())];
@@ -27,7 +27,7 @@ use petgraph::Graph;
 use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
-const ALIAS: &str = "__relation_";
+pub const RELATION_ALIAS: &str = "__relation_";
 
 /// RelationChain is a chain of models that are connected by the relationship.
 /// The chain is used to generate the join plan for the model.
@@ -43,6 +43,7 @@ impl RelationChain {
     pub(crate) fn source(
         dataset: &Dataset,
         required_fields: Vec<Expr>,
+        filters: Vec<Expr>,
         analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
         session_state_ref: SessionStateRef,
     ) -> Result<Self> {
@@ -55,6 +56,7 @@ impl RelationChain {
                         analyzed_wren_mdl,
                         session_state_ref,
                         None,
+                        filters,
                     )?),
                 })))
             }
@@ -93,6 +95,8 @@ impl RelationChain {
             };
             match target {
                 Dataset::Model(target_model) => {
+                    let data_source =
+                        analyzed_wren_mdl.wren_mdl().model_type_source(target_model);
                     let schema = create_schema(
                         fields
                             .iter()
@@ -105,12 +109,14 @@ impl RelationChain {
                                 })
                             })
                             .collect::<Result<_>>()?,
+                        &data_source,
                     )?;
                     let exprs = fields.iter().cloned().map(|c| c.expr).collect();
                     let plan = ModelPlanNode::new(
                         Arc::clone(target_model),
                         exprs,
                         None,
+                        vec![],
                         Arc::clone(&analyzed_wren_mdl),
                         Arc::clone(&session_state_ref),
                         Arc::clone(&properties),
@@ -285,7 +291,7 @@ impl RelationChain {
                     .map(|expr| expr.expr.clone())
                     .collect();
                 let required_field = eliminate_ambiguous_columns(required_field);
-                let alias = alias_generator.next(ALIAS);
+                let alias = alias_generator.next(RELATION_ALIAS);
                 Ok((
                     Some(
                         LogicalPlanBuilder::from(left)
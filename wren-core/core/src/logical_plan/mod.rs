@@ -1,3 +1,5 @@
 pub mod analyze;
 pub mod optimize;
+mod type_mapping;
+mod type_parser;
 pub mod utils;
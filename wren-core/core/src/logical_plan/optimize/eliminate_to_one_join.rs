@@ -0,0 +1,202 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::logical_plan::analyze::RELATION_ALIAS;
+use crate::mdl::context::WrenConfig;
+use crate::mdl::manifest::JoinType;
+use crate::mdl::utils::collect_identifiers;
+use crate::mdl::AnalyzedWrenMDL;
+use datafusion::common::tree_node::{Transformed, TreeNode};
+use datafusion::common::{Column, Result};
+use datafusion::logical_expr::expr_rewriter::NamePreserver;
+use datafusion::logical_expr::{
+    BinaryExpr, Expr, Join, LogicalPlan, Operator, Projection, SubqueryAlias,
+};
+use datafusion::optimizer::{ApplyOrder, OptimizerConfig, OptimizerRule};
+
+/// Eliminate the join of a relation chain if the joined model is a to-one side and
+/// none of its columns is required except the join key.
+///
+/// The relation chain joins the related model with a RIGHT join. If the condition uses the
+/// primary key of the related model and the relationship is to-one, the join never changes
+/// the number of the rows. The join key of the related model is then satisfied by the foreign
+/// key of the other side, so the join can be removed.
+///
+/// It assumes the referential integrity of the relationship: every foreign key refers to an
+/// existing row of the related model. Otherwise, the removed join would have made the key null.
+/// The rule is only applied if [WrenConfig::eliminate_to_one_join] is enabled.
+pub struct EliminateToOneJoin {
+    analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
+}
+
+impl EliminateToOneJoin {
+    pub fn new(analyzed_wren_mdl: Arc<AnalyzedWrenMDL>) -> Self {
+        Self { analyzed_wren_mdl }
+    }
+
+    /// Return the join key of the related model and the foreign key of the other side
+    /// if the join can be eliminated.
+    fn eliminable_join_keys(&self, join: &Join) -> Option<(Column, Column)> {
+        if join.join_type != datafusion::logical_expr::JoinType::Right {
+            return None;
+        }
+        let LogicalPlan::SubqueryAlias(SubqueryAlias { alias, .. }) = join.left.as_ref()
+        else {
+            return None;
+        };
+        // The filter of the related model (e.g. row-level access control) could make the
+        // joined columns null, so the join can't be eliminated.
+        let has_filter = join
+            .left
+            .exists(|plan| Ok(matches!(plan, LogicalPlan::Filter(_))))
+            .unwrap_or(true);
+        if has_filter {
+            return None;
+        }
+        let (left_key, right_key) = match (join.on.as_slice(), &join.filter) {
+            ([(Expr::Column(l), Expr::Column(r))], None) => (l.clone(), r.clone()),
+            (
+                [],
+                Some(Expr::BinaryExpr(BinaryExpr {
+                    left,
+                    op: Operator::Eq,
+                    right,
+                })),
+            ) => match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(l), Expr::Column(r))
+                    if join.left.schema().has_column(l) =>
+                {
+                    (l.clone(), r.clone())
+                }
+                (Expr::Column(r), Expr::Column(l))
+                    if join.left.schema().has_column(l) =>
+                {
+                    (l.clone(), r.clone())
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if !join.left.schema().has_column(&left_key)
+            || !join.right.schema().has_column(&right_key)
+        {
+            return None;
+        }
+
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        let model = wren_mdl.get_model(alias.table())?;
+        if model.primary_key() != Some(left_key.name.as_str()) {
+            return None;
+        }
+        let is_to_one = wren_mdl.relationships().iter().any(|rs| {
+            let Some(index) = rs.models.iter().position(|m| m == model.name()) else {
+                return false;
+            };
+            let Ok(identifiers) = collect_identifiers(&rs.condition) else {
+                return false;
+            };
+            // The identifiers are unqualified columns named like `model.column`
+            let identifiers = identifiers
+                .iter()
+                .filter_map(|c| c.name.rsplit_once('.'))
+                .collect::<Vec<_>>();
+            let contains_key = identifiers.iter().any(|(relation, name)| {
+                *relation == model.name() && *name == left_key.name
+            });
+            let contains_foreign_key = identifiers.iter().any(|(relation, name)| {
+                *relation != model.name() && *name == right_key.name
+            });
+            // The join type is declared from the first model to the second model.
+            let join_type = if index == 1 {
+                rs.join_type
+            } else {
+                match rs.join_type {
+                    JoinType::OneToMany => JoinType::ManyToOne,
+                    JoinType::ManyToOne => JoinType::OneToMany,
+                    _ => rs.join_type,
+                }
+            };
+            contains_key && contains_foreign_key && join_type.is_to_one()
+        });
+        is_to_one.then_some((left_key, right_key))
+    }
+}
+
+impl Debug for EliminateToOneJoin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EliminateToOneJoin").finish()
+    }
+}
+
+impl OptimizerRule for EliminateToOneJoin {
+    fn name(&self) -> &str {
+        "eliminate_to_one_join"
+    }
+
+    fn apply_order(&self) -> Option<ApplyOrder> {
+        Some(ApplyOrder::BottomUp)
+    }
+
+    fn rewrite(
+        &self,
+        plan: LogicalPlan,
+        config: &dyn OptimizerConfig,
+    ) -> Result<Transformed<LogicalPlan>> {
+        let enabled = config
+            .options()
+            .extensions
+            .get::<WrenConfig>()
+            .is_some_and(|config| config.eliminate_to_one_join);
+        if !enabled {
+            return Ok(Transformed::no(plan));
+        }
+        let LogicalPlan::SubqueryAlias(subquery_alias) = &plan else {
+            return Ok(Transformed::no(plan));
+        };
+        if !subquery_alias.alias.table().starts_with(RELATION_ALIAS) {
+            return Ok(Transformed::no(plan));
+        }
+        let LogicalPlan::Projection(projection) = subquery_alias.input.as_ref() else {
+            return Ok(Transformed::no(plan));
+        };
+        let LogicalPlan::Join(join) = projection.input.as_ref() else {
+            return Ok(Transformed::no(plan));
+        };
+        let Some((left_key, right_key)) = self.eliminable_join_keys(join) else {
+            return Ok(Transformed::no(plan));
+        };
+        // Only the join key of the related model can be required.
+        let only_key_required = projection.expr.iter().all(|expr| {
+            expr.column_refs()
+                .into_iter()
+                .all(|c| c == &left_key || !join.left.schema().has_column(c))
+        });
+        if !only_key_required {
+            return Ok(Transformed::no(plan));
+        }
+
+        let name_preserver = NamePreserver::new(subquery_alias.input.as_ref());
+        let exprs = projection
+            .expr
+            .iter()
+            .map(|expr| {
+                let saved_name = name_preserver.save(expr);
+                let rewritten = expr
+                    .clone()
+                    .transform_up(|e| match e {
+                        Expr::Column(c) if c == left_key => {
+                            Ok(Transformed::yes(Expr::Column(right_key.clone())))
+                        }
+                        _ => Ok(Transformed::no(e)),
+                    })?
+                    .data;
+                Ok(saved_name.restore(rewritten))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let projection = Projection::try_new(exprs, Arc::clone(&join.right))
+            .map(LogicalPlan::Projection)?;
+        Ok(Transformed::yes(LogicalPlan::SubqueryAlias(
+            SubqueryAlias::try_new(Arc::new(projection), subquery_alias.alias.clone())?,
+        )))
+    }
+}
@@ -17,4 +17,5 @@
  * under the License.
  */
 
+pub mod eliminate_to_one_join;
 pub mod simplify_timestamp;
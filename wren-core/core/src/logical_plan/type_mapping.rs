@@ -0,0 +1,294 @@
+//! The type names specific to the data sources.
+//!
+//! The same type name may mean different types in different warehouses, e.g. `Int8` of
+//! ClickHouse is a 8-bit integer but `int8` of Postgres is a 64-bit integer, and some types only
+//! exist in one warehouse, e.g. `VARCHAR2` of Oracle. The rules of the data source are applied
+//! before the generic type names.
+
+use datafusion::arrow::datatypes::DataType;
+
+use crate::mdl::manifest::DataSource;
+
+/// How a type name of the data source is mapped
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TypeRule {
+    /// Parse it as the generic type name with the same arguments, e.g. Oracle `NUMBER(10, 2)`
+    /// is parsed as `decimal(10, 2)`.
+    Alias(&'static str),
+    /// Map it to the type. The arguments are ignored.
+    Fixed(DataType),
+    /// The decimal of the fixed precision whose only argument is the scale, e.g. ClickHouse
+    /// `Decimal32(2)` is `decimal(9, 2)`.
+    ScaledDecimal(u8),
+    /// The decimal whose precision and scale default to the values if both are omitted, e.g.
+    /// BigQuery `NUMERIC` is `decimal(38, 9)`.
+    DefaultDecimal(u8, u8),
+}
+
+/// Return the rule of the lowercase type name for the data source. If the data source doesn't
+/// have a rule for it, the generic type names are used.
+pub(crate) fn type_rule(data_source: &DataSource, name: &str) -> Option<TypeRule> {
+    match data_source {
+        DataSource::BigQuery => bigquery_type_rule(name),
+        DataSource::Clickhouse => clickhouse_type_rule(name),
+        DataSource::Trino | DataSource::Athena => trino_type_rule(name),
+        DataSource::MSSQL => mssql_type_rule(name),
+        DataSource::MySQL => mysql_type_rule(name),
+        DataSource::Postgres => postgres_type_rule(name),
+        DataSource::Snowflake => snowflake_type_rule(name),
+        DataSource::DuckDB => duckdb_type_rule(name),
+        DataSource::Oracle => oracle_type_rule(name),
+        _ => None,
+    }
+}
+
+fn bigquery_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        // all the integer types of BigQuery are INT64
+        "int" | "integer" | "smallint" | "tinyint" | "byteint" => {
+            TypeRule::Alias("bigint")
+        }
+        // the float of BigQuery is FLOAT64
+        "float" => TypeRule::Alias("double"),
+        "numeric" | "decimal" => TypeRule::DefaultDecimal(38, 9),
+        "bignumeric" | "bigdecimal" => TypeRule::DefaultDecimal(76, 38),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn clickhouse_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        // the number is the bits of the integer
+        "int8" => TypeRule::Alias("tinyint"),
+        "int128" | "int256" => TypeRule::Alias("hugeint"),
+        "uint128" | "uint256" => TypeRule::Alias("uhugeint"),
+        "decimal32" => TypeRule::ScaledDecimal(9),
+        "decimal64" => TypeRule::ScaledDecimal(18),
+        "decimal128" => TypeRule::ScaledDecimal(38),
+        "decimal256" => TypeRule::ScaledDecimal(76),
+        "ipv4" | "ipv6" => TypeRule::Alias("inet"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn trino_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        "ipaddress" => TypeRule::Alias("inet"),
+        // the sketches are serialized as binary
+        "hyperloglog" | "p4hyperloglog" | "qdigest" | "tdigest" => {
+            TypeRule::Alias("varbinary")
+        }
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn mssql_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        // the tinyint of MSSQL is unsigned
+        "tinyint" => TypeRule::Alias("utinyint"),
+        // the default precision of float is 53
+        "float" => TypeRule::Alias("double"),
+        "datetime2" | "smalldatetime" => TypeRule::Alias("timestamp"),
+        "datetimeoffset" => TypeRule::Alias("timestamptz"),
+        "money" => TypeRule::Fixed(DataType::Decimal128(19, 4)),
+        "smallmoney" => TypeRule::Fixed(DataType::Decimal128(10, 4)),
+        "ntext" => TypeRule::Alias("text"),
+        "uniqueidentifier" => TypeRule::Alias("uuid"),
+        "image" => TypeRule::Alias("varbinary"),
+        "sql_variant" => TypeRule::Alias("variant"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn mysql_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        "mediumint" => TypeRule::Alias("int"),
+        "year" => TypeRule::Fixed(DataType::Int16),
+        "tinytext" | "mediumtext" | "longtext" => TypeRule::Alias("text"),
+        "tinyblob" | "mediumblob" | "longblob" => TypeRule::Alias("blob"),
+        // the values of enum and set are listed in the arguments
+        "enum" | "set" => TypeRule::Alias("varchar"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn postgres_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        "smallserial" | "serial2" => TypeRule::Alias("smallint"),
+        "serial" | "serial4" => TypeRule::Alias("integer"),
+        "bigserial" | "serial8" => TypeRule::Alias("bigint"),
+        "money" => TypeRule::Fixed(DataType::Decimal128(19, 2)),
+        "timetz" => TypeRule::Alias("timestamptz"),
+        "citext" => TypeRule::Alias("text"),
+        "cidr" | "macaddr" | "macaddr8" => TypeRule::Alias("inet"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn snowflake_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        // the default scale of Snowflake NUMBER is 0
+        "number" => TypeRule::DefaultDecimal(38, 0),
+        // all the floating-point types of Snowflake are 64-bit
+        "float" | "float4" | "real" => TypeRule::Alias("double"),
+        "timestamp_ntz" => TypeRule::Alias("timestamp"),
+        "timestamp_ltz" | "timestamp_tz" => TypeRule::Alias("timestamptz"),
+        "object" => TypeRule::Alias("json"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn duckdb_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        "int1" => TypeRule::Alias("tinyint"),
+        "short" => TypeRule::Alias("smallint"),
+        "signed" => TypeRule::Alias("integer"),
+        "long" => TypeRule::Alias("bigint"),
+        "timestamp_s" | "timestamp_ms" | "timestamp_us" => TypeRule::Alias("timestamp"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+fn oracle_type_rule(name: &str) -> Option<TypeRule> {
+    let rule = match name {
+        "number" => TypeRule::Alias("decimal"),
+        "float" | "binary_double" => TypeRule::Alias("double"),
+        "binary_float" => TypeRule::Alias("real"),
+        "varchar2" | "nvarchar2" | "clob" | "nclob" | "long" | "rowid" | "urowid" => {
+            TypeRule::Alias("varchar")
+        }
+        // the date of Oracle has the time part
+        "date" => TypeRule::Alias("timestamp"),
+        "raw" => TypeRule::Alias("varbinary"),
+        "xmltype" => TypeRule::Alias("xml"),
+        _ => return None,
+    };
+    Some(rule)
+}
+
+#[cfg(test)]
+mod test {
+    use datafusion::arrow::datatypes::{DataType, TimeUnit};
+    use datafusion::common::Result;
+
+    use crate::logical_plan::type_parser::{list_type, parse_data_type_by_data_source};
+    use crate::mdl::manifest::DataSource;
+
+    #[test]
+    fn test_data_source_types() -> Result<()> {
+        let test_cases = vec![
+            (
+                DataSource::Oracle,
+                "NUMBER(10, 2)",
+                DataType::Decimal128(10, 2),
+            ),
+            (DataSource::Oracle, "VARCHAR2(255)", DataType::Utf8),
+            (
+                DataSource::Oracle,
+                "DATE",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+            ),
+            (DataSource::MSSQL, "NVARCHAR(MAX)", DataType::Utf8),
+            (DataSource::MSSQL, "tinyint", DataType::UInt8),
+            (DataSource::MSSQL, "money", DataType::Decimal128(19, 4)),
+            (
+                DataSource::MSSQL,
+                "datetimeoffset(7)",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            ),
+            (
+                DataSource::Clickhouse,
+                "LowCardinality(String)",
+                DataType::Utf8,
+            ),
+            (DataSource::Clickhouse, "Int8", DataType::Int8),
+            (
+                DataSource::Clickhouse,
+                "Array(Nullable(Int8))",
+                list_type(DataType::Int8),
+            ),
+            (
+                DataSource::Clickhouse,
+                "Decimal64(4)",
+                DataType::Decimal128(18, 4),
+            ),
+            (DataSource::Postgres, "int8", DataType::Int64),
+            (DataSource::Postgres, "bigserial", DataType::Int64),
+            (DataSource::MySQL, "enum('a', 'b')", DataType::Utf8),
+            (DataSource::MySQL, "mediumint", DataType::Int32),
+            (DataSource::MySQL, "INT UNSIGNED", DataType::UInt32),
+            (DataSource::MySQL, "bigint(20) unsigned", DataType::UInt64),
+            (DataSource::MySQL, "mediumint unsigned", DataType::UInt32),
+            (
+                DataSource::MySQL,
+                "int(10) unsigned zerofill",
+                DataType::UInt32,
+            ),
+            (DataSource::MySQL, "tinyint(3) zerofill", DataType::UInt8),
+            (DataSource::Snowflake, "FLOAT", DataType::Float64),
+            (DataSource::Snowflake, "NUMBER", DataType::Decimal128(38, 0)),
+            (
+                DataSource::Snowflake,
+                "NUMBER(10)",
+                DataType::Decimal128(10, 0),
+            ),
+            (
+                DataSource::Snowflake,
+                "NUMBER(38, 0)",
+                DataType::Decimal128(38, 0),
+            ),
+            (
+                DataSource::Snowflake,
+                "TIMESTAMP_LTZ(9)",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            ),
+            (DataSource::Trino, "ipaddress", DataType::Utf8),
+            (DataSource::Athena, "qdigest(double)", DataType::Binary),
+            (DataSource::DuckDB, "long", DataType::Int64),
+            (DataSource::BigQuery, "FLOAT", DataType::Float64),
+            (DataSource::BigQuery, "INT", DataType::Int64),
+            (DataSource::BigQuery, "smallint", DataType::Int64),
+            (DataSource::BigQuery, "NUMERIC", DataType::Decimal128(38, 9)),
+            (
+                DataSource::BigQuery,
+                "NUMERIC(10, 2)",
+                DataType::Decimal128(10, 2),
+            ),
+            (
+                DataSource::BigQuery,
+                "BIGNUMERIC",
+                DataType::Decimal256(76, 38),
+            ),
+            // the generic type names are used if the data source doesn't have a rule
+            (DataSource::Datafusion, "float", DataType::Float32),
+        ];
+        for (data_source, data_type, expected) in test_cases {
+            assert_eq!(
+                parse_data_type_by_data_source(data_type, &data_source, false)?,
+                expected,
+                "{data_source} {data_type}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_generic_types() {
+        // the types of a data source aren't known by the other data sources
+        let error =
+            parse_data_type_by_data_source("varchar2", &DataSource::Postgres, false)
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Error during planning: Unsupported type varchar2 in type varchar2"
+        );
+    }
+}
@@ -0,0 +1,677 @@
+//! Parse the type names of the columns into Arrow data types.
+//!
+//! The nested types are parsed recursively, e.g. `array<struct<a int, b array<string>>>`,
+//! `map<varchar, int>` or ClickHouse `Array(Nullable(Int32))`. Both the angle brackets and
+//! the parentheses are accepted for the element types.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::{DataType, Field, Fields, IntervalUnit, TimeUnit};
+use datafusion::common::{plan_datafusion_err, plan_err, Result};
+use log::warn;
+
+use crate::logical_plan::type_mapping::{type_rule, TypeRule};
+use crate::mdl::manifest::DataSource;
+
+/// Parse the type name with the type names specific to the data source, e.g. Oracle `NUMBER`.
+/// If `lenient` is true, the unknown types are mapped to Utf8 with a warning instead of failing.
+pub(crate) fn parse_data_type_by_data_source(
+    data_type: &str,
+    data_source: &DataSource,
+    lenient: bool,
+) -> Result<DataType> {
+    let mut parser = TypeParser {
+        data_type,
+        tokens: tokenize(data_type)?,
+        index: 0,
+        data_source: *data_source,
+        lenient,
+    };
+    let result = parser.parse_type()?;
+    if let Some(token) = parser.peek() {
+        return plan_err!("Unexpected {token} in type {data_type}");
+    }
+    Ok(result)
+}
+
+pub(crate) fn list_type(element: DataType) -> DataType {
+    DataType::List(Arc::new(Field::new("element", element, false)))
+}
+
+pub(crate) fn map_type(key: DataType, value: DataType) -> DataType {
+    let entries = Fields::from(vec![
+        Field::new("key", key, false),
+        Field::new("value", value, true),
+    ]);
+    DataType::Map(
+        Arc::new(Field::new("entries", DataType::Struct(entries), false)),
+        false,
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Number(u64),
+    Symbol(char),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Quoted(value) => write!(f, "'{value}'"),
+            Token::Number(number) => write!(f, "{number}"),
+            Token::Symbol(symbol) => write!(f, "{symbol}"),
+        }
+    }
+}
+
+fn tokenize(data_type: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = data_type.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '<' | '>' | '(' | ')' | '[' | ']' | ',' | ':' => {
+                tokens.push(Token::Symbol(c))
+            }
+            '\'' | '"' | '`' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        // the doubled quote is an escaped quote
+                        Some(q) if q == c && chars.peek() == Some(&c) => {
+                            chars.next();
+                            value.push(c);
+                        }
+                        Some(q) if q == c => break,
+                        Some(ch) => value.push(ch),
+                        None => {
+                            return plan_err!("Unterminated quote in type {data_type}")
+                        }
+                    }
+                }
+                tokens.push(Token::Quoted(value));
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = c.to_string();
+                while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                    value.push(d);
+                }
+                let number = value.parse().map_err(|_| {
+                    plan_datafusion_err!("Invalid number {value} in type {data_type}")
+                })?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut value = c.to_string();
+                while let Some(d) = chars.next_if(|d| d.is_alphanumeric() || *d == '_') {
+                    value.push(d);
+                }
+                tokens.push(Token::Word(value));
+            }
+            _ => return plan_err!("Unexpected character {c} in type {data_type}"),
+        }
+    }
+    Ok(tokens)
+}
+
+/// The words following a type name to complete the type, e.g. `double precision` or
+/// `timestamp with time zone`
+fn is_type_suffix(word: &str) -> bool {
+    [
+        "precision",
+        "varying",
+        "with",
+        "without",
+        "unsigned",
+        "zerofill",
+    ]
+    .iter()
+    .any(|suffix| word.eq_ignore_ascii_case(suffix))
+}
+
+struct TypeParser<'a> {
+    data_type: &'a str,
+    tokens: Vec<Token>,
+    index: usize,
+    data_source: DataSource,
+    lenient: bool,
+}
+
+impl TypeParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        if token.is_some() {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn consume_symbol(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<()> {
+        if self.consume_symbol(symbol) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => plan_err!(
+                "Expected {symbol} but got {token} in type {}",
+                self.data_type
+            ),
+            None => plan_err!("Expected {symbol} in type {}", self.data_type),
+        }
+    }
+
+    /// Consume the words if all of them are matched case-insensitively
+    fn consume_words(&mut self, words: &[&str]) -> bool {
+        let matched = words.iter().enumerate().all(|(i, word)| {
+            match self.tokens.get(self.index + i) {
+                Some(Token::Word(w)) => w.eq_ignore_ascii_case(word),
+                _ => false,
+            }
+        });
+        if matched {
+            self.index += words.len();
+        }
+        matched
+    }
+
+    fn parse_type(&mut self) -> Result<DataType> {
+        let word = match self.next_token() {
+            Some(Token::Word(word)) => word,
+            Some(token) => {
+                return plan_err!(
+                    "Expected a type name but got {token} in type {}",
+                    self.data_type
+                )
+            }
+            None => return plan_err!("Expected a type name in type {}", self.data_type),
+        };
+        let name = word.to_lowercase();
+        let mut data_type = match name.as_str() {
+            // the wrappers of ClickHouse
+            "nullable" | "lowcardinality" => {
+                self.expect_symbol('(')?;
+                let data_type = self.parse_type()?;
+                self.expect_symbol(')')?;
+                data_type
+            }
+            "array" | "list" => match self.parse_type_arguments()?.as_slice() {
+                // Workaround for the array type without an element type
+                [] => list_type(DataType::Utf8),
+                [element] => list_type(element.clone()),
+                _ => return plan_err!("Array type must have one element type"),
+            },
+            "map" => match self.parse_type_arguments()?.as_slice() {
+                [] => map_type(DataType::Utf8, DataType::Utf8),
+                [key, value] => map_type(key.clone(), value.clone()),
+                _ => return plan_err!("Map type must have a key type and a value type"),
+            },
+            "struct" | "row" | "tuple" => DataType::Struct(self.parse_fields()?),
+            _ => self.parse_scalar_type(&word, &name)?,
+        };
+        // the array types of Postgres, e.g. integer[]
+        while self.consume_symbol('[') {
+            self.expect_symbol(']')?;
+            data_type = list_type(data_type);
+        }
+        Ok(data_type)
+    }
+
+    /// Parse the element types enclosed by the angle brackets or the parentheses
+    fn parse_type_arguments(&mut self) -> Result<Vec<DataType>> {
+        let close = match self.peek() {
+            Some(Token::Symbol('<')) => '>',
+            Some(Token::Symbol('(')) => ')',
+            _ => return Ok(vec![]),
+        };
+        self.index += 1;
+        let mut arguments = vec![self.parse_type()?];
+        while self.consume_symbol(',') {
+            arguments.push(self.parse_type()?);
+        }
+        self.expect_symbol(close)?;
+        Ok(arguments)
+    }
+
+    /// Parse the fields of a struct, e.g. `struct<name varchar, age int>` or `row(varchar, int)`.
+    /// The unnamed fields are named by their positions.
+    fn parse_fields(&mut self) -> Result<Fields> {
+        let close = match self.peek() {
+            Some(Token::Symbol('<')) => '>',
+            Some(Token::Symbol('(')) => ')',
+            _ => return plan_err!("struct must have at least one field"),
+        };
+        self.index += 1;
+        if self.consume_symbol(close) {
+            return plan_err!("struct must have at least one field");
+        }
+        let mut fields = vec![];
+        loop {
+            let name = match (self.peek(), self.tokens.get(self.index + 1)) {
+                // the quoted names are case-sensitive
+                (Some(Token::Quoted(name)), _) => Some(name.clone()),
+                // the unnamed multi-word types, e.g. `double precision`
+                (Some(Token::Word(_)), Some(Token::Word(next)))
+                    if is_type_suffix(next) =>
+                {
+                    None
+                }
+                (
+                    Some(Token::Word(name)),
+                    Some(Token::Word(_) | Token::Quoted(_) | Token::Symbol(':')),
+                ) => Some(name.to_lowercase()),
+                _ => None,
+            };
+            if name.is_some() {
+                self.index += 1;
+                self.consume_symbol(':');
+            }
+            let data_type = self.parse_type()?;
+            let name = name.unwrap_or_else(|| format!("c{}", fields.len()));
+            fields.push(Field::new(name, data_type, true));
+            if !self.consume_symbol(',') {
+                break;
+            }
+        }
+        self.expect_symbol(close)?;
+        Ok(fields.into())
+    }
+
+    /// Parse the arguments of a scalar type, e.g. the precision and the scale of a decimal
+    fn parse_literal_arguments(&mut self) -> Result<Vec<Token>> {
+        if !self.consume_symbol('(') {
+            return Ok(vec![]);
+        }
+        let mut arguments = vec![];
+        loop {
+            match self.next_token() {
+                Some(token @ (Token::Word(_) | Token::Quoted(_) | Token::Number(_))) => {
+                    arguments.push(token)
+                }
+                Some(token) => {
+                    return plan_err!("Unexpected {token} in type {}", self.data_type)
+                }
+                None => return plan_err!("Expected ) in type {}", self.data_type),
+            }
+            if self.consume_symbol(')') {
+                return Ok(arguments);
+            }
+            self.expect_symbol(',')?;
+        }
+    }
+
+    /// Parse the suffix of the time types, e.g. `with time zone` or `without time zone`
+    fn parse_time_zone_suffix(&mut self) -> bool {
+        if self.consume_words(&["with", "time", "zone"])
+            || self.consume_words(&["with", "local", "time", "zone"])
+        {
+            return true;
+        }
+        self.consume_words(&["without", "time", "zone"]);
+        false
+    }
+
+    fn parse_scalar_type(&mut self, word: &str, name: &str) -> Result<DataType> {
+        let name = match type_rule(&self.data_source, name) {
+            Some(TypeRule::Alias(alias)) => alias,
+            Some(TypeRule::Fixed(data_type)) => {
+                self.parse_literal_arguments()?;
+                return Ok(data_type);
+            }
+            Some(TypeRule::ScaledDecimal(precision)) => {
+                return match self.parse_literal_arguments()?.as_slice() {
+                    [scale @ Token::Number(_)] => self
+                        .decimal_type(&[Token::Number(precision as u64), scale.clone()]),
+                    _ => plan_err!(
+                        "Expected the scale of the decimal in type {}",
+                        self.data_type
+                    ),
+                };
+            }
+            Some(TypeRule::DefaultDecimal(precision, scale)) => {
+                let arguments = self.parse_literal_arguments()?;
+                return if arguments.is_empty() {
+                    self.decimal_type(&[
+                        Token::Number(precision as u64),
+                        Token::Number(scale as u64),
+                    ])
+                } else {
+                    self.decimal_type(&arguments)
+                };
+            }
+            None => name,
+        };
+        let name = match name {
+            "double" if self.consume_words(&["precision"]) => "double",
+            "character" | "char" if self.consume_words(&["varying"]) => "varchar",
+            _ => name,
+        };
+        let arguments = self.parse_literal_arguments()?;
+        // the integers of MySQL can be unsigned, e.g. `int(10) unsigned zerofill`. The zero
+        // filled integers are unsigned as well.
+        let unsigned = self.consume_words(&["unsigned"]);
+        let unsigned = self.consume_words(&["zerofill"]) || unsigned;
+        let data_type = match name {
+            // Wren Definition Types
+            "bool" | "boolean" => DataType::Boolean,
+            "tinyint" => DataType::Int8,
+            "utinyint" | "uint8" => DataType::UInt8,
+            "int2" | "smallint" | "int16" => DataType::Int16,
+            "usmallint" | "uint16" => DataType::UInt16,
+            "int4" | "int" | "integer" | "int32" => DataType::Int32,
+            "uinteger" | "uint32" => DataType::UInt32,
+            "int8" | "bigint" | "int64" => DataType::Int64,
+            "ubigint" | "uint64" => DataType::UInt64,
+            // set the default precision and scale
+            "numeric" | "decimal" | "dec" | "bignumeric" | "bigdecimal" => {
+                self.decimal_type(&arguments)?
+            }
+            "varchar" | "char" | "character" | "bpchar" | "text" | "string" | "name"
+            | "nvarchar" | "nchar" | "fixedstring" => DataType::Utf8,
+            "float4" | "real" | "float" | "float32" => DataType::Float32,
+            "float8" | "double" | "float64" => DataType::Float64,
+            // chose the smallest time unit
+            "timestamp" | "datetime" | "datetime64" | "timestamp_ns" => {
+                let with_time_zone = self.parse_time_zone_suffix();
+                let time_zone = self
+                    .time_zone(&arguments)?
+                    .or_else(|| with_time_zone.then(|| "UTC".to_string()));
+                DataType::Timestamp(TimeUnit::Nanosecond, time_zone.map(Into::into))
+            }
+            "timestamptz" | "timestamp_with_timezone" | "timestamp_with_time_zone" => {
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+            }
+            "time" => {
+                if self.parse_time_zone_suffix() {
+                    // time with time zone isn't equal to timestamp with time zone but
+                    // we don't have a time with time zone type, so we map it to timestamp with time zone
+                    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+                } else {
+                    DataType::Time32(TimeUnit::Nanosecond)
+                }
+            }
+            "date" | "date32" => DataType::Date32,
+            "interval" => DataType::Interval(IntervalUnit::DayTime),
+            // we don't have the JSON, XML, UUID, INET, UNKNOWN, VARIANT and GEO types,
+            // so we map them to Utf8
+            "json" | "xml" | "uuid" | "inet" | "unknown" | "variant" | "geography"
+            | "geometry" => DataType::Utf8,
+            // we don't have a JSONB type, so we map it to Binary
+            "jsonb" | "bytea" | "bytes" | "binary" | "varbinary" | "blob" => {
+                DataType::Binary
+            }
+            "oid" => DataType::Int32,
+            "null" => DataType::Null,
+            // DuckDB Compatible Types
+            "hugeint" => DataType::Int64, // we don't have a HUGEINT type, so we map it to Int64
+            "uhugeint" => DataType::UInt64, // we don't have a UHUINT type, so we map it to UInt64
+            "bit" => DataType::Boolean, // we don't have a BIT type, so we map it to Boolean
+            _ => {
+                // the Arrow type names, e.g. Utf8 or LargeUtf8. from_str is case-sensitive.
+                if arguments.is_empty() {
+                    if let Ok(data_type) = DataType::from_str(word) {
+                        return Ok(data_type);
+                    }
+                }
+                if !self.lenient {
+                    return plan_err!(
+                        "Unsupported type {word} in type {}",
+                        self.data_type
+                    );
+                }
+                warn!(
+                    "Unsupported type {word} in type {}, map it to Utf8",
+                    self.data_type
+                );
+                DataType::Utf8
+            }
+        };
+        if !unsigned {
+            return Ok(data_type);
+        }
+        let data_type = match data_type {
+            DataType::Int8 => DataType::UInt8,
+            DataType::Int16 => DataType::UInt16,
+            DataType::Int32 => DataType::UInt32,
+            DataType::Int64 => DataType::UInt64,
+            // the unsigned decimals and floats have the same range of the positive values
+            data_type => data_type,
+        };
+        Ok(data_type)
+    }
+
+    fn decimal_type(&self, arguments: &[Token]) -> Result<DataType> {
+        let numbers = arguments
+            .iter()
+            .map(|argument| match argument {
+                Token::Number(number) => Ok(*number),
+                _ => plan_err!(
+                    "Expected the precision and the scale of the decimal in type {}",
+                    self.data_type
+                ),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (precision, scale) = match numbers.as_slice() {
+            [] => (38, 10),
+            [precision] => (*precision, 0),
+            [precision, scale] => (*precision, *scale),
+            _ => {
+                return plan_err!(
+                    "Expected the precision and the scale of the decimal in type {}",
+                    self.data_type
+                )
+            }
+        };
+        if precision == 0 || precision > 76 || scale > precision {
+            return plan_err!(
+                "Invalid precision {precision} or scale {scale} of the decimal in type {}",
+                self.data_type
+            );
+        }
+        if precision <= 38 {
+            Ok(DataType::Decimal128(precision as u8, scale as i8))
+        } else {
+            Ok(DataType::Decimal256(precision as u8, scale as i8))
+        }
+    }
+
+    /// The time zone of ClickHouse `DateTime64(3, 'UTC')`. The precision is ignored.
+    fn time_zone(&self, arguments: &[Token]) -> Result<Option<String>> {
+        let mut time_zone = None;
+        for argument in arguments {
+            match argument {
+                Token::Number(_) => {}
+                Token::Quoted(value) => time_zone = Some(value.clone()),
+                Token::Word(_) | Token::Symbol(_) => {
+                    return plan_err!(
+                        "Unexpected {argument} of the timestamp in type {}",
+                        self.data_type
+                    )
+                }
+            }
+        }
+        Ok(time_zone)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use datafusion::arrow::datatypes::{DataType, Field, Fields, TimeUnit};
+    use datafusion::common::Result;
+    use insta::assert_snapshot;
+
+    use crate::logical_plan::type_parser::{
+        list_type, map_type, parse_data_type_by_data_source,
+    };
+    use crate::mdl::manifest::DataSource;
+
+    fn parse_data_type(data_type: &str, lenient: bool) -> Result<DataType> {
+        parse_data_type_by_data_source(data_type, &DataSource::default(), lenient)
+    }
+
+    #[test]
+    fn test_nested_types() -> Result<()> {
+        let struct_type = |a: DataType| {
+            DataType::Struct(Fields::from(vec![
+                Field::new("a", a, true),
+                Field::new("b", list_type(DataType::Utf8), true),
+            ]))
+        };
+        let test_cases = vec![
+            (
+                "array<struct<a int, b array<string>>>",
+                list_type(struct_type(DataType::Int32)),
+            ),
+            (
+                "ARRAY<STRUCT<a INT64, b ARRAY<STRING>>>",
+                list_type(struct_type(DataType::Int64)),
+            ),
+            ("row(a integer, b varchar[])", struct_type(DataType::Int32)),
+            (
+                "Tuple(a Int32, b Array(String))",
+                struct_type(DataType::Int32),
+            ),
+            (
+                "struct<a: int, b: list<text>>",
+                struct_type(DataType::Int32),
+            ),
+            (
+                "map<varchar, int>",
+                map_type(DataType::Utf8, DataType::Int32),
+            ),
+            (
+                "Map(String, Array(Nullable(Int64)))",
+                map_type(DataType::Utf8, list_type(DataType::Int64)),
+            ),
+            ("LowCardinality(Nullable(String))", DataType::Utf8),
+            ("integer[][]", list_type(list_type(DataType::Int32))),
+            (
+                r#"struct<"Name" varchar, `Age` int>"#,
+                DataType::Struct(Fields::from(vec![
+                    Field::new("Name", DataType::Utf8, true),
+                    Field::new("Age", DataType::Int32, true),
+                ])),
+            ),
+            (
+                "row(double precision, int)",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("c0", DataType::Float64, true),
+                    Field::new("c1", DataType::Int32, true),
+                ])),
+            ),
+            (
+                "row(timestamp with time zone)",
+                DataType::Struct(Fields::from(vec![Field::new(
+                    "c0",
+                    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+                    true,
+                )])),
+            ),
+            (
+                "struct<price double precision, name character varying(10)>",
+                DataType::Struct(Fields::from(vec![
+                    Field::new("price", DataType::Float64, true),
+                    Field::new("name", DataType::Utf8, true),
+                ])),
+            ),
+        ];
+        for (data_type, expected) in test_cases {
+            assert_eq!(parse_data_type(data_type, false)?, expected, "{data_type}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scalar_types() -> Result<()> {
+        let test_cases = vec![
+            ("decimal(10, 2)", DataType::Decimal128(10, 2)),
+            ("NUMERIC(18)", DataType::Decimal128(18, 0)),
+            ("bignumeric(76, 38)", DataType::Decimal256(76, 38)),
+            ("numeric", DataType::Decimal128(38, 10)),
+            ("varchar(255)", DataType::Utf8),
+            ("character varying(10)", DataType::Utf8),
+            ("double precision", DataType::Float64),
+            (
+                "timestamp(3) with time zone",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            ),
+            (
+                "timestamp without time zone",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+            ),
+            (
+                "DateTime64(3, 'Asia/Taipei')",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("Asia/Taipei".into())),
+            ),
+            ("VARIANT", DataType::Utf8),
+            ("LargeUtf8", DataType::LargeUtf8),
+        ];
+        for (data_type, expected) in test_cases {
+            assert_eq!(parse_data_type(data_type, false)?, expected, "{data_type}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_types() {
+        let test_cases = vec![
+            ("struct<>", "struct must have at least one field"),
+            ("array<int, int>", "Array type must have one element type"),
+            (
+                "decimal(80, 2)",
+                "Invalid precision 80 or scale 2 of the decimal in type decimal(80, 2)",
+            ),
+            (
+                "array<geography2>",
+                "Unsupported type geography2 in type array<geography2>",
+            ),
+            ("map<int", "Expected > in type map<int"),
+            ("int int", "Unexpected int in type int int"),
+        ];
+        for (data_type, expected) in test_cases {
+            let error = parse_data_type(data_type, false).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Error during planning: {expected}")
+            );
+        }
+    }
+
+    #[test]
+    fn test_lenient() -> Result<()> {
+        assert_eq!(
+            parse_data_type("array<geography2>", true)?,
+            list_type(DataType::Utf8)
+        );
+        let error = parse_data_type("array<int", true).unwrap_err();
+        assert_snapshot!(error.to_string(), @"Error during planning: Expected > in type array<int");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_field() {
+        assert_eq!(
+            list_type(DataType::Int64),
+            DataType::List(Arc::new(Field::new("element", DataType::Int64, false)))
+        );
+    }
+}
@@ -1,196 +1,145 @@
+use crate::logical_plan::type_parser::parse_data_type_by_data_source;
 use crate::mdl::lineage::DatasetLink;
-use crate::mdl::manifest::Column;
+use crate::mdl::manifest::{Column, DataSource};
 use crate::mdl::utils::quoted;
 use crate::mdl::{manifest::Model, WrenMDL};
 use crate::mdl::{Dataset, SessionStateRef};
-use datafusion::arrow::datatypes::{
-    DataType, Field, IntervalUnit, Schema, SchemaBuilder, SchemaRef, TimeUnit,
-};
-use datafusion::common::plan_err;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use datafusion::common::tree_node::{
     Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
 };
 use datafusion::datasource::DefaultTableSource;
 use datafusion::error::Result;
-use datafusion::logical_expr::sqlparser::ast::ArrayElemTypeDef;
-use datafusion::logical_expr::sqlparser::dialect::GenericDialect;
 use datafusion::logical_expr::{builder::LogicalTableSource, Expr, TableSource};
-use datafusion::sql::sqlparser::ast;
-use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::TableReference;
-use log::debug;
+use log::{debug, warn};
 use petgraph::dot::{Config, Dot};
 use petgraph::Graph;
 use std::collections::{BTreeMap, HashSet};
 use std::str::FromStr;
 use std::{collections::HashMap, sync::Arc};
 
-fn create_list_type(array_type: &str) -> Result<DataType> {
-    // Workaround for the array type without an element type
-    if array_type.len() == "array".len() || array_type == "list" {
-        return create_list_type("array<varchar>");
-    }
-    if let ast::DataType::Array(value) = parse_type(array_type)? {
-        let data_type = match value {
-            ArrayElemTypeDef::None => {
-                return plan_err!("Array type must have an element type")
-            }
-            ArrayElemTypeDef::AngleBracket(data_type) => {
-                try_map_data_type(&data_type.to_string())?
-            }
-            ArrayElemTypeDef::SquareBracket(_, _) => {
-                unreachable!()
-            }
-            ArrayElemTypeDef::Parenthesis(_) => {
-                return plan_err!(
-                    "The format of the array type should be 'array<element_type>'"
-                )
-            }
-        };
-        return Ok(DataType::List(Arc::new(Field::new(
-            "element", data_type, false,
-        ))));
-    }
-    unreachable!()
+/// Map the data type from the string to the Arrow data type
+/// If the data type is not supported, it will return Utf8 with a warning
+pub fn try_map_data_type(data_type: &str) -> Result<DataType> {
+    try_map_data_type_by_data_source(data_type, &DataSource::default())
 }
 
-fn create_struct_type(struct_type: &str) -> Result<DataType> {
-    let sql_type = parse_type(struct_type)?;
-    let mut builder = SchemaBuilder::new();
-    let mut counter = 0;
-    match sql_type {
-        ast::DataType::Struct(fields, ..) => {
-            if fields.is_empty() {
-                return plan_err!("struct must have at least one field");
-            }
-            for field in fields {
-                let data_type = try_map_data_type(field.field_type.to_string().as_str())?;
-                let field = Field::new(
-                    field
-                        .field_name
-                        .map(|f| f.to_string())
-                        .unwrap_or_else(|| format!("c{}", counter)),
-                    data_type,
-                    true,
-                );
-                counter += 1;
-                builder.push(field);
-            }
-        }
-        _ => {
-            unreachable!()
-        }
-    }
-    let fields = builder.finish().fields;
-    Ok(DataType::Struct(fields))
+/// Map the data type from the string to the Arrow data type. The nested types are parsed
+/// recursively, e.g. `array<struct<a int, b array<string>>>`.
+pub fn map_data_type(data_type: &str) -> Result<DataType> {
+    map_data_type_by_data_source(data_type, &DataSource::default())
 }
 
-fn parse_type(struct_type: &str) -> Result<ast::DataType> {
-    let dialect = GenericDialect {};
-    Ok(Parser::new(&dialect)
-        .try_with_sql(struct_type)?
-        .parse_data_type()?)
+/// Map the type name of the data source to the Arrow data type, e.g. Oracle `NUMBER(10, 2)`
+/// or ClickHouse `LowCardinality(String)`.
+/// If the data type is not supported, it will return Utf8 with a warning
+pub fn try_map_data_type_by_data_source(
+    data_type: &str,
+    data_source: &DataSource,
+) -> Result<DataType> {
+    Ok(
+        map_data_type_by_data_source(data_type, data_source).unwrap_or_else(|_| {
+            parse_data_type_by_data_source(data_type, data_source, true).unwrap_or_else(
+                |e| {
+                    warn!("can't parse data type {}, return Utf8: {}", data_type, e);
+                    DataType::Utf8
+                },
+            )
+        }),
+    )
 }
 
-/// Map the data type from the string to the Arrow data type
-/// If the data type is not supported, it will return Utf8
-pub fn try_map_data_type(data_type: &str) -> Result<DataType> {
-    Ok(map_data_type(data_type).ok().unwrap_or_else(|| {
-        debug!("can't parse data type {}, return Utf8", data_type);
-        DataType::Utf8
-    }))
+/// Map the type name of the data source to the Arrow data type. The type names specific to
+/// the data source are tried before the generic type names.
+pub fn map_data_type_by_data_source(
+    data_type: &str,
+    data_source: &DataSource,
+) -> Result<DataType> {
+    parse_data_type_by_data_source(data_type, data_source, false).or_else(|e| {
+        debug!("try parse by arrow {}", data_type);
+        // the types printed by arrow, e.g. Timestamp(Nanosecond, None)
+        DataType::from_str(data_type).map_err(|_| e)
+    })
 }
 
-pub fn map_data_type(data_type: &str) -> Result<DataType> {
-    let lower = data_type.to_lowercase();
-    let lower_data_type = lower.as_str();
-    // TODO: try parse nested type by arrow
-    // Currently, we don't care about the element type of the array or struct.
-    // We only care about the array or struct itself.
-    if lower_data_type.starts_with("array") || lower_data_type.starts_with("list") {
-        return create_list_type(lower_data_type);
-    }
-    if lower_data_type.starts_with("struct") {
-        return create_struct_type(lower_data_type);
-    }
-    let result = match lower_data_type {
-        // Wren Definition Types
-        "bool" | "boolean" => DataType::Boolean,
-        "tinyint" => DataType::Int8,
-        "utinyint" => DataType::UInt8,
-        "int2" => DataType::Int16,
-        "smallint" => DataType::Int16,
-        "usmallint" => DataType::UInt16,
-        "int4" => DataType::Int32,
-        "int" => DataType::Int32,
-        "integer" => DataType::Int32,
-        "uinteger" => DataType::UInt32,
-        "int8" => DataType::Int64,
-        "bigint" => DataType::Int64,
-        "ubigint" => DataType::UInt64,
-        "numeric" => DataType::Decimal128(38, 10), // set the default precision and scale
-        "decimal" => DataType::Decimal128(38, 10),
-        "varchar" => DataType::Utf8,
-        "char" => DataType::Utf8,
-        "bpchar" => DataType::Utf8, // we don't have a BPCHAR type, so we map it to Utf8
-        "text" => DataType::Utf8,
-        "string" => DataType::Utf8,
-        "name" => DataType::Utf8,
-        "float4" => DataType::Float32,
-        "real" => DataType::Float32,
-        "float" => DataType::Float32,
-        "float8" => DataType::Float64,
-        "double" => DataType::Float64,
-        "timestamp" | "datetime" => DataType::Timestamp(TimeUnit::Nanosecond, None), // chose the smallest time unit
-        "timestamptz"
-        | "timestamp_with_timezone"
-        | "timestamp_with_time_zone"
-        | "timestamp with time zone"
-        | "time with time zone" => {
-            // time with time zone isn't equal to timestamp with time zone but
-            // we don't have a time with time zone type, so we map it to timestamp with time zone
-            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+/// Map the Arrow data type to the type name of a column, which can be mapped back by [map_data_type]
+pub fn map_arrow_type(data_type: &DataType) -> String {
+    match data_type {
+        DataType::Boolean => "boolean".to_string(),
+        DataType::Int8 => "tinyint".to_string(),
+        DataType::UInt8 => "utinyint".to_string(),
+        DataType::Int16 => "smallint".to_string(),
+        DataType::UInt16 => "usmallint".to_string(),
+        DataType::Int32 => "integer".to_string(),
+        DataType::UInt32 => "uinteger".to_string(),
+        DataType::Int64 => "bigint".to_string(),
+        DataType::UInt64 => "ubigint".to_string(),
+        DataType::Float16 | DataType::Float32 => "real".to_string(),
+        DataType::Float64 => "double".to_string(),
+        DataType::Decimal128(precision, scale)
+        | DataType::Decimal256(precision, scale) => {
+            format!("decimal({precision}, {scale})")
+        }
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            "varchar".to_string()
         }
-        "date" => DataType::Date32,
-        "interval" => DataType::Interval(IntervalUnit::DayTime),
-        "json" => DataType::Utf8, // we don't have a JSON type, so we map it to Utf8
-        "xml" => DataType::Utf8,  // we don't have a XML type, so we map it to Utf8
-        "jsonb" => DataType::Binary, // we don't have a JSONB type, so we map it to Binary
-        "oid" => DataType::Int32,
-        "bytea" => DataType::Binary,
-        "uuid" => DataType::Utf8, // we don't have a UUID type, so we map it to Utf8
-        "inet" => DataType::Utf8, // we don't have a INET type, so we map it to Utf8
-        "unknown" => DataType::Utf8, // we don't have a UNKNOWN type, so we map it to Utf8
-        // BigQuery Compatible Types
-        "bignumeric" => DataType::Decimal128(38, 10), // set the default precision and scale
-        "bytes" => DataType::Binary,
-        "binary" => DataType::Binary,
-        "float64" => DataType::Float64,
-        "int64" => DataType::Int64,
-        "time" => DataType::Time32(TimeUnit::Nanosecond), // chose the smallest time unit
-        "null" => DataType::Null,
-        // Trino Compatible Types
-        "varbinary" => DataType::Binary,
-        // DuckDB Compatible Types
-        "blob" => DataType::Binary,
-        "hugeint" => DataType::Int64, // we don't have a HUGEINT type, so we map it to Int64
-        "uhugeint" => DataType::UInt64, // we don't have a UHUINT type, so we map it to UInt64
-        "bit" => DataType::Boolean, // we don't have a BIT type, so we map it to Boolean
-        "timestamp_ns" => DataType::Timestamp(TimeUnit::Nanosecond, None),
-        _ => {
-            debug!("try parse by arrow {}", lower_data_type);
-            // the from_str is case sensitive, so we need to use the original string
-            DataType::from_str(data_type)?
+        DataType::Binary
+        | DataType::LargeBinary
+        | DataType::BinaryView
+        | DataType::FixedSizeBinary(_) => "bytea".to_string(),
+        DataType::Date32 | DataType::Date64 => "date".to_string(),
+        DataType::Time32(_) | DataType::Time64(_) => "time".to_string(),
+        DataType::Timestamp(_, None) => "timestamp".to_string(),
+        DataType::Timestamp(_, Some(_)) => "timestamptz".to_string(),
+        DataType::Interval(_) => "interval".to_string(),
+        DataType::Null => "null".to_string(),
+        DataType::List(field)
+        | DataType::LargeList(field)
+        | DataType::ListView(field)
+        | DataType::LargeListView(field)
+        | DataType::FixedSizeList(field, _) => {
+            format!("array<{}>", map_arrow_type(field.data_type()))
         }
-    };
-    Ok(result)
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    // quote the names to keep their cases and the special characters
+                    format!(
+                        "\"{}\" {}",
+                        field.name().replace('"', "\"\""),
+                        map_arrow_type(field.data_type())
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("struct<{}>", fields.join(", "))
+        }
+        DataType::Map(field, _) => match field.data_type() {
+            DataType::Struct(fields) if fields.len() == 2 => format!(
+                "map<{}, {}>",
+                map_arrow_type(fields[0].data_type()),
+                map_arrow_type(fields[1].data_type())
+            ),
+            _ => data_type.to_string(),
+        },
+        DataType::Dictionary(_, value_type) => map_arrow_type(value_type),
+        // the other types are parsed by arrow
+        _ => data_type.to_string(),
+    }
 }
 
-pub fn create_schema(columns: Vec<Arc<Column>>) -> Result<SchemaRef> {
+/// Create the schema of the columns. The types of the columns are mapped by the type names of
+/// the data source.
+pub fn create_schema(
+    columns: Vec<Arc<Column>>,
+    data_source: &DataSource,
+) -> Result<SchemaRef> {
     let fields: Vec<Field> = columns
         .iter()
         .map(|column| {
-            let data_type = try_map_data_type(&column.r#type)?;
+            let data_type =
+                try_map_data_type_by_data_source(&column.r#type, data_source)?;
             Ok(Field::new(&column.name, data_type, column.not_null))
         })
         .collect::<Result<Vec<_>>>()?;
@@ -208,9 +157,13 @@ pub fn create_remote_table_source(
     if let Some(table_provider) = mdl.get_table(model.table_reference()) {
         Ok(Arc::new(DefaultTableSource::new(table_provider)))
     } else {
+        let data_source = mdl.model_type_source(&model);
         let dataset = Dataset::Model(model);
-        let schema = dataset
-            .to_remote_schema(Some(mdl.get_register_tables()), session_state_ref)?;
+        let schema = dataset.to_remote_schema(
+            Some(mdl.get_register_tables()),
+            &data_source,
+            session_state_ref,
+        )?;
         Ok(Arc::new(LogicalTableSource::new(Arc::new(
             schema.as_arrow().clone(),
         ))))
@@ -370,9 +323,8 @@ pub fn eliminate_ambiguous_columns(expr: Vec<Expr>) -> Vec<Expr> {
 
 #[cfg(test)]
 mod test {
-    use crate::logical_plan::utils::{
-        create_list_type, create_struct_type, try_map_data_type,
-    };
+    use crate::logical_plan::type_parser::{list_type, map_type};
+    use crate::logical_plan::utils::{map_arrow_type, map_data_type, try_map_data_type};
     use datafusion::arrow::datatypes::{DataType, Field, Fields, IntervalUnit, TimeUnit};
     use datafusion::common::Result;
 
@@ -430,11 +382,20 @@ mod test {
             ("null", DataType::Null),
             ("geography", DataType::Utf8),
             ("range", DataType::Utf8),
-            ("array", create_list_type("array<varchar>")?),
-            ("array<int64>", create_list_type("array<int64>")?),
+            ("array", list_type(DataType::Utf8)),
+            ("array<int64>", list_type(DataType::Int64)),
+            ("array(int64)", list_type(DataType::Int64)),
             (
                 "struct<name string, age int>",
-                create_struct_type("struct<name string, age int>")?,
+                DataType::Struct(Fields::from(vec![
+                    Field::new("name", DataType::Utf8, true),
+                    Field::new("age", DataType::Int32, true),
+                ])),
+            ),
+            ("decimal(10, 2)", DataType::Decimal128(10, 2)),
+            (
+                "map<varchar, int>",
+                map_type(DataType::Utf8, DataType::Int32),
             ),
         ];
         for (data_type, expected) in test_cases {
@@ -445,41 +406,87 @@ mod test {
             assert_eq!(result, expected);
         }
 
-        let _ = try_map_data_type("array").map_err(|e| {
-            assert_eq!(
-                e.to_string(),
-                "SQL error: ParserError(\"Expected: <, found: EOF\")"
-            );
-        });
+        assert_eq!(
+            map_data_type("array<>").unwrap_err().to_string(),
+            "Error during planning: Expected a type name but got > in type array<>"
+        );
+        assert_eq!(
+            map_data_type("struct").unwrap_err().to_string(),
+            "Error during planning: struct must have at least one field"
+        );
+        assert_eq!(
+            map_data_type("geography2").unwrap_err().to_string(),
+            "Error during planning: Unsupported type geography2 in type geography2"
+        );
+        // the unknown types are mapped to Utf8 with a warning
+        assert_eq!(
+            try_map_data_type("array<geography2>")?,
+            list_type(DataType::Utf8)
+        );
+        // the types printed by arrow
+        assert_eq!(
+            map_data_type("Timestamp(Microsecond, None)")?,
+            DataType::Timestamp(TimeUnit::Microsecond, None)
+        );
 
-        let _ = try_map_data_type("array<>").map_err(|e| {
-            assert_eq!(
-                e.to_string(),
-                "SQL error: ParserError(\"Expected: <, found: <> at Line: 1, Column: 6\")"
-            );
-        });
-
-        let _ = try_map_data_type("array(int64)").map_err(|e| {
-            assert_eq!(
-                e.to_string(),
-                "SQL error: ParserError(\"Expected: <, found: ( at Line: 1, Column: 6\")"
-            );
-        });
-
-        let _ = try_map_data_type("struct").map_err(|e| {
-            assert_eq!(
-                e.to_string(),
-                "Error during planning: struct must have at least one field"
-            );
-        });
+        Ok(())
+    }
 
+    #[test]
+    fn test_map_arrow_type() -> Result<()> {
+        let test_cases = vec![
+            DataType::Boolean,
+            DataType::Int8,
+            DataType::Int16,
+            DataType::Int32,
+            DataType::Int64,
+            DataType::UInt64,
+            DataType::Float32,
+            DataType::Float64,
+            DataType::Utf8,
+            DataType::Binary,
+            DataType::Date32,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            DataType::Null,
+            DataType::Decimal128(10, 2),
+            list_type(DataType::Int64),
+            map_type(DataType::Utf8, list_type(DataType::Int32)),
+            DataType::Struct(Fields::from(vec![
+                Field::new("name", DataType::Utf8, true),
+                Field::new("age", DataType::Int32, true),
+            ])),
+            DataType::Struct(Fields::from(vec![
+                Field::new("First Name", DataType::Utf8, true),
+                Field::new("Quoted\"Age", DataType::Int32, true),
+            ])),
+        ];
+        // the mapped types are mapped back to the same types
+        for data_type in test_cases {
+            let result = try_map_data_type(&map_arrow_type(&data_type))?;
+            assert_eq!(result, data_type);
+        }
+        assert_eq!(map_arrow_type(&DataType::LargeUtf8), "varchar");
+        assert_eq!(map_arrow_type(&DataType::Float16), "real");
+        assert_eq!(
+            map_arrow_type(&DataType::Decimal128(10, 2)),
+            "decimal(10, 2)"
+        );
+        assert_eq!(
+            map_arrow_type(&DataType::Struct(Fields::from(vec![Field::new(
+                "Name",
+                DataType::Utf8,
+                true
+            )]))),
+            r#"struct<"Name" varchar>"#
+        );
         Ok(())
     }
 
     #[test]
     fn test_parse_struct() -> Result<()> {
         let struct_string = "STRUCT<name VARCHAR, age INT>";
-        let result = create_struct_type(struct_string)?;
+        let result = map_data_type(struct_string)?;
         let fields: Fields = vec![
             Field::new("name", DataType::Utf8, true),
             Field::new("age", DataType::Int32, true),
@@ -489,7 +496,7 @@ mod test {
         assert_eq!(result, expected);
 
         let struct_string = "STRUCT<VARCHAR, INT>";
-        let result = create_struct_type(struct_string)?;
+        let result = map_data_type(struct_string)?;
         let fields: Fields = vec![
             Field::new("c0", DataType::Utf8, true),
             Field::new("c1", DataType::Int32, true),
@@ -498,12 +505,10 @@ mod test {
         let expected = DataType::Struct(fields);
         assert_eq!(result, expected);
         let struct_string = "STRUCT<>";
-        let _ = create_struct_type(struct_string).map_err(|e| {
-            assert_eq!(
-                e.to_string(),
-                "Error during planning: struct must have at least one field"
-            )
-        });
+        assert_eq!(
+            map_data_type(struct_string).unwrap_err().to_string(),
+            "Error during planning: struct must have at least one field"
+        );
         Ok(())
     }
 }
@@ -0,0 +1,137 @@
+//! The connectors scan the source tables of the models, so the local runtime can execute the
+//! queries whose models are stored in different data sources.
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::catalog::Session;
+use datafusion::common::{plan_datafusion_err, Result};
+use datafusion::datasource::TableProvider;
+use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
+use datafusion::physical_expr::expressions::{cast, col};
+use datafusion::physical_plan::projection::ProjectionExec;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::sql::TableReference;
+
+/// Scan the source table of a model from its data source.
+///
+/// The projection, the filters and the limit of the scan are pushed down to the connector.
+/// The projection refers to the fields of `schema`, which is the schema of the source table
+/// declared by the model, and the returned plan should produce the projected fields of it.
+#[async_trait]
+pub trait Connector: Debug + Send + Sync {
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        table_reference: &TableReference,
+        schema: &SchemaRef,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>>;
+
+    /// Tell whether the filters can be evaluated by the data source. None of them is pushed down by default.
+    fn supports_filters_pushdown(
+        &self,
+        _table_reference: &TableReference,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(vec![
+            TableProviderFilterPushDown::Unsupported;
+            filters.len()
+        ])
+    }
+}
+
+type TableProviderFactory =
+    dyn Fn(&TableReference) -> Result<Arc<dyn TableProvider>> + Send + Sync;
+
+/// A [Connector] which scans the [TableProvider] created for the table reference by the factory,
+/// e.g. the tables of another SessionContext or the providers of a DataFusion table factory.
+pub struct TableProviderConnector {
+    factory: Box<TableProviderFactory>,
+}
+
+impl TableProviderConnector {
+    pub fn new(
+        factory: impl Fn(&TableReference) -> Result<Arc<dyn TableProvider>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            factory: Box::new(factory),
+        }
+    }
+}
+
+impl Debug for TableProviderConnector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TableProviderConnector").finish()
+    }
+}
+
+#[async_trait]
+impl Connector for TableProviderConnector {
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        table_reference: &TableReference,
+        schema: &SchemaRef,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let provider = (self.factory)(table_reference)?;
+        let projected = match projection {
+            Some(projection) => Arc::new(schema.project(projection)?),
+            None => Arc::clone(schema),
+        };
+        // the provider may order the columns differently, so they're mapped by name
+        let source_schema = provider.schema();
+        let indices = projected
+            .fields()
+            .iter()
+            .map(|field| {
+                source_schema.index_of(field.name()).map_err(|_| {
+                    plan_datafusion_err!(
+                        "Column {} not found in the source table {}",
+                        field.name(),
+                        table_reference
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let plan = provider.scan(state, Some(&indices), filters, limit).await?;
+        let input_schema = plan.schema();
+        if input_schema
+            .fields()
+            .iter()
+            .zip(projected.fields())
+            .all(|(source, target)| source.data_type() == target.data_type())
+        {
+            return Ok(plan);
+        }
+        // cast the source columns to the types declared by the model
+        let exprs = projected
+            .fields()
+            .iter()
+            .map(|field| {
+                let column = col(field.name(), &input_schema)?;
+                let expr = cast(column, &input_schema, field.data_type().clone())?;
+                Ok((expr, field.name().clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Arc::new(ProjectionExec::try_new(exprs, plan)?))
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        table_reference: &TableReference,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        (self.factory)(table_reference)?.supports_filters_pushdown(filters)
+    }
+}
@@ -2,24 +2,30 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::logical_plan::analyze::access_control::validate_clac_rule;
 use crate::logical_plan::analyze::expand_view::ExpandWrenViewRule;
 use crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule;
 use crate::logical_plan::analyze::model_generation::ModelGenerationRule;
+use crate::logical_plan::optimize::eliminate_to_one_join::EliminateToOneJoin;
 use crate::logical_plan::optimize::simplify_timestamp::TimestampSimplify;
 use crate::logical_plan::utils::create_schema;
-use crate::mdl::manifest::Model;
+use crate::mdl::connector::Connector;
+use crate::mdl::file_source::{register_file_sources, session_runtime_env};
+use crate::mdl::manifest::{DataSource, Model};
 use crate::mdl::{AnalyzedWrenMDL, SessionStateRef, WrenMDL};
 use async_trait::async_trait;
+use datafusion::arrow::compute::kernels::cast_utils::string_to_timestamp_nanos;
 use datafusion::arrow::datatypes::SchemaRef;
 use datafusion::catalog::memory::MemoryCatalogProvider;
 use datafusion::catalog::{MemorySchemaProvider, Session};
 use datafusion::catalog_common::CatalogProvider;
-use datafusion::common::Result;
+use datafusion::common::config::ConfigExtension;
+use datafusion::common::{extensions_options, internal_err, plan_datafusion_err, Result};
 use datafusion::datasource::{TableProvider, TableType, ViewTable};
 use datafusion::execution::session_state::SessionStateBuilder;
-use datafusion::logical_expr::Expr;
+use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
 use datafusion::optimizer::analyzer::expand_wildcard_rule::ExpandWildcardRule;
 use datafusion::optimizer::analyzer::inline_table_scan::InlineTableScan;
 use datafusion::optimizer::analyzer::type_coercion::TypeCoercion;
@@ -45,6 +51,58 @@ use parking_lot::RwLock;
 
 pub type SessionPropertiesRef = Arc<HashMap<String, Option<String>>>;
 
+extensions_options! {
+    /// The options of Wren Engine. Register it by [datafusion::prelude::SessionConfig::with_option_extension]
+    /// and set it by `SET wren.<option> = <value>`.
+    pub struct WrenConfig {
+        /// Hoist the model subqueries used more than once into a WITH clause of the planned SQL
+        pub enable_model_cte: bool, default = false
+        /// Infer the condition of the joins between models from their relationships when the query omits it
+        pub infer_join_path: bool, default = false
+        /// Eliminate the to-one joins of the relation chains whose joined columns are unused. It's only correct if every foreign key refers to an existing row
+        pub eliminate_to_one_join: bool, default = false
+        /// The directory, or the path in the bucket, against which the relative paths of the files referred by the models are resolved
+        pub file_root: String, default = "".to_string()
+        /// The format of the files whose extension doesn't tell it, e.g. `csv`, `parquet`, `json` or `arrow`
+        pub file_format: Option<String>, default = None
+        /// The time to check the freshness of the materializations, e.g. `2024-01-01T00:00:00Z`. If it isn't set, the materializations with a refresh time aren't used
+        pub current_time: Option<String>, default = None
+    }
+}
+
+impl ConfigExtension for WrenConfig {
+    const PREFIX: &'static str = "wren";
+}
+
+impl WrenConfig {
+    /// Get the options registered in the session or the default options.
+    pub fn from_session(ctx: &SessionContext) -> Self {
+        ctx.state()
+            .config_options()
+            .extensions
+            .get::<WrenConfig>()
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The time set by `current_time`. The current time isn't used implicitly, so the planned
+    /// SQL doesn't depend on when it's planned.
+    pub fn current_time(&self) -> Result<Option<SystemTime>> {
+        let Some(current_time) = &self.current_time else {
+            return Ok(None);
+        };
+        let nanos = string_to_timestamp_nanos(current_time).map_err(|e| {
+            plan_datafusion_err!("Invalid current time {current_time}: {e}")
+        })?;
+        let duration = Duration::from_nanos(nanos.unsigned_abs());
+        if nanos >= 0 {
+            Ok(Some(UNIX_EPOCH + duration))
+        } else {
+            Ok(Some(UNIX_EPOCH - duration))
+        }
+    }
+}
+
 /// Apply Wren Rules to the context for sql generation.
 pub async fn create_ctx_with_mdl(
     ctx: &SessionContext,
@@ -52,6 +110,19 @@ pub async fn create_ctx_with_mdl(
     properties: SessionPropertiesRef,
     is_local_runtime: bool,
 ) -> Result<SessionContext> {
+    create_ctx_and_mdl(ctx, analyzed_mdl, properties, is_local_runtime)
+        .await
+        .map(|(ctx, _)| ctx)
+}
+
+/// Create the SessionContext like [create_ctx_with_mdl] and return the MDL whose file
+/// sources are registered for the local runtime.
+pub(crate) async fn create_ctx_and_mdl(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    is_local_runtime: bool,
+) -> Result<(SessionContext, Arc<AnalyzedWrenMDL>)> {
     let config = ctx
         .copied_config()
         .with_create_default_catalog_and_schema(false)
@@ -59,9 +130,16 @@ pub async fn create_ctx_with_mdl(
             analyzed_mdl.wren_mdl.catalog(),
             analyzed_mdl.wren_mdl.schema(),
         );
+    // The object stores of the files are built from the properties of the session
+    let runtime_env = if is_local_runtime {
+        session_runtime_env(&ctx.runtime_env())
+    } else {
+        ctx.runtime_env()
+    };
     let reset_default_catalog_schema = Arc::new(RwLock::new(
         SessionStateBuilder::new_from_existing(ctx.state())
             .with_config(config.clone())
+            .with_runtime_env(runtime_env)
             .build(),
     ));
 
@@ -80,6 +158,18 @@ pub async fn create_ctx_with_mdl(
             .collect::<HashMap<_, _>>(),
     );
 
+    let analyzed_mdl = if is_local_runtime {
+        register_file_sources(
+            reset_default_catalog_schema.read().deref(),
+            analyzed_mdl,
+            &properties,
+            &WrenConfig::from_session(ctx),
+        )
+        .await?
+    } else {
+        analyzed_mdl
+    };
+
     let new_state = if is_local_runtime {
         new_state.with_analyzer_rules(analyze_rule_for_local_runtime(
             Arc::clone(&analyzed_mdl),
@@ -94,13 +184,13 @@ pub async fn create_ctx_with_mdl(
                 reset_default_catalog_schema.clone(),
                 Arc::clone(&properties),
             ))
-            .with_optimizer_rules(optimize_rule_for_unparsing())
+            .with_optimizer_rules(optimize_rule_for_unparsing(Arc::clone(&analyzed_mdl)))
     };
 
     let new_state = new_state.with_config(config).build();
     let ctx = SessionContext::new_with_state(new_state);
     register_table_with_mdl(&ctx, analyzed_mdl.wren_mdl(), properties).await?;
-    Ok(ctx)
+    Ok((ctx, analyzed_mdl))
 }
 
 // Analyzer rules for local runtime
@@ -174,7 +264,9 @@ fn analyze_rule_for_unparsing(
 }
 
 /// Optimizer rules for unparse
-fn optimize_rule_for_unparsing() -> Vec<Arc<dyn OptimizerRule + Send + Sync>> {
+fn optimize_rule_for_unparsing(
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+) -> Vec<Arc<dyn OptimizerRule + Send + Sync>> {
     vec![
         // Disable EliminateNestedUnion because unparser only support unparsing an union with two inputs
         // see https://github.com/apache/datafusion/issues/13621 for details
@@ -188,6 +280,9 @@ fn optimize_rule_for_unparsing() -> Vec<Arc<dyn OptimizerRule + Send + Sync>> {
         // Disable ScalarSubqueryToJoin to avoid generate invalid sql (join without condition)
         // Arc::new(ScalarSubqueryToJoin::new()),
         Arc::new(ExtractEquijoinPredicate::new()),
+        // Must be after ExtractEquijoinPredicate and before EliminateOuterJoin.
+        // It's only applied if `wren.eliminate_to_one_join` is enabled.
+        Arc::new(EliminateToOneJoin::new(analyzed_mdl)),
         // Disable SimplifyExpressions to avoid apply some function locally
         // Arc::new(SimplifyExpressions::new()),
         Arc::new(EliminateDuplicatedExpr::new()),
@@ -231,12 +326,34 @@ pub async fn register_table_with_mdl(
     ctx.register_catalog(&wren_mdl.manifest.catalog, Arc::new(catalog));
 
     for model in wren_mdl.manifest.models.iter() {
-        let table = WrenDataSource::new(Arc::clone(model), &properties)?;
+        let data_source = wren_mdl.model_type_source(model);
+        let table = WrenDataSource::new(Arc::clone(model), &data_source, &properties)?;
         ctx.register_table(
             TableReference::full(wren_mdl.catalog(), wren_mdl.schema(), model.name()),
             Arc::new(table),
         )?;
     }
+    for materialization in wren_mdl.materializations.values().flatten() {
+        let table_ref = TableReference::from(materialization.name.as_str());
+        let options = ctx.state().config_options().catalog.clone();
+        let catalog_name = table_ref.catalog().unwrap_or(&options.default_catalog);
+        let schema_name = table_ref.schema().unwrap_or(&options.default_schema);
+        let catalog = match ctx.catalog(catalog_name) {
+            Some(catalog) => catalog,
+            None => {
+                let catalog: Arc<dyn CatalogProvider> =
+                    Arc::new(MemoryCatalogProvider::new());
+                ctx.register_catalog(catalog_name, Arc::clone(&catalog));
+                catalog
+            }
+        };
+        if catalog.schema(schema_name).is_none() {
+            catalog
+                .register_schema(schema_name, Arc::new(MemorySchemaProvider::new()))?;
+        }
+        let table = WrenDataSource::new_with_schema(materialization.schema(&wren_mdl)?);
+        ctx.register_table(table_ref, Arc::new(table))?;
+    }
     for view in wren_mdl.manifest.views.iter() {
         let plan = ctx.state().create_logical_plan(&view.statement).await?;
         let view_table = ViewTable::try_new(plan, Some(view.statement.clone()))?;
@@ -251,10 +368,16 @@ pub async fn register_table_with_mdl(
 #[derive(Debug)]
 pub struct WrenDataSource {
     schema: SchemaRef,
+    /// The connector scanning the source table. The data source only carries the schema without it.
+    connector: Option<(Arc<dyn Connector>, TableReference)>,
 }
 
 impl WrenDataSource {
-    pub fn new(model: Arc<Model>, properties: &SessionPropertiesRef) -> Result<Self> {
+    pub fn new(
+        model: Arc<Model>,
+        data_source: &DataSource,
+        properties: &SessionPropertiesRef,
+    ) -> Result<Self> {
         let available_columns = model
             .get_physical_columns()
             .iter()
@@ -269,12 +392,33 @@ impl WrenDataSource {
             .into_iter()
             .flatten()
             .collect::<Vec<_>>();
-        let schema = create_schema(available_columns)?;
-        Ok(Self { schema })
+        let schema = create_schema(available_columns, data_source)?;
+        Ok(Self {
+            schema,
+            connector: None,
+        })
     }
 
     pub fn new_with_schema(schema: SchemaRef) -> Self {
-        Self { schema }
+        Self {
+            schema,
+            connector: None,
+        }
+    }
+
+    /// Scan the source table through the connector to make the data source executable.
+    pub fn with_connector(
+        mut self,
+        connector: Arc<dyn Connector>,
+        table_reference: TableReference,
+    ) -> Self {
+        self.connector = Some((connector, table_reference));
+        self
+    }
+
+    /// Return true if the data source can be scanned
+    pub fn is_executable(&self) -> bool {
+        self.connector.is_some()
     }
 }
 
@@ -289,17 +433,49 @@ impl TableProvider for WrenDataSource {
     }
 
     fn table_type(&self) -> TableType {
-        TableType::View
+        if self.is_executable() {
+            TableType::Base
+        } else {
+            TableType::View
+        }
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        match &self.connector {
+            Some((connector, table_reference)) => {
+                connector.supports_filters_pushdown(table_reference, filters)
+            }
+            None => Ok(vec![
+                TableProviderFilterPushDown::Unsupported;
+                filters.len()
+            ]),
+        }
     }
 
     async fn scan(
         &self,
-        _state: &dyn Session,
-        _projection: Option<&Vec<usize>>,
-        // filters and limit can be used here to inject some push-down operations if needed
-        _filters: &[Expr],
-        _limit: Option<usize>,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
     ) -> Result<Arc<dyn ExecutionPlan>> {
-        unreachable!("WrenDataSource should be replaced before physical planning")
+        let Some((connector, table_reference)) = &self.connector else {
+            return internal_err!(
+                "WrenDataSource should be replaced before physical planning"
+            );
+        };
+        connector
+            .scan(
+                state,
+                table_reference,
+                &self.schema,
+                projection,
+                filters,
+                limit,
+            )
+            .await
     }
 }
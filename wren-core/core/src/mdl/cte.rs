@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::tree_node::{Transformed, TreeNodeRecursion};
+use datafusion::common::{internal_datafusion_err, internal_err, Result};
+use datafusion::logical_expr::builder::LogicalTableSource;
+use datafusion::logical_expr::{LogicalPlan, LogicalPlanBuilder, SubqueryAlias};
+use datafusion::sql::sqlparser::ast::{Query, Statement, Visit, Visitor, With};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::unparser::Unparser;
+use datafusion::sql::TableReference;
+
+const CTE_PREFIX: &str = "__cte_";
+
+/// Unparse the plan and hoist the model subqueries that appear more than once into a WITH clause.
+///
+/// A model subquery is a subquery aliased by the name of a model. The repeated ones are
+/// detected by comparing their plans, and every occurrence is replaced by a reference to the
+/// common table expression that keeps its original alias. The names of the common table
+/// expressions don't collide with the tables, the aliases and the existing WITH clause of the
+/// query, and they are placed before the existing ones. Return the statement without the WITH
+/// clause if no subquery is reused.
+pub(crate) fn unparse_with_reused_models(
+    unparser: &Unparser,
+    plan: &LogicalPlan,
+    model_names: &HashSet<&str>,
+) -> Result<Statement> {
+    let statement = unparser.plan_to_sql(plan)?;
+    let mut counts: HashMap<LogicalPlan, usize> = HashMap::new();
+    plan.apply_with_subqueries(|node| {
+        if is_model_subquery(node, model_names) {
+            *counts.entry(node.clone()).or_default() += 1;
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    let reused: HashSet<LogicalPlan> = counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(plan, _)| plan)
+        .collect();
+    if reused.is_empty() {
+        return Ok(statement);
+    }
+
+    let mut taken = cte_names(&statement);
+    plan.apply_with_subqueries(|node| {
+        match node {
+            LogicalPlan::TableScan(scan) => {
+                taken.insert(scan.table_name.table().to_lowercase());
+            }
+            LogicalPlan::SubqueryAlias(subquery_alias) => {
+                taken.insert(subquery_alias.alias.table().to_lowercase());
+            }
+            _ => {}
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+
+    // the name and the plan of the common table expressions in the order of the first use
+    let mut ctes: Vec<(String, Arc<LogicalPlan>)> = vec![];
+    let mut names: HashMap<LogicalPlan, String> = HashMap::new();
+    let rewritten = plan
+        .clone()
+        .transform_down_with_subqueries(|node| {
+            if !reused.contains(&node) {
+                return Ok(Transformed::no(node));
+            }
+            let LogicalPlan::SubqueryAlias(SubqueryAlias { input, alias, .. }) = &node
+            else {
+                return internal_err!("model subquery should be a subquery alias");
+            };
+            let name = match names.get(&node) {
+                Some(name) => name.clone(),
+                None => {
+                    let name = next_cte_name(&mut taken);
+                    ctes.push((name.clone(), Arc::clone(input)));
+                    names.insert(node.clone(), name.clone());
+                    name
+                }
+            };
+            let source =
+                LogicalTableSource::new(Arc::new(input.schema().as_arrow().clone()));
+            let reference = LogicalPlanBuilder::scan(
+                TableReference::bare(name),
+                Arc::new(source),
+                None,
+            )?
+            .alias(alias.clone())?
+            .build()?;
+            // the occurrences inside the hoisted subquery are left as they are
+            Ok(Transformed::new(reference, true, TreeNodeRecursion::Jump))
+        })?
+        .data;
+
+    let mut statement = unparser.plan_to_sql(&rewritten)?;
+    let Statement::Query(query) = &mut statement else {
+        return internal_err!("model subqueries should be in a query");
+    };
+    let ctes = ctes
+        .into_iter()
+        .map(|(name, input)| Ok((name, unparser.plan_to_sql(&input)?.to_string())))
+        .collect::<Result<Vec<_>>>()?;
+    let mut with = parse_with(&ctes)?;
+    match &mut query.with {
+        Some(existing) => {
+            with.cte_tables.append(&mut existing.cte_tables);
+            existing.cte_tables = with.cte_tables;
+        }
+        None => query.with = Some(with),
+    }
+    Ok(statement)
+}
+
+fn is_model_subquery(plan: &LogicalPlan, model_names: &HashSet<&str>) -> bool {
+    matches!(
+        plan,
+        LogicalPlan::SubqueryAlias(subquery_alias)
+            if model_names.contains(subquery_alias.alias.table())
+    )
+}
+
+/// Generate the next name that isn't taken. The names are compared case-insensitively.
+fn next_cte_name(taken: &mut HashSet<String>) -> String {
+    let mut index = 1;
+    loop {
+        let name = format!("{CTE_PREFIX}{index}");
+        if taken.insert(name.clone()) {
+            return name;
+        }
+        index += 1;
+    }
+}
+
+/// Parse the common table expressions into a WITH clause
+fn parse_with(ctes: &[(String, String)]) -> Result<With> {
+    let ctes = ctes
+        .iter()
+        .map(|(name, query)| format!("{name} AS ({query})"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let query = Parser::new(&GenericDialect {})
+        .try_with_sql(&format!("WITH {ctes} SELECT 1"))
+        .and_then(|mut parser| parser.parse_query())?;
+    query
+        .with
+        .ok_or_else(|| internal_datafusion_err!("expected a WITH clause"))
+}
+
+/// Collect the names of the common table expressions of all the WITH clauses in the statement
+fn cte_names(statement: &Statement) -> HashSet<String> {
+    let mut collector = CteNameCollector {
+        names: HashSet::new(),
+    };
+    let _ = statement.visit(&mut collector);
+    collector.names
+}
+
+struct CteNameCollector {
+    names: HashSet<String>,
+}
+
+impl Visitor for CteNameCollector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            self.names.extend(
+                with.cte_tables
+                    .iter()
+                    .map(|cte| cte.alias.name.value.to_lowercase()),
+            );
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch};
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::unparser::Unparser;
+
+    use crate::mdl::cte::unparse_with_reused_models;
+
+    fn context() -> Result<SessionContext> {
+        let ctx = SessionContext::new();
+        let a: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        ctx.register_batch("t", RecordBatch::try_from_iter(vec![("a", a)])?)?;
+        Ok(ctx)
+    }
+
+    async fn hoist(ctx: &SessionContext, sql: &str) -> Result<String> {
+        let plan = ctx.sql(sql).await?.into_unoptimized_plan();
+        let statement = unparse_with_reused_models(
+            &Unparser::default(),
+            &plan,
+            &["m"].into_iter().collect(),
+        )?;
+        Ok(statement.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_hoist_reused_model_subqueries() -> Result<()> {
+        let ctx = context()?;
+        let sql = "SELECT m.a FROM (SELECT a FROM t) AS m \
+            WHERE m.a IN (SELECT m.a FROM (SELECT a FROM t) AS m)";
+        let actual = hoist(&ctx, sql).await?;
+        assert!(actual.starts_with("WITH __cte_1 AS ("), "{actual}");
+        assert_eq!(actual.matches("FROM t").count(), 1, "{actual}");
+        assert_eq!(actual.matches("FROM __cte_1 AS m").count(), 2, "{actual}");
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        // the subquery used once isn't hoisted
+        let sql = "SELECT m.a FROM (SELECT a FROM t) AS m";
+        assert!(!hoist(&ctx, sql).await?.starts_with("WITH"));
+
+        // the different plans aren't hoisted
+        let sql = "SELECT m.a FROM (SELECT a FROM t) AS m \
+            WHERE m.a IN (SELECT m.a FROM (SELECT a FROM t WHERE a > 1) AS m)";
+        assert!(!hoist(&ctx, sql).await?.starts_with("WITH"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hoist_with_taken_name() -> Result<()> {
+        let ctx = context()?;
+        let sql = "WITH __cte_1 AS (SELECT 1 AS x) \
+            SELECT m.a, __cte_1.x FROM (SELECT a FROM t) AS m, __cte_1 \
+            WHERE m.a IN (SELECT m.a FROM (SELECT a FROM t) AS m)";
+        let actual = hoist(&ctx, sql).await?;
+        assert!(actual.starts_with("WITH __cte_2 AS ("), "{actual}");
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(batches.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        Ok(())
+    }
+}
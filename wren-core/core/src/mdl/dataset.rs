@@ -1,4 +1,4 @@
-use crate::mdl::manifest::{Metric, Model};
+use crate::mdl::manifest::{DataSource, Metric, Model};
 use crate::mdl::utils::{quoted, to_field, to_remote_field};
 use crate::mdl::{RegisterTables, SessionStateRef};
 use datafusion::arrow::datatypes::Field;
@@ -28,13 +28,15 @@ impl Dataset {
         }
     }
 
-    pub fn to_qualified_schema(&self) -> Result<DFSchema> {
+    /// Create the schema with the model name. The types of the columns are mapped by the type
+    /// names of the data source.
+    pub fn to_qualified_schema(&self, data_source: &DataSource) -> Result<DFSchema> {
         match self {
             Dataset::Model(model) => {
                 let fields: Vec<_> = model
                     .get_physical_columns()
                     .iter()
-                    .map(|c| to_field(c))
+                    .map(|c| to_field(c, data_source))
                     .collect::<Result<_>>()?;
                 let arrow_schema = datafusion::arrow::datatypes::Schema::new(fields);
                 DFSchema::try_from_qualified_schema(quoted(&model.name), &arrow_schema)
@@ -47,6 +49,7 @@ impl Dataset {
     pub fn to_remote_schema(
         &self,
         register_tables: Option<&RegisterTables>,
+        data_source: &DataSource,
         session_state: SessionStateRef,
     ) -> Result<DFSchema> {
         match self {
@@ -63,7 +66,9 @@ impl Dataset {
                         .get_physical_columns()
                         .iter()
                         .filter(|c| !c.is_calculated)
-                        .map(|c| to_remote_field(c, Arc::clone(&session_state)))
+                        .map(|c| {
+                            to_remote_field(c, data_source, Arc::clone(&session_state))
+                        })
                         .collect::<Result<Vec<Vec<Field>>>>()?
                         .iter()
                         .flat_map(|c| c.clone())
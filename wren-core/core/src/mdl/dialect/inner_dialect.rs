@@ -17,13 +17,21 @@
  * under the License.
  */
 
-use crate::mdl::dialect::utils::scalar_function_to_sql_internal;
+use crate::mdl::dialect::utils::{
+    date_trunc_args_to_sql, function_to_sql, interval_parts,
+    scalar_function_to_sql_internal, template_to_sql, type_name, unnamed_arg,
+};
 use crate::mdl::manifest::DataSource;
-use datafusion::common::Result;
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
+use datafusion::common::{plan_err, Result};
 use datafusion::logical_expr::sqlparser::keywords::ALL_KEYWORDS;
 use datafusion::logical_expr::Expr;
 
 use datafusion::sql::sqlparser::ast;
+use datafusion::sql::sqlparser::dialect::{
+    BigQueryDialect as BigQueryParserDialect, GenericDialect as GenericParserDialect,
+    MySqlDialect as MySqlParserDialect, PostgreSqlDialect,
+};
 use datafusion::sql::unparser::Unparser;
 use regex::Regex;
 
@@ -48,6 +56,13 @@ pub trait InnerDialect: Send + Sync {
     fn identifier_quote_style(&self, _identifier: &str) -> Option<char> {
         None
     }
+
+    /// This method is used to override the type name of the casts, e.g. `STRING` for BigQuery.
+    /// Only the types which have a cast hook in [datafusion::sql::unparser::dialect::Dialect]
+    /// are asked. If the type is not rewritten, it should return `None`.
+    fn cast_dtype_overrides(&self, _data_type: &DataType) -> Option<ast::DataType> {
+        None
+    }
 }
 
 /// [get_inner_dialect] returns the suitable InnerDialect for the given data source.
@@ -56,6 +71,11 @@ pub fn get_inner_dialect(data_source: &DataSource) -> Box<dyn InnerDialect> {
         DataSource::MySQL => Box::new(MySQLDialect {}),
         DataSource::BigQuery => Box::new(BigQueryDialect {}),
         DataSource::Oracle => Box::new(OracleDialect {}),
+        DataSource::Postgres => Box::new(PostgresDialect {}),
+        DataSource::MSSQL => Box::new(MSSQLDialect {}),
+        DataSource::Snowflake => Box::new(SnowflakeDialect {}),
+        DataSource::Clickhouse => Box::new(ClickHouseDialect {}),
+        DataSource::Trino | DataSource::Athena => Box::new(TrinoDialect {}),
         _ => Box::new(GenericDialect {}),
     }
 }
@@ -78,22 +98,236 @@ impl InnerDialect for MySQLDialect {
     ) -> Result<Option<ast::Expr>> {
         match function_name {
             "btrim" => scalar_function_to_sql_internal(unparser, "trim", args),
+            "date_trunc" => {
+                let Some((unit, expr)) = date_trunc_args_to_sql(unparser, args)? else {
+                    return Ok(None);
+                };
+                // MySQL doesn't have date_trunc. Format the truncated part and convert it back.
+                let format = match unit.as_str() {
+                    "year" => "%Y-01-01 00:00:00",
+                    "month" => "%Y-%m-01 00:00:00",
+                    "day" => "%Y-%m-%d 00:00:00",
+                    "hour" => "%Y-%m-%d %H:00:00",
+                    "minute" => "%Y-%m-%d %H:%i:00",
+                    "second" => "%Y-%m-%d %H:%i:%s",
+                    // The week of DataFusion starts on Monday, and WEEKDAY of Monday is 0
+                    "quarter" | "week" => {
+                        let template = if unit == "quarter" {
+                            "TIMESTAMP(DATE_ADD(MAKEDATE(YEAR(__expr), 1), INTERVAL QUARTER(__expr) - 1 QUARTER))"
+                        } else {
+                            "TIMESTAMP(DATE_SUB(DATE(__expr), INTERVAL WEEKDAY(__expr) DAY))"
+                        };
+                        return Ok(Some(template_to_sql(
+                            &MySqlParserDialect {},
+                            template,
+                            vec![("__expr", expr)],
+                        )?));
+                    }
+                    _ => {
+                        return plan_err!(
+                            "Unsupported unit {} of date_trunc for MySQL",
+                            unit
+                        )
+                    }
+                };
+                let formatted = function_to_sql(
+                    "DATE_FORMAT",
+                    vec![
+                        unnamed_arg(expr),
+                        unnamed_arg(ast::Expr::Value(ast::Value::SingleQuotedString(
+                            format.to_string(),
+                        ))),
+                    ],
+                );
+                Ok(Some(function_to_sql(
+                    "TIMESTAMP",
+                    vec![unnamed_arg(formatted)],
+                )))
+            }
             _ => Ok(None),
         }
     }
+
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        // CAST of MySQL only accepts a few types
+        match data_type {
+            DataType::Utf8 | DataType::LargeUtf8 => Some(type_name("CHAR", &[])),
+            DataType::Int64 | DataType::Int32 => Some(type_name("SIGNED", &[])),
+            // MySQL can't cast to a time zone aware type. Casting it to DATETIME would drop
+            // the time zone, so the default type is kept and the cast is rejected by MySQL.
+            DataType::Timestamp(_, None) => Some(type_name("DATETIME", &[])),
+            _ => None,
+        }
+    }
 }
 
 pub struct BigQueryDialect {}
 
 impl InnerDialect for BigQueryDialect {
+    fn scalar_function_to_sql_overrides(
+        &self,
+        unparser: &Unparser,
+        function_name: &str,
+        args: &[Expr],
+    ) -> Result<Option<ast::Expr>> {
+        match function_name {
+            "date_trunc" => {
+                let Some((unit, expr)) = date_trunc_args_to_sql(unparser, args)? else {
+                    return Ok(None);
+                };
+                // The week of DataFusion starts on Monday
+                let part = match unit.as_str() {
+                    "week" => "ISOWEEK".to_string(),
+                    _ => unit.to_uppercase(),
+                };
+                Ok(Some(function_to_sql(
+                    "TIMESTAMP_TRUNC",
+                    vec![
+                        unnamed_arg(expr),
+                        unnamed_arg(ast::Expr::Identifier(ast::Ident::new(part))),
+                    ],
+                )))
+            }
+            // The date spine of the metrics
+            "generate_series" => {
+                let [start, end, step] = args else {
+                    return Ok(None);
+                };
+                let Some((value, unit)) = interval_parts(step) else {
+                    return Ok(None);
+                };
+                // GENERATE_TIMESTAMP_ARRAY doesn't support the interval longer than a day
+                let template = match unit {
+                    "MONTH" => format!(
+                        "ARRAY(SELECT TIMESTAMP(d) FROM UNNEST(GENERATE_DATE_ARRAY(DATE(__start), DATE(__end), INTERVAL {value} MONTH)) AS d)"
+                    ),
+                    _ => format!(
+                        "GENERATE_TIMESTAMP_ARRAY(__start, __end, INTERVAL {value} {unit})"
+                    ),
+                };
+                Ok(Some(template_to_sql(
+                    &BigQueryParserDialect {},
+                    &template,
+                    vec![
+                        ("__start", unparser.expr_to_sql(start)?),
+                        ("__end", unparser.expr_to_sql(end)?),
+                    ],
+                )?))
+            }
+            _ => Ok(None),
+        }
+    }
+
     fn unnest_as_table_factor(&self) -> bool {
         true
     }
+
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Utf8 | DataType::LargeUtf8 => Some(type_name("STRING", &[])),
+            DataType::Int64 | DataType::Int32 => Some(type_name("INT64", &[])),
+            DataType::Float64 => Some(type_name("FLOAT64", &[])),
+            // the timestamp of BigQuery is an absolute point in time, and the datetime is
+            // the civil time without a time zone
+            DataType::Timestamp(_, None) => Some(type_name("DATETIME", &[])),
+            DataType::Timestamp(_, Some(_)) => Some(type_name("TIMESTAMP", &[])),
+            _ => None,
+        }
+    }
+}
+
+pub struct PostgresDialect {}
+
+impl InnerDialect for PostgresDialect {
+    fn scalar_function_to_sql_overrides(
+        &self,
+        unparser: &Unparser,
+        function_name: &str,
+        args: &[Expr],
+    ) -> Result<Option<ast::Expr>> {
+        match function_name {
+            // generate_series of PostgreSQL returns a set instead of an array
+            "generate_series" => {
+                let [start, end, step] = args else {
+                    return Ok(None);
+                };
+                let Some((value, unit)) = interval_parts(step) else {
+                    return Ok(None);
+                };
+                Ok(Some(template_to_sql(
+                    &PostgreSqlDialect {},
+                    &format!(
+                        "ARRAY(SELECT generate_series(__start, __end, INTERVAL '{value} {unit}'))"
+                    ),
+                    vec![
+                        ("__start", unparser.expr_to_sql(start)?),
+                        ("__end", unparser.expr_to_sql(end)?),
+                    ],
+                )?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Float64 => Some(type_name("DOUBLE PRECISION", &[])),
+            _ => None,
+        }
+    }
 }
 
 pub struct OracleDialect {}
 
 impl InnerDialect for OracleDialect {
+    fn scalar_function_to_sql_overrides(
+        &self,
+        unparser: &Unparser,
+        function_name: &str,
+        args: &[Expr],
+    ) -> Result<Option<ast::Expr>> {
+        match function_name {
+            "date_trunc" => {
+                let Some((unit, expr)) = date_trunc_args_to_sql(unparser, args)? else {
+                    return Ok(None);
+                };
+                let format = match unit.as_str() {
+                    "year" => "YYYY",
+                    "quarter" => "Q",
+                    "month" => "MM",
+                    "week" => "IW",
+                    "day" => "DD",
+                    "hour" => "HH",
+                    "minute" => "MI",
+                    // TRUNC doesn't support the second. The DATE of Oracle drops the fraction.
+                    "second" => {
+                        return Ok(Some(template_to_sql(
+                            &GenericParserDialect {},
+                            "CAST(CAST(__expr AS DATE) AS TIMESTAMP)",
+                            vec![("__expr", expr)],
+                        )?));
+                    }
+                    _ => {
+                        return plan_err!(
+                            "Unsupported unit {} of date_trunc for Oracle",
+                            unit
+                        )
+                    }
+                };
+                Ok(Some(function_to_sql(
+                    "TRUNC",
+                    vec![
+                        unnamed_arg(expr),
+                        unnamed_arg(ast::Expr::Value(ast::Value::SingleQuotedString(
+                            format.to_string(),
+                        ))),
+                    ],
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
     fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
         // Oracle defaults to upper case for identifiers
         let identifier_regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
@@ -106,6 +340,86 @@ impl InnerDialect for OracleDialect {
             None
         }
     }
+
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Utf8 => Some(type_name("VARCHAR2", &["4000"])),
+            DataType::LargeUtf8 => Some(type_name("CLOB", &[])),
+            DataType::Int64 => Some(type_name("NUMBER", &["19"])),
+            DataType::Int32 => Some(type_name("NUMBER", &["10"])),
+            DataType::Float64 => Some(type_name("BINARY_DOUBLE", &[])),
+            _ => None,
+        }
+    }
+}
+
+pub struct MSSQLDialect {}
+
+impl InnerDialect for MSSQLDialect {
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Utf8 | DataType::LargeUtf8 => Some(type_name("NVARCHAR", &["MAX"])),
+            DataType::Int32 => Some(type_name("INT", &[])),
+            DataType::Float64 => Some(type_name("FLOAT", &[])),
+            DataType::Timestamp(_, None) => Some(type_name("DATETIME2", &[])),
+            DataType::Timestamp(_, Some(_)) => Some(type_name("DATETIMEOFFSET", &[])),
+            _ => None,
+        }
+    }
+}
+
+pub struct SnowflakeDialect {}
+
+impl InnerDialect for SnowflakeDialect {
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Timestamp(_, None) => Some(type_name("TIMESTAMP_NTZ", &[])),
+            DataType::Timestamp(_, Some(_)) => Some(type_name("TIMESTAMP_TZ", &[])),
+            _ => None,
+        }
+    }
+}
+
+pub struct ClickHouseDialect {}
+
+impl InnerDialect for ClickHouseDialect {
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            DataType::Utf8 | DataType::LargeUtf8 => Some(type_name("String", &[])),
+            DataType::Int64 => Some(type_name("Int64", &[])),
+            DataType::Int32 => Some(type_name("Int32", &[])),
+            DataType::Float64 => Some(type_name("Float64", &[])),
+            DataType::Timestamp(unit, tz) => {
+                let precision = match unit {
+                    TimeUnit::Second => "0",
+                    TimeUnit::Millisecond => "3",
+                    TimeUnit::Microsecond => "6",
+                    TimeUnit::Nanosecond => "9",
+                };
+                match tz {
+                    Some(tz) => {
+                        let tz = format!("'{}'", tz.replace('\'', "''"));
+                        Some(type_name("DateTime64", &[precision, tz.as_str()]))
+                    }
+                    None => Some(type_name("DateTime64", &[precision])),
+                }
+            }
+            DataType::Date32 => Some(type_name("Date32", &[])),
+            _ => None,
+        }
+    }
+}
+
+pub struct TrinoDialect {}
+
+impl InnerDialect for TrinoDialect {
+    fn cast_dtype_overrides(&self, data_type: &DataType) -> Option<ast::DataType> {
+        match data_type {
+            // Trino doesn't have the TEXT type
+            DataType::LargeUtf8 => Some(type_name("VARCHAR", &[])),
+            _ => None,
+        }
+    }
 }
 
 fn non_uppercase(sql: &str) -> bool {
@@ -16,10 +16,16 @@
  * specific language governing permissions and limitations
  * under the License.
  */
-use datafusion::common::Result;
+use std::ops::ControlFlow;
+
+use datafusion::common::{Result, ScalarValue};
 use datafusion::logical_expr::Expr;
 use datafusion::sql::sqlparser::ast;
-use datafusion::sql::sqlparser::ast::{Function, Ident, ObjectName};
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, Function, Ident, ObjectName,
+};
+use datafusion::sql::sqlparser::dialect::Dialect;
+use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::sqlparser::tokenizer::Span;
 use datafusion::sql::unparser::Unparser;
 
@@ -54,7 +60,23 @@ pub(crate) fn scalar_function_to_sql_internal(
     args: &[Expr],
 ) -> Result<Option<ast::Expr>> {
     let args = function_args_to_sql(unparser, args)?;
-    Ok(Some(ast::Expr::Function(Function {
+    Ok(Some(function_to_sql(func_name, args)))
+}
+
+/// Unparse the `date_trunc` function and return the time unit and the unparsed expression.
+/// Return None if the time unit isn't a literal string.
+pub(crate) fn date_trunc_args_to_sql(
+    unparser: &Unparser,
+    args: &[Expr],
+) -> Result<Option<(String, ast::Expr)>> {
+    let [Expr::Literal(ScalarValue::Utf8(Some(unit))), expr] = args else {
+        return Ok(None);
+    };
+    Ok(Some((unit.to_lowercase(), unparser.expr_to_sql(expr)?)))
+}
+
+pub(crate) fn function_to_sql(func_name: &str, args: Vec<ast::FunctionArg>) -> ast::Expr {
+    ast::Expr::Function(Function {
         name: ObjectName(vec![Ident {
             value: func_name.to_string(),
             quote_style: None,
@@ -71,5 +93,80 @@ pub(crate) fn scalar_function_to_sql_internal(
         within_group: vec![],
         parameters: ast::FunctionArguments::None,
         uses_odbc_syntax: false,
-    })))
+    })
+}
+
+/// Build the type name of the data source with the modifiers, e.g. `NVARCHAR(MAX)`
+pub(crate) fn type_name(name: &str, modifiers: &[&str]) -> ast::DataType {
+    ast::DataType::Custom(
+        ObjectName(vec![Ident {
+            value: name.to_string(),
+            quote_style: None,
+            span: Span::empty(),
+        }]),
+        modifiers.iter().map(|m| m.to_string()).collect(),
+    )
+}
+
+pub(crate) fn unnamed_arg(expr: ast::Expr) -> ast::FunctionArg {
+    ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(expr))
+}
+
+/// Return the value and the unit of an interval literal with a single unit.
+/// e.g. `INTERVAL '3' MONTH` returns `(3, "MONTH")`.
+pub(crate) fn interval_parts(expr: &Expr) -> Option<(i64, &'static str)> {
+    match expr {
+        Expr::Literal(ScalarValue::IntervalMonthDayNano(Some(interval))) => {
+            match (interval.months, interval.days, interval.nanoseconds) {
+                (months, 0, 0) if months != 0 => Some((months as i64, "MONTH")),
+                (0, days, 0) if days != 0 => Some((days as i64, "DAY")),
+                (0, 0, nanoseconds) => nanoseconds_parts(nanoseconds),
+                _ => None,
+            }
+        }
+        Expr::Literal(ScalarValue::IntervalYearMonth(Some(months))) if *months != 0 => {
+            Some((*months as i64, "MONTH"))
+        }
+        Expr::Literal(ScalarValue::IntervalDayTime(Some(interval))) => {
+            match (interval.days, interval.milliseconds) {
+                (days, 0) if days != 0 => Some((days as i64, "DAY")),
+                (0, milliseconds) => nanoseconds_parts(milliseconds as i64 * 1_000_000),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn nanoseconds_parts(nanoseconds: i64) -> Option<(i64, &'static str)> {
+    const SECOND: i64 = 1_000_000_000;
+    [
+        (3600 * SECOND, "HOUR"),
+        (60 * SECOND, "MINUTE"),
+        (SECOND, "SECOND"),
+    ]
+    .into_iter()
+    .find(|(unit, _)| nanoseconds != 0 && nanoseconds % unit == 0)
+    .map(|(unit, name)| (nanoseconds / unit, name))
+}
+
+/// Parse the SQL template in the dialect and replace the placeholder identifiers with the
+/// unparsed expressions. The expressions aren't parsed again, so their quote style is kept.
+pub(crate) fn template_to_sql(
+    dialect: &dyn Dialect,
+    template: &str,
+    placeholders: Vec<(&str, ast::Expr)>,
+) -> Result<ast::Expr> {
+    let mut expr = Parser::new(dialect).try_with_sql(template)?.parse_expr()?;
+    let _ = visit_expressions_mut(&mut expr, |expr| {
+        if let ast::Expr::Identifier(ident) = expr {
+            if let Some((_, value)) =
+                placeholders.iter().find(|(name, _)| *name == ident.value)
+            {
+                *expr = value.clone();
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(expr)
 }
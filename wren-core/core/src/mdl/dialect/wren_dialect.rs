@@ -18,6 +18,7 @@
  */
 use crate::mdl::dialect::inner_dialect::{get_inner_dialect, InnerDialect};
 use crate::mdl::manifest::DataSource;
+use datafusion::arrow::datatypes::{DataType, TimeUnit};
 use datafusion::common::{internal_err, plan_err, Result, ScalarValue};
 use datafusion::logical_expr::sqlparser::ast::{Ident, Subscript};
 use datafusion::logical_expr::sqlparser::keywords::ALL_KEYWORDS;
@@ -25,9 +26,10 @@ use datafusion::logical_expr::Expr;
 use datafusion::sql::sqlparser::ast;
 use datafusion::sql::sqlparser::ast::{AccessExpr, Array, Value};
 use datafusion::sql::sqlparser::tokenizer::Span;
-use datafusion::sql::unparser::dialect::{Dialect, IntervalStyle};
+use datafusion::sql::unparser::dialect::{DefaultDialect, Dialect, IntervalStyle};
 use datafusion::sql::unparser::Unparser;
 use regex::Regex;
+use std::sync::Arc;
 
 /// WrenDialect is a dialect for Wren engine. Handle the identifier quote style based on the
 /// original Datafusion Dialect implementation but with more strict rules.
@@ -91,6 +93,52 @@ impl Dialect for WrenDialect {
     fn unnest_as_table_factor(&self) -> bool {
         self.inner_dialect.unnest_as_table_factor()
     }
+
+    fn float64_ast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Float64)
+            .unwrap_or_else(|| DefaultDialect {}.float64_ast_dtype())
+    }
+
+    fn utf8_cast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Utf8)
+            .unwrap_or_else(|| DefaultDialect {}.utf8_cast_dtype())
+    }
+
+    fn large_utf8_cast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::LargeUtf8)
+            .unwrap_or_else(|| DefaultDialect {}.large_utf8_cast_dtype())
+    }
+
+    fn int64_cast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Int64)
+            .unwrap_or_else(|| DefaultDialect {}.int64_cast_dtype())
+    }
+
+    fn int32_cast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Int32)
+            .unwrap_or_else(|| DefaultDialect {}.int32_cast_dtype())
+    }
+
+    fn timestamp_cast_dtype(
+        &self,
+        time_unit: &TimeUnit,
+        tz: &Option<Arc<str>>,
+    ) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Timestamp(*time_unit, tz.clone()))
+            .unwrap_or_else(|| DefaultDialect {}.timestamp_cast_dtype(time_unit, tz))
+    }
+
+    fn date32_cast_dtype(&self) -> ast::DataType {
+        self.inner_dialect
+            .cast_dtype_overrides(&DataType::Date32)
+            .unwrap_or_else(|| DefaultDialect {}.date32_cast_dtype())
+    }
 }
 
 impl Default for WrenDialect {
@@ -202,3 +250,78 @@ fn non_lowercase(sql: &str) -> bool {
     let lowercase = sql.to_lowercase();
     lowercase != sql
 }
+
+#[cfg(test)]
+mod test {
+    use datafusion::arrow::datatypes::{DataType, TimeUnit};
+    use datafusion::common::Result;
+    use datafusion::logical_expr::{cast, lit};
+    use datafusion::sql::unparser::Unparser;
+    use insta::assert_snapshot;
+
+    use crate::mdl::dialect::WrenDialect;
+    use crate::mdl::manifest::DataSource;
+
+    #[test]
+    fn test_cast_type_names() -> Result<()> {
+        let data_types = [
+            DataType::Utf8,
+            DataType::LargeUtf8,
+            DataType::Int64,
+            DataType::Int32,
+            DataType::Float64,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+            DataType::Date32,
+        ];
+        let mut lines = vec![];
+        for data_source in [
+            DataSource::BigQuery,
+            DataSource::Clickhouse,
+            DataSource::Canner,
+            DataSource::Trino,
+            DataSource::MSSQL,
+            DataSource::MySQL,
+            DataSource::Postgres,
+            DataSource::Snowflake,
+            DataSource::Datafusion,
+            DataSource::DuckDB,
+            DataSource::LocalFile,
+            DataSource::S3File,
+            DataSource::GcsFile,
+            DataSource::MinioFile,
+            DataSource::Oracle,
+            DataSource::Athena,
+        ] {
+            let dialect = WrenDialect::new(&data_source);
+            let unparser = Unparser::new(&dialect);
+            let casts = data_types
+                .iter()
+                .map(|data_type| {
+                    let sql = unparser.expr_to_sql(&cast(lit("a"), data_type.clone()))?;
+                    Ok(sql.to_string())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            lines.push(format!("{data_source}: {}", casts.join(", ")));
+        }
+        assert_snapshot!(lines.join("\n"), @r"
+        BIGQUERY: CAST('a' AS STRING), CAST('a' AS STRING), CAST('a' AS INT64), CAST('a' AS INT64), CAST('a' AS FLOAT64), CAST('a' AS DATETIME), CAST('a' AS TIMESTAMP), CAST('a' AS DATE)
+        CLICKHOUSE: CAST('a' AS String), CAST('a' AS String), CAST('a' AS Int64), CAST('a' AS Int32), CAST('a' AS Float64), CAST('a' AS DateTime64(9)), CAST('a' AS DateTime64(3, 'UTC')), CAST('a' AS Date32)
+        CANNER: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        TRINO: CAST('a' AS VARCHAR), CAST('a' AS VARCHAR), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        MSSQL: CAST('a' AS NVARCHAR(MAX)), CAST('a' AS NVARCHAR(MAX)), CAST('a' AS BIGINT), CAST('a' AS INT), CAST('a' AS FLOAT), CAST('a' AS DATETIME2), CAST('a' AS DATETIMEOFFSET), CAST('a' AS DATE)
+        MYSQL: CAST('a' AS CHAR), CAST('a' AS CHAR), CAST('a' AS SIGNED), CAST('a' AS SIGNED), CAST('a' AS DOUBLE), CAST('a' AS DATETIME), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        POSTGRES: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE PRECISION), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        SNOWFLAKE: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP_NTZ), CAST('a' AS TIMESTAMP_TZ), CAST('a' AS DATE)
+        DATAFUSION: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        DUCKDB: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        LOCAL_FILE: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        S3_FILE: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        GCS_FILE: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        MINIO_FILE: CAST('a' AS VARCHAR), CAST('a' AS TEXT), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        ORACLE: CAST('a' AS VARCHAR2(4000)), CAST('a' AS CLOB), CAST('a' AS NUMBER(19)), CAST('a' AS NUMBER(10)), CAST('a' AS BINARY_DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        ATHENA: CAST('a' AS VARCHAR), CAST('a' AS VARCHAR), CAST('a' AS BIGINT), CAST('a' AS INTEGER), CAST('a' AS DOUBLE), CAST('a' AS TIMESTAMP), CAST('a' AS TIMESTAMP WITH TIME ZONE), CAST('a' AS DATE)
+        ");
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Detect the drift between the columns declared by the models and the physical tables.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use datafusion::arrow::datatypes::{DataType, SchemaRef};
+use datafusion::sql::TableReference;
+
+use crate::logical_plan::utils::map_data_type_by_data_source;
+use crate::mdl::manifest::{DataSource, Manifest, Model};
+use crate::mdl::{RegisterTables, WrenMDL};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaDrift {
+    /// The source table of the model isn't found
+    MissingTable {
+        model: String,
+        table_reference: String,
+    },
+    /// The declared type doesn't match the physical type. `declared` is the type of the column
+    /// in the manifest.
+    TypeMismatch {
+        model: String,
+        column: String,
+        declared: String,
+        physical: DataType,
+    },
+    /// The physical column isn't declared by the model
+    UndeclaredColumn {
+        model: String,
+        column: String,
+        physical: DataType,
+    },
+    /// The declared column doesn't exist in the physical table
+    MissingColumn { model: String, column: String },
+}
+
+impl Display for SchemaDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaDrift::MissingTable {
+                model,
+                table_reference,
+            } => write!(f, "{model}: table {table_reference} not found"),
+            SchemaDrift::TypeMismatch {
+                model,
+                column,
+                declared,
+                physical,
+            } => write!(
+                f,
+                "{model}.{column}: declared as {declared} but the physical type is {physical}"
+            ),
+            SchemaDrift::UndeclaredColumn {
+                model,
+                column,
+                physical,
+            } => write!(f, "{model}.{column}: {physical} column isn't declared"),
+            SchemaDrift::MissingColumn { model, column } => {
+                write!(f, "{model}.{column}: column not found in the physical table")
+            }
+        }
+    }
+}
+
+/// Compare the models with the registered tables keyed by their table references. The models
+/// without a table reference and the columns without a source column, e.g. the calculated
+/// fields, are skipped.
+pub fn detect_schema_drift(
+    manifest: &Manifest,
+    register_tables: &RegisterTables,
+) -> Vec<SchemaDrift> {
+    let schemas = register_tables
+        .iter()
+        .map(|(name, table)| (name.clone(), table.schema()))
+        .collect();
+    detect_schema_drift_by_schemas(manifest, &schemas)
+}
+
+/// Compare the models with the schemas of the physical tables keyed by their table references
+pub fn detect_schema_drift_by_schemas(
+    manifest: &Manifest,
+    schemas: &HashMap<String, SchemaRef>,
+) -> Vec<SchemaDrift> {
+    let schemas = schemas
+        .iter()
+        .map(|(name, schema)| (TableReference::from(name.as_str()), schema))
+        .collect::<HashMap<_, _>>();
+    manifest
+        .models
+        .iter()
+        .filter(|model| !model.table_reference().is_empty())
+        .flat_map(|model| {
+            let table_reference = TableReference::from(model.table_reference());
+            // the declared types are the type names of the data source
+            let data_source = model
+                .data_source()
+                .or(manifest.data_source)
+                .unwrap_or_default();
+            match schemas.get(&table_reference) {
+                Some(schema) => detect_model_drift(model, &data_source, schema),
+                None => vec![SchemaDrift::MissingTable {
+                    model: model.name().to_string(),
+                    table_reference: model.table_reference().to_string(),
+                }],
+            }
+        })
+        .collect()
+}
+
+fn detect_model_drift(
+    model: &Model,
+    data_source: &DataSource,
+    schema: &SchemaRef,
+) -> Vec<SchemaDrift> {
+    let mut drifts = vec![];
+    let mut declared_columns = vec![];
+    for column in &model.columns {
+        // the source column name may be different from the column name
+        let Ok(Some(source)) = WrenMDL::infer_source_column(column, data_source) else {
+            continue;
+        };
+        declared_columns.push(source.name().clone());
+        let Ok(physical) = schema.field_with_name(source.name()) else {
+            drifts.push(SchemaDrift::MissingColumn {
+                model: model.name().to_string(),
+                column: column.name.clone(),
+            });
+            continue;
+        };
+        let matched = map_data_type_by_data_source(&column.r#type, data_source)
+            .map(|declared| is_compatible(&declared, physical.data_type()))
+            .unwrap_or(false);
+        if !matched {
+            drifts.push(SchemaDrift::TypeMismatch {
+                model: model.name().to_string(),
+                column: column.name.clone(),
+                declared: column.r#type.clone(),
+                physical: physical.data_type().clone(),
+            });
+        }
+    }
+    schema
+        .fields()
+        .iter()
+        .filter(|field| !declared_columns.contains(field.name()))
+        .for_each(|field| {
+            drifts.push(SchemaDrift::UndeclaredColumn {
+                model: model.name().to_string(),
+                column: field.name().clone(),
+                physical: field.data_type().clone(),
+            })
+        });
+    drifts
+}
+
+/// The types are compatible if they're equal or only the representation is different, e.g.
+/// the string types, the precisions of the decimals and the units of the timestamps.
+fn is_compatible(declared: &DataType, physical: &DataType) -> bool {
+    match (declared, physical) {
+        (declared, physical) if declared == physical => true,
+        (DataType::Dictionary(_, declared), physical) => {
+            is_compatible(declared, physical)
+        }
+        (declared, DataType::Dictionary(_, physical)) => {
+            is_compatible(declared, physical)
+        }
+        (
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View,
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View,
+        ) => true,
+        (
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView,
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView,
+        ) => true,
+        (
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _),
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _),
+        ) => true,
+        (DataType::Timestamp(_, declared), DataType::Timestamp(_, physical)) => {
+            declared.is_some() == physical.is_some()
+        }
+        (
+            DataType::Time32(_) | DataType::Time64(_),
+            DataType::Time32(_) | DataType::Time64(_),
+        ) => true,
+        (
+            DataType::List(declared) | DataType::LargeList(declared),
+            DataType::List(physical) | DataType::LargeList(physical),
+        ) => is_compatible(declared.data_type(), physical.data_type()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use datafusion::datasource::empty::EmptyTable;
+    use datafusion::datasource::TableProvider;
+    use insta::assert_snapshot;
+
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::drift::detect_schema_drift;
+    use crate::mdl::manifest::DataSource;
+
+    #[test]
+    fn test_detect_schema_drift() {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .column(ColumnBuilder::new("c_phone", "varchar").build())
+                    .column(
+                        ColumnBuilder::new("created_at", "timestamp")
+                            .expression("c_created")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("upper_name", "varchar")
+                            .expression("upper(c_name)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("public.orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let customer: Arc<dyn TableProvider> =
+            Arc::new(EmptyTable::new(Arc::new(Schema::new(vec![
+                Field::new("c_custkey", DataType::Int64, false),
+                Field::new("c_name", DataType::Utf8View, true),
+                Field::new(
+                    "c_created",
+                    DataType::Timestamp(TimeUnit::Microsecond, None),
+                    true,
+                ),
+                Field::new("c_address", DataType::Utf8, true),
+            ]))));
+        let register_tables = HashMap::from([("public.customer".to_string(), customer)]);
+
+        let report = detect_schema_drift(&manifest, &register_tables)
+            .iter()
+            .map(|drift| drift.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_snapshot!(report, @r"
+        customer.c_custkey: declared as int but the physical type is Int64
+        customer.c_phone: column not found in the physical table
+        customer.c_address: Utf8 column isn't declared
+        orders: table public.orders not found
+        ");
+    }
+
+    #[test]
+    fn test_data_source_types() {
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(DataSource::Oracle)
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "NUMBER(10, 0)").build())
+                    .column(ColumnBuilder::new("c_name", "VARCHAR2(25)").build())
+                    .column(ColumnBuilder::new("c_created", "DATE").build())
+                    .build(),
+            )
+            .build();
+        let customer: Arc<dyn TableProvider> =
+            Arc::new(EmptyTable::new(Arc::new(Schema::new(vec![
+                Field::new("c_custkey", DataType::Decimal128(10, 0), false),
+                Field::new("c_name", DataType::Utf8, true),
+                Field::new(
+                    "c_created",
+                    DataType::Timestamp(TimeUnit::Second, None),
+                    true,
+                ),
+            ]))));
+        let register_tables = HashMap::from([("customer".to_string(), customer)]);
+        assert!(detect_schema_drift(&manifest, &register_tables).is_empty());
+    }
+}
@@ -0,0 +1,373 @@
+//! Split the plan of a query joining the models stored in different data sources.
+//!
+//! The largest sub-plans whose tables are stored in the same data source are unparsed by the
+//! [WrenDialect] of the data source and executed by a [SqlExecutor]. They are replaced by the
+//! scans of their results, so the local runtime joins the results of the data sources. The
+//! results are streamed when the plan is executed.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::catalog::Session;
+use datafusion::common::tree_node::{Transformed, TreeNode, TreeNodeRecursion};
+use datafusion::common::{internal_err, plan_err, Column, Result};
+use datafusion::datasource::{provider_as_source, source_as_provider};
+use datafusion::datasource::{TableProvider, TableType};
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::{Expr, LogicalPlan, LogicalPlanBuilder, TableScan};
+use datafusion::physical_expr::EquivalenceProperties;
+use datafusion::physical_plan::execution_plan::{Boundedness, EmissionType};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionPlan, Partitioning, PlanProperties,
+};
+use datafusion::sql::unparser::Unparser;
+use datafusion::sql::TableReference;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::debug;
+use wren_core_base::mdl::DataSource;
+
+use crate::mdl::context::WrenDataSource;
+use crate::mdl::dialect::WrenDialect;
+use crate::mdl::WrenMDL;
+
+/// Execute the SQL planned for a data source, e.g. through the client of the database.
+#[async_trait]
+pub trait SqlExecutor: Debug + Send + Sync {
+    /// The columns of the result should be in the order of the selected items of the SQL.
+    async fn execute(
+        &self,
+        data_source: DataSource,
+        sql: &str,
+    ) -> Result<SendableRecordBatchStream>;
+}
+
+/// Where the tables of a plan are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlanSource {
+    /// The plan doesn't scan any table
+    Any,
+    /// The tables are executable by the local runtime
+    Local,
+    Remote(DataSource),
+    Mixed,
+}
+
+impl PlanSource {
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (PlanSource::Any, source) | (source, PlanSource::Any) => source,
+            (left, right) if left == right => left,
+            _ => PlanSource::Mixed,
+        }
+    }
+}
+
+/// Replace the sub-plans of the remote data sources with the scans of their results.
+pub fn federate(
+    wren_mdl: &WrenMDL,
+    plan: LogicalPlan,
+    executor: Arc<dyn SqlExecutor>,
+) -> Result<LogicalPlan> {
+    let sources = wren_mdl
+        .models()
+        .iter()
+        .filter(|model| !model.table_reference().is_empty())
+        .filter_map(|model| {
+            wren_mdl.model_data_source(model).map(|data_source| {
+                (TableReference::from(model.table_reference()), data_source)
+            })
+        })
+        .collect();
+    Federation {
+        sources,
+        executor,
+        count: 0,
+    }
+    .federate(plan)
+}
+
+struct Federation {
+    sources: HashMap<TableReference, DataSource>,
+    executor: Arc<dyn SqlExecutor>,
+    count: usize,
+}
+
+impl Federation {
+    fn federate(&mut self, plan: LogicalPlan) -> Result<LogicalPlan> {
+        match self.source_of(&plan)? {
+            PlanSource::Any | PlanSource::Local => return Ok(plan),
+            PlanSource::Remote(data_source) => {
+                match unparse(&plan, plan.schema().columns(), data_source) {
+                    Ok(_) => return self.remote_scan(plan, data_source),
+                    // try to push down the smaller sub-plans
+                    Err(e) if !matches!(plan, LogicalPlan::TableScan(_)) => {
+                        debug!("Failed to unparse the plan for {data_source}: {e}")
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            PlanSource::Mixed => {}
+        }
+        Ok(plan
+            .map_children(|child| Ok(Transformed::yes(self.federate(child)?)))?
+            .data)
+    }
+
+    fn source_of(&self, plan: &LogicalPlan) -> Result<PlanSource> {
+        let mut source = PlanSource::Any;
+        plan.apply_with_subqueries(|node| {
+            if let LogicalPlan::TableScan(scan) = node {
+                source = source.merge(self.scan_source(scan)?);
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        Ok(source)
+    }
+
+    /// Only the placeholders of the model sources are scanned from the remote data sources
+    fn scan_source(&self, scan: &TableScan) -> Result<PlanSource> {
+        let provider = source_as_provider(&scan.source)?;
+        let placeholder = provider
+            .as_any()
+            .downcast_ref::<WrenDataSource>()
+            .is_some_and(|table| !table.is_executable());
+        match self.sources.get(&scan.table_name) {
+            Some(data_source) if placeholder => Ok(PlanSource::Remote(*data_source)),
+            _ => Ok(PlanSource::Local),
+        }
+    }
+
+    /// Scan the result of the plan executed by the data source and restore the qualified
+    /// columns of the plan
+    fn remote_scan(
+        &mut self,
+        plan: LogicalPlan,
+        data_source: DataSource,
+    ) -> Result<LogicalPlan> {
+        let name = format!("__federated_{}", self.count);
+        self.count += 1;
+        let fields = plan
+            .schema()
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                Field::new(format!("__c{i}"), field.data_type().clone(), true)
+            })
+            .collect::<Vec<_>>();
+        let exprs = plan
+            .schema()
+            .iter()
+            .enumerate()
+            .map(|(i, (qualifier, field))| {
+                Expr::Column(Column::new(Some(name.as_str()), format!("__c{i}")))
+                    .alias_qualified(qualifier.cloned(), field.name())
+            })
+            .collect::<Vec<_>>();
+        let table = RemoteQueryTable {
+            data_source,
+            plan,
+            schema: Arc::new(Schema::new(fields)),
+            executor: Arc::clone(&self.executor),
+        };
+        LogicalPlanBuilder::scan(
+            name.as_str(),
+            provider_as_source(Arc::new(table)),
+            None,
+        )?
+        .project(exprs)?
+        .build()
+    }
+}
+
+/// Unparse the plan with the dialect of the data source. The columns are selected explicitly
+/// to keep their order in the result.
+fn unparse(
+    plan: &LogicalPlan,
+    columns: Vec<Column>,
+    data_source: DataSource,
+) -> Result<String> {
+    let columns = columns.into_iter().map(Expr::Column).collect::<Vec<_>>();
+    let plan = LogicalPlanBuilder::from(plan.clone())
+        .project(columns)?
+        .build()?;
+    let dialect = WrenDialect::new(&data_source);
+    let unparser = Unparser::new(&dialect).with_pretty(true);
+    Ok(unparser.plan_to_sql(&plan)?.to_string())
+}
+
+/// The result of the plan executed by a remote data source
+#[derive(Debug)]
+struct RemoteQueryTable {
+    data_source: DataSource,
+    plan: LogicalPlan,
+    schema: SchemaRef,
+    executor: Arc<dyn SqlExecutor>,
+}
+
+#[async_trait]
+impl TableProvider for RemoteQueryTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    /// The projection and the limit are pushed down to the SQL, which is executed when the
+    /// returned plan is executed.
+    ///
+    /// The filters aren't pushed down. The filters of the query are already in the remote
+    /// plan, and the filters added by the local optimizer aren't supported by
+    /// `supports_filters_pushdown`, so they're evaluated locally above the scan.
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let columns = self.plan.schema().columns();
+        let (columns, schema) = match projection {
+            Some(projection) => (
+                projection.iter().map(|i| columns[*i].clone()).collect(),
+                Arc::new(self.schema.project(projection)?),
+            ),
+            None => (columns, Arc::clone(&self.schema)),
+        };
+        let mut plan = LogicalPlanBuilder::from(self.plan.clone());
+        if limit.is_some() {
+            plan = plan.limit(0, limit)?;
+        }
+        let sql = unparse(&plan.build()?, columns, self.data_source)?;
+        debug!("Federated SQL for {}: {sql}", self.data_source);
+        Ok(Arc::new(RemoteQueryExec::new(
+            self.data_source,
+            sql,
+            schema,
+            Arc::clone(&self.executor),
+        )))
+    }
+}
+
+/// Execute the SQL by the [SqlExecutor] and stream the result in a single partition
+#[derive(Debug)]
+struct RemoteQueryExec {
+    data_source: DataSource,
+    sql: String,
+    schema: SchemaRef,
+    executor: Arc<dyn SqlExecutor>,
+    properties: PlanProperties,
+}
+
+impl RemoteQueryExec {
+    fn new(
+        data_source: DataSource,
+        sql: String,
+        schema: SchemaRef,
+        executor: Arc<dyn SqlExecutor>,
+    ) -> Self {
+        let properties = PlanProperties::new(
+            EquivalenceProperties::new(Arc::clone(&schema)),
+            Partitioning::UnknownPartitioning(1),
+            EmissionType::Incremental,
+            Boundedness::Bounded,
+        );
+        Self {
+            data_source,
+            sql,
+            schema,
+            executor,
+            properties,
+        }
+    }
+}
+
+impl DisplayAs for RemoteQueryExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RemoteQueryExec: data_source={}", self.data_source)
+    }
+}
+
+impl ExecutionPlan for RemoteQueryExec {
+    fn name(&self) -> &str {
+        "RemoteQueryExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.properties
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        if partition != 0 {
+            return internal_err!("RemoteQueryExec has only one partition");
+        }
+        let executor = Arc::clone(&self.executor);
+        let data_source = self.data_source;
+        let sql = self.sql.clone();
+        let schema = Arc::clone(&self.schema);
+        let batches =
+            stream::once(async move { executor.execute(data_source, &sql).await })
+                .try_flatten()
+                .map(move |batch| cast_batch(batch?, &schema, data_source));
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            Arc::clone(&self.schema),
+            batches,
+        )))
+    }
+}
+
+/// Cast the columns of the batch to the schema. The columns are matched by position because
+/// the names depend on the data source.
+fn cast_batch(
+    batch: RecordBatch,
+    schema: &SchemaRef,
+    data_source: DataSource,
+) -> Result<RecordBatch> {
+    if batch.num_columns() != schema.fields().len() {
+        return plan_err!(
+            "Expected {} columns from {} but got {}",
+            schema.fields().len(),
+            data_source,
+            batch.num_columns()
+        );
+    }
+    let columns = batch
+        .columns()
+        .iter()
+        .zip(schema.fields())
+        .map(|(column, field)| cast(column, field.data_type()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(Arc::clone(schema), columns)?)
+}
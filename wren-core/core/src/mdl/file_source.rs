@@ -0,0 +1,505 @@
+//! Register the files referred by the models as the tables of the local runtime.
+//!
+//! For the file data sources, the table reference of a model is the path of the files,
+//! e.g. `{"table": "data/orders.csv"}` or `{"table": "data/lineitem/*.parquet"}`. A relative
+//! path is resolved against the `url` property or `wren.file_root`, and the format of the files
+//! is inferred from the extension or given by the `format` property or `wren.file_format`.
+//!
+//! The files of [DataSource::S3File], [DataSource::MinioFile] and [DataSource::GcsFile] are read
+//! through the object store of the bucket. The store is built from the session properties
+//! (`bucket`, `region`, `access_key`, `secret_key`, `endpoint`, `ssl_enabled` and `credentials`)
+//! unless one is already registered in the runtime for the bucket. The built store is only
+//! registered in the runtime of the session, see [session_runtime_env], so the other sessions
+//! sharing the runtime don't read the bucket with the credentials of the session.
+
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use datafusion::catalog::Session;
+use datafusion::common::{plan_datafusion_err, plan_err, DataFusionError, Result};
+use datafusion::datasource::file_format::arrow::ArrowFormat;
+use datafusion::datasource::file_format::csv::CsvFormat;
+use datafusion::datasource::file_format::json::JsonFormat;
+use datafusion::datasource::file_format::parquet::ParquetFormat;
+use datafusion::datasource::file_format::FileFormat;
+use datafusion::datasource::listing::{
+    ListingOptions, ListingTable, ListingTableConfig, ListingTableUrl,
+};
+use datafusion::datasource::TableProvider;
+use datafusion::execution::object_store::{
+    DefaultObjectStoreRegistry, ObjectStoreRegistry, ObjectStoreUrl,
+};
+use datafusion::execution::runtime_env::RuntimeEnv;
+use object_store::aws::AmazonS3Builder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::ObjectStore;
+use url::Url;
+use wren_core_base::mdl::DataSource;
+
+use crate::mdl::context::{SessionPropertiesRef, WrenConfig, WrenDataSource};
+use crate::mdl::manifest::Model;
+use crate::mdl::{AnalyzedWrenMDL, WrenMDL};
+
+/// The supported formats of the files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSourceFormat {
+    Csv,
+    Parquet,
+    /// Newline-delimited JSON
+    Json,
+    /// Arrow IPC file
+    Arrow,
+}
+
+impl FromStr for FileSourceFormat {
+    type Err = DataFusionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "parquet" => Ok(Self::Parquet),
+            "json" | "ndjson" | "jsonl" => Ok(Self::Json),
+            "arrow" | "ipc" | "feather" => Ok(Self::Arrow),
+            _ => plan_err!("Unsupported file format: {}", s),
+        }
+    }
+}
+
+impl FileSourceFormat {
+    /// Infer the format from the extension of the path or the glob
+    pub fn from_path(path: &str) -> Option<Self> {
+        file_extension(path).and_then(|extension| extension.parse().ok())
+    }
+
+    fn file_format(&self) -> Arc<dyn FileFormat> {
+        match self {
+            Self::Csv => Arc::new(CsvFormat::default().with_has_header(true)),
+            Self::Parquet => Arc::new(ParquetFormat::default()),
+            Self::Json => Arc::new(JsonFormat::default()),
+            Self::Arrow => Arc::new(ArrowFormat),
+        }
+    }
+
+    fn default_extension(&self) -> &'static str {
+        match self {
+            Self::Csv => ".csv",
+            Self::Parquet => ".parquet",
+            Self::Json => ".json",
+            Self::Arrow => ".arrow",
+        }
+    }
+}
+
+fn file_extension(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next()?;
+    name.rsplit_once('.').map(|(_, extension)| extension)
+}
+
+/// The table reference of the model is the path of the files. Only a fully quoted
+/// identifier is unquoted, so a plain path like `data/orders.csv` is kept as it is.
+fn file_path(table_reference: &str) -> String {
+    match table_reference
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+    {
+        Some(path) if !path.contains("\".\"") => path.replace("\"\"", "\""),
+        _ => table_reference.to_string(),
+    }
+}
+
+fn property<'a>(properties: &'a SessionPropertiesRef, key: &str) -> Option<&'a str> {
+    properties
+        .get(key)
+        .and_then(|value| value.as_deref())
+        .filter(|value| !value.is_empty())
+}
+
+/// Where the files of the data source are stored
+#[derive(Debug, Clone, PartialEq)]
+enum FileLocation {
+    Local {
+        root: String,
+    },
+    ObjectStore {
+        url: ObjectStoreUrl,
+        /// The path prefix in the bucket, e.g. `s3://bucket/root`
+        prefix: String,
+    },
+}
+
+impl FileLocation {
+    fn try_new(
+        data_source: DataSource,
+        properties: &SessionPropertiesRef,
+        config: &WrenConfig,
+    ) -> Result<Self> {
+        let root = property(properties, "url")
+            .unwrap_or(&config.file_root)
+            .to_string();
+        let scheme = match data_source {
+            DataSource::LocalFile => return Ok(Self::Local { root }),
+            DataSource::S3File | DataSource::MinioFile => "s3",
+            DataSource::GcsFile => "gs",
+            _ => return plan_err!("{} isn't a file data source", data_source),
+        };
+        let Some(bucket) = property(properties, "bucket") else {
+            return plan_err!("The bucket property is required by {}", data_source);
+        };
+        let root = root.trim_matches('/');
+        let prefix = if root.is_empty() {
+            format!("{scheme}://{bucket}")
+        } else {
+            format!("{scheme}://{bucket}/{root}")
+        };
+        Ok(Self::ObjectStore {
+            url: ObjectStoreUrl::parse(format!("{scheme}://{bucket}"))?,
+            prefix,
+        })
+    }
+
+    fn resolve(&self, path: &str) -> String {
+        if path.contains("://") {
+            return path.to_string();
+        }
+        match self {
+            Self::Local { root } => {
+                if root.is_empty() || Path::new(path).is_absolute() {
+                    path.to_string()
+                } else {
+                    Path::new(root).join(path).to_string_lossy().to_string()
+                }
+            }
+            Self::ObjectStore { prefix, .. } => {
+                format!("{prefix}/{}", path.trim_start_matches('/'))
+            }
+        }
+    }
+}
+
+/// Build the object store of the bucket from the session properties
+pub fn build_object_store(
+    data_source: DataSource,
+    properties: &SessionPropertiesRef,
+) -> Result<Arc<dyn ObjectStore>> {
+    let Some(bucket) = property(properties, "bucket") else {
+        return plan_err!("The bucket property is required by {}", data_source);
+    };
+    match data_source {
+        DataSource::S3File | DataSource::MinioFile => {
+            let mut builder = AmazonS3Builder::from_env().with_bucket_name(bucket);
+            if let Some(region) = property(properties, "region") {
+                builder = builder.with_region(region);
+            }
+            if let Some(access_key) = property(properties, "access_key") {
+                builder = builder.with_access_key_id(access_key);
+            }
+            if let Some(secret_key) = property(properties, "secret_key") {
+                builder = builder.with_secret_access_key(secret_key);
+            }
+            if data_source == DataSource::MinioFile {
+                let Some(endpoint) = property(properties, "endpoint") else {
+                    return plan_err!(
+                        "The endpoint property is required by {}",
+                        data_source
+                    );
+                };
+                let ssl_enabled = property(properties, "ssl_enabled")
+                    .is_some_and(|enabled| enabled.eq_ignore_ascii_case("true"));
+                let endpoint = if endpoint.contains("://") {
+                    endpoint.to_string()
+                } else if ssl_enabled {
+                    format!("https://{endpoint}")
+                } else {
+                    format!("http://{endpoint}")
+                };
+                builder = builder
+                    .with_endpoint(endpoint)
+                    .with_allow_http(!ssl_enabled)
+                    .with_virtual_hosted_style_request(false);
+            }
+            Ok(Arc::new(builder.build()?))
+        }
+        DataSource::GcsFile => {
+            let mut builder =
+                GoogleCloudStorageBuilder::from_env().with_bucket_name(bucket);
+            if let Some(credentials) = property(properties, "credentials") {
+                let key = BASE64_STANDARD
+                    .decode(credentials)
+                    .ok()
+                    .and_then(|key| String::from_utf8(key).ok())
+                    .ok_or_else(|| {
+                        plan_datafusion_err!("The credentials should be base64 encoded")
+                    })?;
+                builder = builder.with_service_account_key(key);
+            }
+            Ok(Arc::new(builder.build()?))
+        }
+        _ => plan_err!("{} isn't an object store data source", data_source),
+    }
+}
+
+/// The object stores registered by a session. The stores of the shared runtime are used if the
+/// session doesn't register one for the URL.
+#[derive(Debug)]
+struct SessionObjectStoreRegistry {
+    session: DefaultObjectStoreRegistry,
+    shared: Arc<dyn ObjectStoreRegistry>,
+}
+
+impl ObjectStoreRegistry for SessionObjectStoreRegistry {
+    fn register_store(
+        &self,
+        url: &Url,
+        store: Arc<dyn ObjectStore>,
+    ) -> Option<Arc<dyn ObjectStore>> {
+        self.session.register_store(url, store)
+    }
+
+    fn get_store(&self, url: &Url) -> Result<Arc<dyn ObjectStore>> {
+        self.session
+            .get_store(url)
+            .or_else(|_| self.shared.get_store(url))
+    }
+}
+
+/// Return a runtime sharing the memory pool, the disk manager and the caches of the runtime,
+/// whose object stores are registered in the session only.
+pub fn session_runtime_env(runtime_env: &RuntimeEnv) -> Arc<RuntimeEnv> {
+    Arc::new(RuntimeEnv {
+        memory_pool: Arc::clone(&runtime_env.memory_pool),
+        disk_manager: Arc::clone(&runtime_env.disk_manager),
+        cache_manager: Arc::clone(&runtime_env.cache_manager),
+        object_store_registry: Arc::new(SessionObjectStoreRegistry {
+            session: DefaultObjectStoreRegistry::new(),
+            shared: Arc::clone(&runtime_env.object_store_registry),
+        }),
+    })
+}
+
+/// Build a listing table for the files referred by the table reference of the model
+async fn file_table(
+    state: &dyn Session,
+    model: &Model,
+    location: &FileLocation,
+    default_format: Option<FileSourceFormat>,
+) -> Result<Arc<dyn TableProvider>> {
+    let path = file_path(model.table_reference());
+    let inferred = FileSourceFormat::from_path(&path);
+    let Some(format) = inferred.or(default_format) else {
+        return plan_err!(
+            "Can't infer the file format of model {} from {}. Set the format property or wren.file_format to the format of the files",
+            model.name(),
+            path
+        );
+    };
+    // keep the extension of the path, e.g. `.jsonl`, to list the files
+    let extension = match (inferred, file_extension(&path)) {
+        (Some(_), Some(extension)) => format!(".{extension}"),
+        _ => format.default_extension().to_string(),
+    };
+    let url = ListingTableUrl::parse(location.resolve(&path))?;
+    let options =
+        ListingOptions::new(format.file_format()).with_file_extension(extension);
+    let config = ListingTableConfig::new(url)
+        .with_listing_options(options)
+        .infer_schema(state)
+        .await?;
+    Ok(Arc::new(ListingTable::try_new(config)?))
+}
+
+/// Register the files referred by the models of a file data source. The data source of each
+/// model is used, so a manifest can mix the file models with the models of other data sources.
+/// The tables registered by the user are kept, and only the placeholders inferred from the
+/// models are replaced.
+///
+/// The object store of the bucket is registered in the runtime of the state, which should be
+/// created by [session_runtime_env].
+pub async fn register_file_sources(
+    state: &dyn Session,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: &SessionPropertiesRef,
+    config: &WrenConfig,
+) -> Result<Arc<AnalyzedWrenMDL>> {
+    let wren_mdl = analyzed_mdl.wren_mdl();
+    let unregistered = wren_mdl
+        .models()
+        .iter()
+        .filter_map(|model| {
+            let data_source =
+                wren_mdl.model_data_source(model).filter(|data_source| {
+                    matches!(
+                        data_source,
+                        DataSource::LocalFile
+                            | DataSource::S3File
+                            | DataSource::MinioFile
+                            | DataSource::GcsFile
+                    )
+                })?;
+            let table_reference = model.table_reference();
+            let unregistered = !table_reference.is_empty()
+                && match wren_mdl.get_table(table_reference) {
+                    Some(table) => table
+                        .as_any()
+                        .downcast_ref::<WrenDataSource>()
+                        .is_some_and(|table| !table.is_executable()),
+                    None => true,
+                };
+            unregistered.then_some((model, data_source))
+        })
+        .collect::<Vec<_>>();
+    if unregistered.is_empty() {
+        return Ok(analyzed_mdl);
+    }
+
+    let default_format = property(properties, "format")
+        .or(config.file_format.as_deref())
+        .map(FileSourceFormat::from_str)
+        .transpose()?;
+
+    let mut mdl = WrenMDL::new(wren_mdl.manifest.clone());
+    mdl.register_tables = wren_mdl.register_tables.clone();
+    mdl.materializations = wren_mdl.materializations.clone();
+    for (model, data_source) in unregistered {
+        let location = FileLocation::try_new(data_source, properties, config)?;
+        if let FileLocation::ObjectStore { url, .. } = &location {
+            let runtime_env = state.runtime_env();
+            if runtime_env.object_store(url).is_err() {
+                runtime_env.register_object_store(
+                    url.as_ref(),
+                    build_object_store(data_source, properties)?,
+                );
+            }
+        }
+        let table = file_table(state, model, &location, default_format).await?;
+        mdl.register_table(model.table_reference().to_string(), table);
+    }
+    Ok(Arc::new(AnalyzedWrenMDL {
+        wren_mdl: Arc::new(mdl),
+        lineage: Arc::clone(&analyzed_mdl.lineage),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use crate::mdl::context::WrenConfig;
+    use crate::mdl::file_source::{
+        build_object_store, file_path, session_runtime_env, FileLocation,
+        FileSourceFormat,
+    };
+    use datafusion::common::Result;
+    use datafusion::execution::object_store::ObjectStoreUrl;
+    use datafusion::execution::runtime_env::RuntimeEnv;
+    use object_store::memory::InMemory;
+    use wren_core_base::mdl::DataSource;
+
+    #[test]
+    fn test_file_source_format() -> Result<()> {
+        assert_eq!(
+            FileSourceFormat::from_path("data/orders.csv"),
+            Some(FileSourceFormat::Csv)
+        );
+        assert_eq!(
+            FileSourceFormat::from_path("data/lineitem/*.parquet"),
+            Some(FileSourceFormat::Parquet)
+        );
+        assert_eq!(
+            FileSourceFormat::from_path("events.jsonl"),
+            Some(FileSourceFormat::Json)
+        );
+        assert_eq!(FileSourceFormat::from_path("data.dir/orders"), None);
+        assert_eq!("IPC".parse::<FileSourceFormat>()?, FileSourceFormat::Arrow);
+        assert!("xlsx".parse::<FileSourceFormat>().is_err());
+
+        assert_eq!(file_path("\"data/orders.csv\""), "data/orders.csv");
+        assert_eq!(file_path("data/orders.csv"), "data/orders.csv");
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_location() -> Result<()> {
+        let properties = |pairs: &[(&str, &str)]| {
+            Arc::new(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), Some(v.to_string())))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let config = WrenConfig::default();
+
+        let location = FileLocation::try_new(
+            DataSource::S3File,
+            &properties(&[("bucket", "wren"), ("url", "/tpch/")]),
+            &config,
+        )?;
+        assert_eq!(
+            location.resolve("orders/*.parquet"),
+            "s3://wren/tpch/orders/*.parquet"
+        );
+        let location = FileLocation::try_new(
+            DataSource::GcsFile,
+            &properties(&[("bucket", "wren")]),
+            &config,
+        )?;
+        assert_eq!(location.resolve("orders.csv"), "gs://wren/orders.csv");
+        let location = FileLocation::try_new(
+            DataSource::LocalFile,
+            &properties(&[("url", "/data")]),
+            &config,
+        )?;
+        assert_eq!(location.resolve("orders.csv"), "/data/orders.csv");
+        assert_eq!(location.resolve("/tmp/orders.csv"), "/tmp/orders.csv");
+
+        match FileLocation::try_new(DataSource::S3File, &properties(&[]), &config) {
+            Ok(_) => unreachable!("the bucket is required"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The bucket property is required by S3_FILE"
+            ),
+        }
+
+        let minio = properties(&[
+            ("bucket", "wren"),
+            ("endpoint", "localhost:9000"),
+            ("access_key", "minioadmin"),
+            ("secret_key", "minioadmin"),
+        ]);
+        assert!(build_object_store(DataSource::MinioFile, &minio).is_ok());
+        match build_object_store(
+            DataSource::MinioFile,
+            &properties(&[("bucket", "wren")]),
+        ) {
+            Ok(_) => unreachable!("the endpoint is required"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Error during planning: The endpoint property is required by MINIO_FILE"
+            ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_runtime_env() -> Result<()> {
+        let shared = RuntimeEnv::default();
+        let shared_url = ObjectStoreUrl::parse("s3://shared")?;
+        shared.register_object_store(shared_url.as_ref(), Arc::new(InMemory::new()));
+
+        let session = session_runtime_env(&shared);
+        let session_url = ObjectStoreUrl::parse("s3://session")?;
+        session.register_object_store(session_url.as_ref(), Arc::new(InMemory::new()));
+        assert!(session.object_store(&shared_url).is_ok());
+        assert!(session.object_store(&session_url).is_ok());
+        // the store of the session isn't shared with the other sessions
+        assert!(shared.object_store(&session_url).is_err());
+        assert!(session_runtime_env(&shared)
+            .object_store(&session_url)
+            .is_err());
+        Ok(())
+    }
+}
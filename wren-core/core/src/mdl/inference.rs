@@ -0,0 +1,277 @@
+//! Scaffold a draft MDL from the tables registered in a SessionContext.
+//!
+//! The columns of the models are typed by the schema of the tables. The primary keys and the
+//! relationships are guessed by the column names, e.g. `orders.customer_id` refers to
+//! `customer.id`, so the draft should be reviewed before using it.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::prelude::SessionContext;
+use datafusion::sql::TableReference;
+
+use crate::logical_plan::utils::map_arrow_type;
+use crate::mdl::builder::{
+    ColumnBuilder, ManifestBuilder, ModelBuilder, RelationshipBuilder,
+};
+use crate::mdl::manifest::{JoinType, Manifest, Model};
+use crate::mdl::utils::quoted;
+
+/// Infer the draft models of the tables and the relationships between them. The tables
+/// should be registered in the SessionContext, e.g. the files registered by `register_csv`.
+pub async fn infer_manifest(
+    ctx: &SessionContext,
+    catalog: &str,
+    schema: &str,
+    tables: &[TableReference],
+) -> Result<Manifest> {
+    let state = ctx.state();
+    let mut names = HashSet::new();
+    let mut models = vec![];
+    for table in tables {
+        let resolved = state.resolve_table_ref(table.clone());
+        let provider = ctx.table_provider(table.clone()).await?;
+        // the tables of different schemas may have the same name
+        let mut name = resolved.table.to_string();
+        if !names.insert(name.clone()) {
+            name = format!("{}_{}", resolved.schema, resolved.table);
+            if !names.insert(name.clone()) {
+                return plan_err!("Duplicate model {name} inferred from {table}");
+            }
+        }
+        let table_reference = TableReference::full(
+            Arc::clone(&resolved.catalog),
+            Arc::clone(&resolved.schema),
+            Arc::clone(&resolved.table),
+        );
+        let mut builder =
+            ModelBuilder::new(&name).table_reference(&table_reference.to_quoted_string());
+        let schema = provider.schema();
+        for field in schema.fields() {
+            builder = builder.column(
+                ColumnBuilder::new(field.name(), &map_arrow_type(field.data_type()))
+                    .not_null(!field.is_nullable())
+                    .build(),
+            );
+        }
+        let primary_key = schema
+            .fields()
+            .iter()
+            .map(|field| field.name())
+            .find(|column| is_primary_key(&name, column));
+        if let Some(primary_key) = primary_key {
+            builder = builder.primary_key(primary_key);
+        }
+        models.push(builder.build());
+    }
+    Ok(infer_relationships(models, catalog, schema))
+}
+
+/// Add the many-to-one relationships from the foreign-key-looking columns to the primary keys
+/// of the referred models, and the relationship columns to navigate them.
+fn infer_relationships(models: Vec<Arc<Model>>, catalog: &str, schema: &str) -> Manifest {
+    let mut builder = ManifestBuilder::new().catalog(catalog).schema(schema);
+    let mut relationship_names = HashSet::new();
+    for model in &models {
+        let mut relationship_columns = vec![];
+        for column in model.get_physical_columns() {
+            let Some(referred_name) = referred_model_name(&column.name) else {
+                continue;
+            };
+            let Some((referred, primary_key)) = models.iter().find_map(|referred| {
+                let primary_key = referred.primary_key()?;
+                (referred.name() != model.name()
+                    && (referred.name().eq_ignore_ascii_case(referred_name)
+                        || singular(referred.name()).eq_ignore_ascii_case(referred_name)))
+                .then_some((referred, primary_key))
+            }) else {
+                continue;
+            };
+            let relationship_name = format!("{}_{}", model.name(), referred.name());
+            if !relationship_names.insert(relationship_name.clone()) {
+                continue;
+            }
+            builder = builder.relationship(
+                RelationshipBuilder::new(&relationship_name)
+                    .model(model.name())
+                    .model(referred.name())
+                    .join_type(JoinType::ManyToOne)
+                    .condition(&format!(
+                        "{}.{} = {}.{}",
+                        quoted(model.name()),
+                        quoted(&column.name),
+                        quoted(referred.name()),
+                        quoted(primary_key)
+                    ))
+                    .build(),
+            );
+            if model.get_column(referred.name()).is_none() {
+                relationship_columns.push(
+                    ColumnBuilder::new_relationship(
+                        referred.name(),
+                        referred.name(),
+                        &relationship_name,
+                    )
+                    .build(),
+                );
+            }
+        }
+        let mut model = Model::clone(model);
+        model.columns.extend(relationship_columns);
+        builder = builder.model(Arc::new(model));
+    }
+    builder.build()
+}
+
+/// The primary key is the column named `id`, `<model>_id` or `<model>id`
+fn is_primary_key(model: &str, column: &str) -> bool {
+    let column = column.to_lowercase();
+    column == "id"
+        || [model.to_lowercase(), singular(model).to_lowercase()]
+            .iter()
+            .any(|name| column == format!("{name}_id") || column == format!("{name}id"))
+}
+
+/// Return the model name referred by a column named `<model>_id` or `<model>Id`. The camel case
+/// suffix should follow a lowercase letter, so the columns like `paid` or `uuid` aren't matched.
+fn referred_model_name(column: &str) -> Option<&str> {
+    let stem = if column.to_ascii_lowercase().ends_with("_id") {
+        &column[..column.len() - 3]
+    } else {
+        column
+            .strip_suffix("Id")
+            .filter(|stem| stem.ends_with(|c: char| c.is_ascii_lowercase()))?
+    };
+    (!stem.is_empty()).then_some(stem)
+}
+
+fn singular(name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    if lower.ends_with("ies") {
+        format!("{}y", &name[..name.len() - 3])
+    } else if lower.ends_with('s') && !lower.ends_with("ss") {
+        name[..name.len() - 1].to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::TableReference;
+    use insta::assert_snapshot;
+
+    use crate::mdl::inference::{infer_manifest, referred_model_name, singular};
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+
+    #[test]
+    fn test_naming() {
+        assert_eq!(referred_model_name("customer_id"), Some("customer"));
+        assert_eq!(referred_model_name("CustomerId"), Some("Customer"));
+        assert_eq!(referred_model_name("customerId"), Some("customer"));
+        assert_eq!(referred_model_name("CUSTOMER_ID"), Some("CUSTOMER"));
+        assert_eq!(referred_model_name("id"), None);
+        assert_eq!(referred_model_name("_id"), None);
+        assert_eq!(referred_model_name("name"), None);
+        // the words ending with `id` aren't foreign keys
+        assert_eq!(referred_model_name("paid"), None);
+        assert_eq!(referred_model_name("valid"), None);
+        assert_eq!(referred_model_name("uuid"), None);
+        assert_eq!(referred_model_name("PAID"), None);
+        assert_eq!(referred_model_name("ID"), None);
+        assert_eq!(singular("customers"), "customer");
+        assert_eq!(singular("categories"), "category");
+        assert_eq!(singular("address"), "address");
+    }
+
+    #[tokio::test]
+    async fn test_infer_manifest() -> Result<()> {
+        let ctx = SessionContext::new();
+        let id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let name: ArrayRef = Arc::new(StringArray::from_iter_values(["Gura", "Azki"]));
+        ctx.register_batch(
+            "customers",
+            RecordBatch::try_from_iter(vec![("id", Arc::clone(&id)), ("name", name)])?,
+        )?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("order_id", Arc::clone(&id)),
+                ("customer_id", Arc::clone(&id)),
+                ("totalprice", id),
+            ])?,
+        )?;
+        let manifest = infer_manifest(
+            &ctx,
+            "wren",
+            "test",
+            &[
+                TableReference::bare("customers"),
+                TableReference::bare("orders"),
+            ],
+        )
+        .await?;
+
+        let customers = manifest
+            .models
+            .iter()
+            .find(|m| m.name == "customers")
+            .unwrap();
+        assert_eq!(customers.table_reference(), "datafusion.public.customers");
+        assert_eq!(customers.primary_key(), Some("id"));
+        assert_eq!(customers.columns[0].r#type, "bigint");
+        assert_eq!(customers.columns[1].r#type, "varchar");
+        let orders = manifest.models.iter().find(|m| m.name == "orders").unwrap();
+        assert_eq!(orders.primary_key(), Some("order_id"));
+        let relationship_column = orders.get_column("customers").unwrap();
+        assert_eq!(
+            relationship_column.relationship.as_deref(),
+            Some("orders_customers")
+        );
+        assert_eq!(manifest.relationships.len(), 1);
+        let relationship = &manifest.relationships[0];
+        assert_eq!(relationship.join_type, JoinType::ManyToOne);
+        assert_eq!(
+            relationship.condition,
+            r#""orders"."customer_id" = "customers"."id""#
+        );
+
+        // the draft is ready to be queried
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::new()),
+        )?);
+        let actual = transform_sql_with_ctx(
+            &SessionContext::new(),
+            analyzed_mdl,
+            &[],
+            Arc::new(HashMap::new()),
+            "SELECT o.totalprice, o.customers.name FROM orders o ORDER BY o.totalprice",
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(pretty_format_batches(&batches)?.to_string(), @r"
+        +------------+------+
+        | totalprice | name |
+        +------------+------+
+        | 1          | Gura |
+        | 2          | Azki |
+        +------------+------+
+        ");
+
+        let err = infer_manifest(&ctx, "wren", "test", &[TableReference::bare("x")])
+            .await
+            .unwrap_err();
+        assert_snapshot!(err.to_string(), @"Error during planning: No table named 'x'");
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use crate::mdl::lineage::DatasetLink;
+use crate::mdl::manifest::Model;
+use crate::mdl::relationship_path::{
+    normalize_ident, requalify_condition, try_get_model,
+};
+use crate::mdl::utils::quoted;
+use crate::mdl::{Dataset, WrenMDL};
+use datafusion::common::{internal_err, plan_datafusion_err, plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::sqlparser::ast::{
+    Expr, Join, JoinConstraint, JoinOperator, Query, Select, SetExpr, Statement,
+    TableFactor, VisitMut, VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+use petgraph::graph::{EdgeIndex, NodeIndex, UnGraph};
+use petgraph::visit::EdgeRef;
+
+/// Infer the join conditions of the models joined without an explicit condition.
+///
+/// The models joined by a comma or a `JOIN` without `ON` are connected by the relationship
+/// between them. e.g.
+///
+/// ```sql
+/// SELECT o.o_orderkey, c.c_name FROM orders o JOIN customer c
+/// ```
+///
+/// is rewritten to
+///
+/// ```sql
+/// SELECT o.o_orderkey, c.c_name FROM orders o JOIN customer c ON c.c_custkey = o.o_custkey
+/// ```
+///
+/// A model is joined along the shortest path of the relationships to the models before it, and
+/// the models in the middle of the path are joined as well. If there are several shortest paths,
+/// the path is ambiguous and an error is returned. An explicit `CROSS JOIN` is kept.
+pub(crate) fn infer_join_conditions(
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+) -> Result<()> {
+    if wren_mdl.relationships().is_empty() {
+        return Ok(());
+    }
+    let mut inferrer = JoinConditionInferrer {
+        wren_mdl,
+        graph: relationship_graph(wren_mdl),
+        error: None,
+    };
+    let _ = statement.visit(&mut inferrer);
+    match inferrer.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The undirected graph of the models connected by the relationships
+struct RelationshipGraph {
+    graph: UnGraph<Dataset, DatasetLink>,
+    nodes: HashMap<String, NodeIndex>,
+}
+
+fn relationship_graph(wren_mdl: &WrenMDL) -> RelationshipGraph {
+    let mut graph = UnGraph::new_undirected();
+    let mut nodes = HashMap::new();
+    for model in wren_mdl.models() {
+        let index = graph.add_node(Dataset::Model(Arc::clone(model)));
+        nodes.insert(model.name().to_string(), index);
+    }
+    for relationship in wren_mdl.relationships() {
+        let [left, right] = relationship.models.as_slice() else {
+            continue;
+        };
+        // A self relationship can't be identified by the model names in the condition
+        if left == right {
+            continue;
+        }
+        let (Some(left), Some(right)) = (nodes.get(left), nodes.get(right)) else {
+            continue;
+        };
+        graph.add_edge(
+            *left,
+            *right,
+            DatasetLink {
+                join_type: relationship.join_type,
+                condition: relationship.condition.clone(),
+            },
+        );
+    }
+    RelationshipGraph { graph, nodes }
+}
+
+struct JoinConditionInferrer<'a> {
+    wren_mdl: &'a WrenMDL,
+    graph: RelationshipGraph,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for JoinConditionInferrer<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.infer_set_expr(query.body.as_mut()) {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// The model relation used in the FROM clause
+struct ModelRelation {
+    qualifier: String,
+    model: Arc<Model>,
+}
+
+impl JoinConditionInferrer<'_> {
+    fn infer_set_expr(&self, set_expr: &mut SetExpr) -> Result<()> {
+        match set_expr {
+            SetExpr::Select(select) => self.infer_select(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.infer_set_expr(left)?;
+                self.infer_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn infer_select(&self, select: &mut Select) -> Result<()> {
+        let Some(first) = select.from.first_mut() else {
+            return Ok(());
+        };
+        let mut visited: Vec<ModelRelation> =
+            self.model_relation(&first.relation).into_iter().collect();
+
+        let mut joins = vec![];
+        for join in std::mem::take(&mut first.joins) {
+            match self.model_relation(&join.relation) {
+                Some(relation)
+                    if matches!(
+                        join.join_operator,
+                        JoinOperator::Inner(JoinConstraint::None)
+                    ) =>
+                {
+                    joins.extend(self.infer_joins(
+                        join.relation,
+                        relation,
+                        &mut visited,
+                    )?);
+                }
+                relation => {
+                    joins.push(join);
+                    visited.extend(relation);
+                }
+            }
+        }
+        first.joins = joins;
+
+        // Merge the models joined by comma into the first relation
+        let mut remaining = vec![];
+        for table_with_joins in select.from.split_off(1) {
+            match self.model_relation(&table_with_joins.relation) {
+                Some(relation) if remaining.is_empty() => {
+                    let joins = self.infer_joins(
+                        table_with_joins.relation,
+                        relation,
+                        &mut visited,
+                    )?;
+                    let first = &mut select.from[0];
+                    first.joins.extend(joins);
+                    first.joins.extend(table_with_joins.joins);
+                }
+                _ => remaining.push(table_with_joins),
+            }
+        }
+        select.from.extend(remaining);
+        Ok(())
+    }
+
+    fn model_relation(&self, relation: &TableFactor) -> Option<ModelRelation> {
+        let TableFactor::Table { name, alias, .. } = relation else {
+            return None;
+        };
+        let model = try_get_model(self.wren_mdl, name)?;
+        let qualifier = alias
+            .as_ref()
+            .map(|a| normalize_ident(&a.name))
+            .unwrap_or_else(|| model.name().to_string());
+        Some(ModelRelation { qualifier, model })
+    }
+
+    /// Build the inner joins of the relation along the unique shortest path of the relationships
+    /// between the relation and the visited relations. The models in the middle of the path are
+    /// joined before the relation and aliased by `{qualifier}__{model}`. The joined relations are
+    /// added to the visited relations.
+    fn infer_joins(
+        &self,
+        table_factor: TableFactor,
+        relation: ModelRelation,
+        visited: &mut Vec<ModelRelation>,
+    ) -> Result<Vec<Join>> {
+        let RelationshipGraph { graph, nodes } = &self.graph;
+        let Some(node) = nodes.get(relation.model.name()) else {
+            return plan_err!("Model {} not found", relation.model.name());
+        };
+        let mut candidates = self.shortest_paths(*node, visited);
+        let (target, path) = match candidates.len() {
+            0 => {
+                return plan_err!(
+                    "No relationship found to join {}. Please specify the join condition",
+                    relation.qualifier
+                )
+            }
+            1 => candidates.remove(0),
+            _ => {
+                let mut paths = candidates
+                    .iter()
+                    .map(|(target, path)| {
+                        let links = path
+                            .iter()
+                            .map(|edge| graph[*edge].to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        format!("{} ({})", visited[*target].qualifier, links)
+                    })
+                    .collect::<Vec<_>>();
+                paths.sort();
+                return plan_err!(
+                    "Ambiguous join path for {}: {}. Please specify the join condition",
+                    relation.qualifier,
+                    paths.join(", ")
+                );
+            }
+        };
+
+        // the nodes of the path from the relation to the target
+        let mut path_nodes = vec![*node];
+        for edge in &path {
+            let Some((a, b)) = graph.edge_endpoints(*edge) else {
+                return internal_err!("Edge of the relationship graph not found");
+            };
+            let last = path_nodes[path_nodes.len() - 1];
+            path_nodes.push(if a == last { b } else { a });
+        }
+
+        // join the path backwards from the target, so every condition refers to a joined relation
+        let mut joins = vec![];
+        let mut joined = (
+            Arc::clone(&visited[target].model),
+            visited[target].qualifier.clone(),
+        );
+        for (index, edge) in path.iter().enumerate().rev() {
+            let current = if index == 0 {
+                ModelRelation {
+                    qualifier: relation.qualifier.clone(),
+                    model: Arc::clone(&relation.model),
+                }
+            } else {
+                let Dataset::Model(model) = &graph[path_nodes[index]] else {
+                    return internal_err!("Only models are in the relationship graph");
+                };
+                ModelRelation {
+                    qualifier: format!("{}__{}", relation.qualifier, model.name()),
+                    model: Arc::clone(model),
+                }
+            };
+            let condition = requalify_condition(
+                &graph[*edge].condition,
+                &[
+                    (current.model.name(), current.qualifier.as_str()),
+                    (joined.0.name(), joined.1.as_str()),
+                ],
+            )?;
+            let join = if index == 0 {
+                let mut join = build_join("__right", &condition, &current.qualifier)?;
+                join.relation = table_factor.clone();
+                join
+            } else {
+                let relation = format!(
+                    "{} AS {}",
+                    quoted(current.model.name()),
+                    quoted(&current.qualifier)
+                );
+                build_join(&relation, &condition, &current.qualifier)?
+            };
+            joined = (Arc::clone(&current.model), current.qualifier.clone());
+            visited.push(current);
+            joins.push(join);
+        }
+        Ok(joins)
+    }
+
+    /// Find the shortest paths from the node to the visited relations. Return the index of the
+    /// visited relation and the edges of the path. A path ends at the first visited relation.
+    fn shortest_paths(
+        &self,
+        node: NodeIndex,
+        visited: &[ModelRelation],
+    ) -> Vec<(usize, Vec<EdgeIndex>)> {
+        let RelationshipGraph { graph, nodes } = &self.graph;
+        let visited_nodes = visited
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| nodes.get(v.model.name()).map(|n| (i, *n)))
+            .collect::<Vec<_>>();
+        // the depth where the node is reached first. A node reached at a deeper level can't be a
+        // part of the shortest paths.
+        let mut depths = HashMap::from([(node, 0)]);
+        let mut paths = vec![(node, vec![])];
+        let mut found = vec![];
+        let mut depth = 0;
+        while found.is_empty() && !paths.is_empty() {
+            depth += 1;
+            let mut next = vec![];
+            for (end, edges) in paths {
+                for edge in graph.edges(end) {
+                    let other = if edge.source() == end {
+                        edge.target()
+                    } else {
+                        edge.source()
+                    };
+                    if *depths.entry(other).or_insert(depth) < depth {
+                        continue;
+                    }
+                    let mut edges: Vec<EdgeIndex> = edges.clone();
+                    edges.push(edge.id());
+                    let targets = visited_nodes
+                        .iter()
+                        .filter(|(_, n)| *n == other)
+                        .map(|(i, _)| *i)
+                        .collect::<Vec<_>>();
+                    if targets.is_empty() {
+                        next.push((other, edges));
+                    } else {
+                        found.extend(targets.into_iter().map(|i| (i, edges.clone())));
+                    }
+                }
+            }
+            paths = next;
+        }
+        found
+    }
+}
+
+/// Build the inner join of the relation on the condition
+fn build_join(relation: &str, condition: &Expr, qualifier: &str) -> Result<Join> {
+    let sql = format!("SELECT 1 FROM __left JOIN {relation} ON {condition}");
+    Parser::parse_sql(&GenericDialect {}, &sql)?
+        .into_iter()
+        .next()
+        .and_then(|statement| match statement {
+            Statement::Query(query) => match *query.body {
+                SetExpr::Select(mut select) => {
+                    select.from.pop().and_then(|mut t| t.joins.pop())
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+        .ok_or_else(|| plan_datafusion_err!("Failed to build the join for {}", qualifier))
+}
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use datafusion::common::{internal_err, plan_err, Column};
 use datafusion::error::Result;
 use datafusion::sql::TableReference;
+use petgraph::algo::toposort;
 use petgraph::Graph;
 
 use crate::logical_plan::utils::from_qualified_name;
@@ -20,6 +21,8 @@ pub struct Lineage {
     pub source_columns_map: HashMap<Column, HashSet<Column>>,
     pub required_fields_map: HashMap<Column, HashSet<Column>>,
     pub required_dataset_topo: HashMap<Column, Graph<Dataset, DatasetLink>>,
+    /// The metrics which the derived metric depends on
+    pub metric_dependencies: HashMap<String, HashSet<String>>,
 }
 
 impl Lineage {
@@ -29,13 +32,67 @@ impl Lineage {
             required_fields_map,
             required_dataset_topo,
         } = Lineage::collect_required_fields(mdl, &source_columns_map)?;
+        let metric_dependencies = Lineage::collect_metric_dependencies(mdl)?;
         Ok(Lineage {
             source_columns_map,
             required_fields_map,
             required_dataset_topo,
+            metric_dependencies,
         })
     }
 
+    /// Collect the metrics referenced by the measures of the derived metrics and check
+    /// there is no cyclic dependency.
+    fn collect_metric_dependencies(
+        mdl: &WrenMDL,
+    ) -> Result<HashMap<String, HashSet<String>>> {
+        let mut metric_dependencies: HashMap<String, HashSet<String>> = HashMap::new();
+        for metric in mdl.metrics().iter().filter(|m| m.is_derived()) {
+            for measure in metric.measure.iter() {
+                let Some(expr) = measure.expression() else {
+                    return plan_err!(
+                        "measure of metric should have expression: {}.{}",
+                        metric.name(),
+                        measure.name()
+                    );
+                };
+                // The identifiers are unqualified columns named like `metric.measure`
+                for identifier in collect_identifiers(expr)? {
+                    let Some((dependency, _)) = identifier.name.rsplit_once('.') else {
+                        continue;
+                    };
+                    if mdl.get_metric(dependency).is_some() {
+                        metric_dependencies
+                            .entry(metric.name().to_string())
+                            .or_default()
+                            .insert(dependency.to_string());
+                    }
+                }
+            }
+        }
+
+        let mut graph: Graph<&str, ()> = Graph::new();
+        let mut node_index_map = HashMap::new();
+        for (metric, dependencies) in metric_dependencies.iter() {
+            let source = *node_index_map
+                .entry(metric.as_str())
+                .or_insert_with(|| graph.add_node(metric.as_str()));
+            for dependency in dependencies {
+                let target = *node_index_map
+                    .entry(dependency.as_str())
+                    .or_insert_with(|| graph.add_node(dependency.as_str()));
+                graph.add_edge(source, target, ());
+            }
+        }
+        if let Err(cycle) = toposort(&graph, None) {
+            return plan_err!(
+                "cyclic dependency detected in metric: {}",
+                graph[cycle.node_id()]
+            );
+        }
+        Ok(metric_dependencies)
+    }
+
     fn collect_source_columns(mdl: &WrenMDL) -> Result<HashMap<Column, HashSet<Column>>> {
         let mut source_columns_map = HashMap::new();
 
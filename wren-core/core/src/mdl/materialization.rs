@@ -0,0 +1,711 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::logical_plan::utils::try_map_data_type;
+use crate::mdl::manifest::{Metric, Model, TimeGrain, TimeUnit};
+use crate::mdl::metric::{
+    collect_node_references, collect_references, rollup_function, select_wildcards,
+    selects_all_columns, time_grain_column, truncate,
+};
+use crate::mdl::relationship_path::{mdl_table_name, normalize_ident};
+use crate::mdl::utils::{collect_identifiers, quoted, to_field};
+use crate::mdl::WrenMDL;
+use datafusion::arrow::datatypes::{Field, Schema, SchemaRef};
+use datafusion::common::{plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::sqlparser::ast::{
+    OrderBy, Query, Select, SetExpr, Statement, TableFactor, TableWithJoins, VisitMut,
+    VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+
+/// A pre-aggregated table of a cached metric or a copy of a cached model.
+///
+/// The columns of the table are named like the output of the metric: the dimensions, the time
+/// grain columns (e.g. `order_date__month`) and the measures. A materialization of a model has
+/// the model columns as the dimensions only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Materialization {
+    /// The table name in the data source. e.g. `cache.revenue_by_month`
+    pub name: String,
+    /// The name of the metric or the model
+    pub object: String,
+    pub dimensions: Vec<String>,
+    pub time_grains: Vec<MaterializedTimeGrain>,
+    pub measures: Vec<String>,
+    /// The time the table was refreshed. The materialization is ignored if it's older than
+    /// the `refresh_time` of the metric or the model.
+    pub refreshed_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterializedTimeGrain {
+    pub time_grain: String,
+    pub unit: TimeUnit,
+}
+
+impl Materialization {
+    pub fn new(name: &str, object: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            object: object.to_string(),
+            dimensions: vec![],
+            time_grains: vec![],
+            measures: vec![],
+            refreshed_at: None,
+        }
+    }
+
+    pub fn dimension(mut self, name: &str) -> Self {
+        self.dimensions.push(name.to_string());
+        self
+    }
+
+    pub fn time_grain(mut self, time_grain: &str, unit: TimeUnit) -> Self {
+        self.time_grains.push(MaterializedTimeGrain {
+            time_grain: time_grain.to_string(),
+            unit,
+        });
+        self
+    }
+
+    pub fn measure(mut self, name: &str) -> Self {
+        self.measures.push(name.to_string());
+        self
+    }
+
+    pub fn refreshed_at(mut self, refreshed_at: SystemTime) -> Self {
+        self.refreshed_at = Some(refreshed_at);
+        self
+    }
+
+    /// The schema of the table inferred from the columns of the metric or the model
+    pub fn schema(&self, wren_mdl: &WrenMDL) -> Result<SchemaRef> {
+        let mut fields = vec![];
+        if let Some(metric) = wren_mdl.get_metric(&self.object) {
+            let data_source = wren_mdl.metric_data_source(&metric).unwrap_or_default();
+            for name in self.dimensions.iter() {
+                let Some(column) = metric.get_dimension(name) else {
+                    return plan_err!(
+                        "Dimension {} of materialization {} not found",
+                        name,
+                        self.name
+                    );
+                };
+                fields.push(to_field(&column, &data_source)?);
+            }
+            for MaterializedTimeGrain { time_grain, unit } in self.time_grains.iter() {
+                if metric.get_time_grain(time_grain).is_none() {
+                    return plan_err!(
+                        "Time grain {} of materialization {} not found",
+                        time_grain,
+                        self.name
+                    );
+                }
+                fields.push(Field::new(
+                    time_grain_column(time_grain, unit),
+                    try_map_data_type("timestamp")?,
+                    true,
+                ));
+            }
+            for name in self.measures.iter() {
+                let Some(column) = metric.get_measure(name) else {
+                    return plan_err!(
+                        "Measure {} of materialization {} not found",
+                        name,
+                        self.name
+                    );
+                };
+                fields.push(to_field(&column, &data_source)?);
+            }
+        } else if let Some(model) = wren_mdl.get_model(&self.object) {
+            if !self.time_grains.is_empty() || !self.measures.is_empty() {
+                return plan_err!(
+                    "Materialization {} of model {} should have the dimensions only",
+                    self.name,
+                    self.object
+                );
+            }
+            let data_source = wren_mdl.model_type_source(&model);
+            for name in self.dimensions.iter() {
+                let Some(column) =
+                    model.get_column(name).filter(|c| c.relationship.is_none())
+                else {
+                    return plan_err!(
+                        "Column {} of materialization {} not found",
+                        name,
+                        self.name
+                    );
+                };
+                fields.push(to_field(&column, &data_source)?);
+            }
+        } else {
+            return plan_err!(
+                "Metric or model {} of materialization {} not found",
+                self.object,
+                self.name
+            );
+        }
+        Ok(Arc::new(Schema::new(fields)))
+    }
+
+    /// Check if the table was refreshed within the refresh time before `now`. The freshness
+    /// can't be checked without `now`, so it's regarded as stale.
+    fn is_fresh(
+        &self,
+        refresh_time: Option<&str>,
+        now: Option<SystemTime>,
+    ) -> Result<bool> {
+        let (Some(refresh_time), Some(refreshed_at)) = (refresh_time, self.refreshed_at)
+        else {
+            return Ok(true);
+        };
+        let Some(now) = now else {
+            return Ok(false);
+        };
+        let refresh_time = parse_refresh_time(refresh_time)?;
+        // A refresh time in the future is regarded as fresh
+        Ok(now
+            .duration_since(refreshed_at)
+            .map(|elapsed| elapsed <= refresh_time)
+            .unwrap_or(true))
+    }
+
+    /// The quoted table name in the SQL
+    fn table_name(&self) -> String {
+        self.name
+            .split('.')
+            .map(quoted)
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn table_factor(&self, qualifier: &str) -> Result<TableFactor> {
+        Ok(Parser::new(&GenericDialect {})
+            .try_with_sql(&format!("{} AS {}", self.table_name(), quoted(qualifier)))?
+            .parse_table_factor()?)
+    }
+}
+
+/// Parse the refresh time like `30m`. The units are `ms`, `s`, `m`, `h` and `d`.
+fn parse_refresh_time(refresh_time: &str) -> Result<Duration> {
+    let refresh_time = refresh_time.trim();
+    let index = refresh_time
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(refresh_time.len());
+    let (value, unit) = refresh_time.split_at(index);
+    let Ok(value) = value.parse::<u64>() else {
+        return plan_err!("Invalid refresh time {refresh_time}");
+    };
+    let seconds = match unit.trim() {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "s" => value,
+        "m" => value * 60,
+        "h" => value * 60 * 60,
+        "d" => value * 60 * 60 * 24,
+        _ => return plan_err!("Invalid refresh time {refresh_time}"),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Rewrite the cached metrics and models used in the query to read from their materializations.
+///
+/// A metric is read from a materialization if it contains the referenced measures and its grain
+/// covers the referenced dimensions and time grains. The filters on the metric refer to the
+/// dimensions, so they're a part of the referenced grain. If the grain of the materialization is
+/// finer, the measures are aggregated again to the referenced grain. e.g. a materialization of
+/// `revenue` by `order_date__day` can be rolled up to `order_date__month`:
+///
+/// ```sql
+/// SELECT r.order_date__month, r.total FROM revenue r
+/// ```
+///
+/// is rewritten to
+///
+/// ```sql
+/// SELECT r.order_date__month, r.total FROM (
+///   SELECT date_trunc('month', "order_date__day") AS "order_date__month", sum("total") AS "total"
+///   FROM "revenue_by_day" GROUP BY date_trunc('month', "order_date__day")
+/// ) AS r
+/// ```
+///
+/// Only `sum`, `count`, `min` and `max` measures can be rolled up. A cumulative or
+/// period-over-period metric can only be read at the same grain.
+///
+/// A model is read from a materialization containing all the referenced columns.
+///
+/// The materialization is skipped if it's stale at `now` or the access control of the models is
+/// required, and the query falls back to the metric or the model. If `now` isn't given, the
+/// materializations whose freshness depends on the time are skipped.
+pub(crate) fn rewrite_materialized_relations(
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+    now: Option<SystemTime>,
+) -> Result<()> {
+    if wren_mdl.materializations.is_empty() {
+        return Ok(());
+    }
+    let mut rewriter = MaterializationRewriter {
+        wren_mdl,
+        now,
+        error: None,
+    };
+    let _ = statement.visit(&mut rewriter);
+    match rewriter.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+struct MaterializationRewriter<'a> {
+    wren_mdl: &'a WrenMDL,
+    now: Option<SystemTime>,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for MaterializationRewriter<'_> {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.rewrite_query(query) {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// The cached metric or model used in the FROM clause
+enum CachedRelation {
+    Metric(Arc<Metric>),
+    Model(Arc<Model>),
+}
+
+impl MaterializationRewriter<'_> {
+    fn rewrite_query(&self, query: &mut Query) -> Result<()> {
+        let Query { body, order_by, .. } = query;
+        match body.as_mut() {
+            SetExpr::Select(select) => self.rewrite_select(select, order_by),
+            set_expr => self.rewrite_set_expr(set_expr),
+        }
+    }
+
+    fn rewrite_set_expr(&self, set_expr: &mut SetExpr) -> Result<()> {
+        match set_expr {
+            SetExpr::Select(select) => self.rewrite_select(select, &None),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.rewrite_set_expr(left)?;
+                self.rewrite_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn rewrite_select(
+        &self,
+        select: &mut Select,
+        order_by: &Option<OrderBy>,
+    ) -> Result<()> {
+        let qualifiers = select
+            .from
+            .iter()
+            .flat_map(|t| {
+                std::iter::once(&t.relation).chain(t.joins.iter().map(|j| &j.relation))
+            })
+            .filter_map(|relation| self.cached_relation(relation))
+            .map(|(qualifier, _)| qualifier)
+            .collect::<Vec<_>>();
+        if qualifiers.is_empty() {
+            return Ok(());
+        }
+        let qualifiers = qualifiers.iter().map(|q| q.as_str()).collect::<Vec<_>>();
+        let references = collect_references(select, order_by, &qualifiers);
+        let mut filter_references = HashMap::new();
+        collect_node_references(&select.selection, &qualifiers, &mut filter_references);
+        let wildcards = select_wildcards(select);
+
+        let empty = HashSet::new();
+        for relation in select.from.iter_mut().flat_map(|t| {
+            let TableWithJoins { relation, joins } = t;
+            std::iter::once(relation).chain(joins.iter_mut().map(|j| &mut j.relation))
+        }) {
+            let Some((qualifier, cached)) = self.cached_relation(relation) else {
+                continue;
+            };
+            let all_columns = selects_all_columns(&wildcards, &qualifier);
+            let referenced = references.get(&qualifier).unwrap_or(&empty);
+            let filtered = filter_references.get(&qualifier).unwrap_or(&empty);
+            let materialized = match cached {
+                // The filters on the dimensions out of the grain are applied below the
+                // aggregation of the metric, which isn't in the materialization
+                CachedRelation::Metric(_)
+                    if !all_columns && !filtered.is_subset(referenced) =>
+                {
+                    None
+                }
+                CachedRelation::Metric(metric) => materialized_metric_query(
+                    self.wren_mdl,
+                    &metric,
+                    referenced,
+                    all_columns,
+                    self.now,
+                )?
+                .map(|sql| {
+                    Parser::new(&GenericDialect {})
+                        .try_with_sql(&format!("({sql}) AS {}", quoted(&qualifier)))?
+                        .parse_table_factor()
+                })
+                .transpose()?,
+                CachedRelation::Model(model) => {
+                    let referenced = referenced | filtered;
+                    materialized_model(
+                        self.wren_mdl,
+                        &model,
+                        &referenced,
+                        all_columns,
+                        self.now,
+                    )?
+                    .map(|m| m.table_factor(&qualifier))
+                    .transpose()?
+                }
+            };
+            if let Some(materialized) = materialized {
+                *relation = materialized;
+            }
+        }
+        Ok(())
+    }
+
+    fn cached_relation(
+        &self,
+        relation: &TableFactor,
+    ) -> Option<(String, CachedRelation)> {
+        let TableFactor::Table { name, alias, .. } = relation else {
+            return None;
+        };
+        let name = mdl_table_name(self.wren_mdl, name)?;
+        if self.wren_mdl.get_materializations(&name).is_empty() {
+            return None;
+        }
+        let cached = if let Some(metric) = self.wren_mdl.get_metric(&name) {
+            metric.cached.then_some(CachedRelation::Metric(metric))?
+        } else {
+            let model = self.wren_mdl.get_model(&name)?;
+            model.cached.then_some(CachedRelation::Model(model))?
+        };
+        let qualifier = alias
+            .as_ref()
+            .map(|a| normalize_ident(&a.name))
+            .unwrap_or(name);
+        Some((qualifier, cached))
+    }
+}
+
+/// Build the query of the metric reading from the first matched materialization
+fn materialized_metric_query(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    referenced: &HashSet<String>,
+    all_columns: bool,
+    now: Option<SystemTime>,
+) -> Result<Option<String>> {
+    if requires_access_control(wren_mdl, metric, &mut vec![]) {
+        return Ok(None);
+    }
+    let dimensions = metric
+        .dimension
+        .iter()
+        .map(|d| d.name())
+        .filter(|name| all_columns || referenced.contains(*name))
+        .collect::<Vec<_>>();
+    let measures = metric
+        .measure
+        .iter()
+        .map(|m| m.name())
+        .filter(|name| all_columns || referenced.contains(*name))
+        .collect::<Vec<_>>();
+    let mut time_grains = vec![];
+    for time_grain in metric.time_grain.iter() {
+        for unit in TimeUnit::all() {
+            if referenced.contains(&time_grain_column(time_grain.name(), &unit)) {
+                time_grains.push((time_grain, unit));
+            }
+        }
+    }
+
+    for materialization in wren_mdl.get_materializations(metric.name()) {
+        if !materialization.is_fresh(metric.refresh_time.as_deref(), now)? {
+            continue;
+        }
+        if let Some(sql) = match_metric_materialization(
+            metric,
+            materialization,
+            &dimensions,
+            &time_grains,
+            &measures,
+        )? {
+            return Ok(Some(sql));
+        }
+    }
+    Ok(None)
+}
+
+fn match_metric_materialization(
+    metric: &Metric,
+    materialization: &Materialization,
+    dimensions: &[&str],
+    time_grains: &[(&TimeGrain, TimeUnit)],
+    measures: &[&str],
+) -> Result<Option<String>> {
+    let contains = |names: &[String], name: &str| names.iter().any(|n| n == name);
+    if !dimensions
+        .iter()
+        .all(|d| contains(&materialization.dimensions, *d))
+        || !measures
+            .iter()
+            .all(|m| contains(&materialization.measures, *m))
+    {
+        return Ok(None);
+    }
+    // The source time unit of each referenced time grain column
+    let mut sources = vec![];
+    for (time_grain, unit) in time_grains {
+        let materialized = materialization
+            .time_grains
+            .iter()
+            .filter(|t| t.time_grain == time_grain.name())
+            .map(|t| t.unit)
+            .collect::<Vec<_>>();
+        let source = if materialized.contains(unit) {
+            *unit
+        } else if let Some(source) = materialized
+            .into_iter()
+            .find(|source| can_roll_up(source, unit))
+        {
+            source
+        } else {
+            return Ok(None);
+        };
+        sources.push(source);
+    }
+    let is_same_grain = time_grains
+        .iter()
+        .zip(sources.iter())
+        .all(|((_, unit), source)| unit == source)
+        && materialization.dimensions.len() + materialization.time_grains.len()
+            == dimensions.len() + time_grains.len();
+
+    let mut grain = dimensions
+        .iter()
+        .map(|d| (quoted(d), d.to_string()))
+        .collect::<Vec<_>>();
+    for ((time_grain, unit), source) in time_grains.iter().zip(sources) {
+        let source_column = quoted(&time_grain_column(time_grain.name(), &source));
+        let expr = if *unit == source {
+            source_column
+        } else {
+            truncate(&source_column, unit, time_grain.fiscal_year_start_month())?
+        };
+        grain.push((expr, time_grain_column(time_grain.name(), unit)));
+    }
+    let table = materialization.table_name();
+
+    let sql = if is_same_grain {
+        let projection = grain
+            .iter()
+            .map(|(expr, _)| expr.clone())
+            .chain(measures.iter().map(|m| quoted(m)))
+            .collect::<Vec<_>>();
+        if projection.is_empty() {
+            return Ok(None);
+        }
+        format!("SELECT {} FROM {table}", projection.join(", "))
+    } else {
+        if metric.cumulative.is_some() || metric.period_offset.is_some() {
+            return Ok(None);
+        }
+        let mut projection = grain
+            .iter()
+            .map(|(expr, name)| format!("{expr} AS {}", quoted(name)))
+            .collect::<Vec<_>>();
+        for name in measures {
+            let Some(function) =
+                metric.get_measure(name).and_then(|m| rollup_function(&m))
+            else {
+                return Ok(None);
+            };
+            projection.push(format!("{function}({}) AS {}", quoted(name), quoted(name)));
+        }
+        if projection.is_empty() {
+            return Ok(None);
+        }
+        let mut sql = format!("SELECT {} FROM {table}", projection.join(", "));
+        if !grain.is_empty() {
+            let group_by = grain
+                .iter()
+                .map(|(expr, _)| expr.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            sql.push_str(&format!(" GROUP BY {group_by}"));
+        }
+        sql
+    };
+    Ok(Some(sql))
+}
+
+/// Return the first materialization of the model containing the referenced columns
+fn materialized_model<'a>(
+    wren_mdl: &'a WrenMDL,
+    model: &Model,
+    referenced: &HashSet<String>,
+    all_columns: bool,
+    now: Option<SystemTime>,
+) -> Result<Option<&'a Materialization>> {
+    if has_access_control(model) {
+        return Ok(None);
+    }
+    let required = model
+        .get_visible_columns()
+        .filter(|c| all_columns || referenced.contains(c.name()))
+        .collect::<Vec<_>>();
+    // The relationship columns can't be materialized
+    if required
+        .iter()
+        .any(|c| c.relationship.is_some() && referenced.contains(c.name()))
+    {
+        return Ok(None);
+    }
+    for materialization in wren_mdl.get_materializations(model.name()) {
+        if !materialization.is_fresh(model.refresh_time.as_deref(), now)? {
+            continue;
+        }
+        let covered = required
+            .iter()
+            .filter(|c| c.relationship.is_none())
+            .all(|c| materialization.dimensions.iter().any(|d| d == c.name()));
+        if covered {
+            return Ok(Some(materialization.as_ref()));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether the time truncated by `from` can be truncated again to `to`
+fn can_roll_up(from: &TimeUnit, to: &TimeUnit) -> bool {
+    let rank = |unit: &TimeUnit| match unit {
+        TimeUnit::Second => 0,
+        TimeUnit::Minute => 1,
+        TimeUnit::Hour => 2,
+        TimeUnit::Day => 3,
+        TimeUnit::Week | TimeUnit::IsoWeek => 4,
+        TimeUnit::Month => 5,
+        TimeUnit::Quarter => 6,
+        TimeUnit::Year | TimeUnit::FiscalYear => 7,
+    };
+    match from {
+        TimeUnit::Second | TimeUnit::Minute | TimeUnit::Hour | TimeUnit::Day => {
+            rank(from) < rank(to)
+        }
+        TimeUnit::Month => {
+            matches!(
+                to,
+                TimeUnit::Quarter | TimeUnit::Year | TimeUnit::FiscalYear
+            )
+        }
+        TimeUnit::Quarter => matches!(to, TimeUnit::Year),
+        _ => false,
+    }
+}
+
+/// Whether any model used by the metric requires the row-level or column-level access control,
+/// which isn't applied to the materialization.
+fn requires_access_control(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    visiting: &mut Vec<String>,
+) -> bool {
+    if visiting.iter().any(|name| name == metric.name()) {
+        return false;
+    }
+    visiting.push(metric.name().to_string());
+    let required = if metric.is_derived() {
+        metric
+            .measure
+            .iter()
+            .filter_map(|measure| measure.expression())
+            .filter_map(|expression| collect_identifiers(expression).ok())
+            .flatten()
+            // The identifiers are unqualified columns named like `metric.measure`
+            .filter_map(|c| {
+                c.name
+                    .split_once('.')
+                    .and_then(|(name, _)| wren_mdl.get_metric(name))
+            })
+            .any(|dependency| requires_access_control(wren_mdl, &dependency, visiting))
+    } else if let Some(model) = wren_mdl.get_model(metric.base_object()) {
+        // The related models could be joined by the dimensions
+        has_access_control(&model)
+            || model
+                .get_visible_columns()
+                .filter_map(|c| c.relationship.clone())
+                .filter_map(|name| wren_mdl.get_relationship(&name))
+                .flat_map(|relationship| relationship.models.clone())
+                .filter_map(|name| wren_mdl.get_model(&name))
+                .any(|model| has_access_control(&model))
+    } else {
+        wren_mdl
+            .get_metric(metric.base_object())
+            .map(|base| requires_access_control(wren_mdl, &base, visiting))
+            .unwrap_or(true)
+    };
+    visiting.pop();
+    required
+}
+
+fn has_access_control(model: &Model) -> bool {
+    !model.row_level_access_controls().is_empty()
+        || model
+            .get_visible_columns()
+            .any(|c| c.column_level_access_control.is_some())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::manifest::TimeUnit;
+    use crate::mdl::materialization::{can_roll_up, parse_refresh_time};
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_refresh_time() {
+        assert_eq!(
+            parse_refresh_time("30m").unwrap(),
+            Duration::from_secs(1800)
+        );
+        assert_eq!(parse_refresh_time("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(
+            parse_refresh_time("1d").unwrap(),
+            Duration::from_secs(86400)
+        );
+        assert_eq!(
+            parse_refresh_time("100ms").unwrap(),
+            Duration::from_millis(100)
+        );
+        assert!(parse_refresh_time("1y").is_err());
+        assert!(parse_refresh_time("m").is_err());
+    }
+
+    #[test]
+    fn test_roll_up() {
+        assert!(can_roll_up(&TimeUnit::Day, &TimeUnit::Month));
+        assert!(can_roll_up(&TimeUnit::Day, &TimeUnit::IsoWeek));
+        assert!(can_roll_up(&TimeUnit::Month, &TimeUnit::FiscalYear));
+        assert!(!can_roll_up(&TimeUnit::Month, &TimeUnit::Week));
+        assert!(!can_roll_up(&TimeUnit::Week, &TimeUnit::Month));
+        assert!(!can_roll_up(&TimeUnit::Month, &TimeUnit::Day));
+    }
+}
@@ -0,0 +1,1362 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::{ControlFlow, Range};
+use std::sync::Arc;
+
+use crate::mdl::manifest::{
+    Additivity, Column, CumulativeWindow, DataSource, JoinType, Metric, Model,
+    NonAdditiveWindow, PeriodOffset, TimeGrain, TimeUnit,
+};
+use crate::mdl::relationship_path::{
+    mdl_table_name, normalize_ident, requalify_condition,
+};
+use crate::mdl::utils::{collect_identifiers, quoted};
+use crate::mdl::WrenMDL;
+use datafusion::common::{not_impl_err, plan_datafusion_err, plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions, visit_expressions_mut, BinaryOperator, DuplicateTreatment, Expr,
+    FunctionArg, FunctionArgExpr, FunctionArguments, Ident, OrderBy, Query, Select,
+    SelectItem, SetExpr, Statement, TableFactor, TableWithJoins, Value, Visit, VisitMut,
+    VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+
+/// The function to truncate a time grain of a metric. e.g. `time_grain(created_at, 'month')`
+const TIME_GRAIN_FUNCTION: &str = "time_grain";
+const WINDOW_BASE_ALIAS: &str = "__metric";
+const CURRENT_PERIOD_ALIAS: &str = "__current";
+const PREVIOUS_PERIOD_ALIAS: &str = "__previous";
+const PREVIOUS_MEASURE_SUFFIX: &str = "_previous";
+const SNAPSHOT_TIME_ALIAS: &str = "__snapshot_time";
+const SNAPSHOT_ALIAS: &str = "__snapshot";
+
+/// Expand the metrics used in the query to the aggregation of their base objects.
+///
+/// A metric is aggregated by the dimensions and the time grain columns referenced in the query.
+/// A time grain column is the name of the time grain and the time unit joined by `__`. e.g.
+///
+/// ```sql
+/// SELECT m.status, m.created_at__month, m.revenue FROM order_metric m
+/// ```
+///
+/// is expanded to
+///
+/// ```sql
+/// SELECT m.status, m.created_at__month, m.revenue FROM (
+///   SELECT "status" AS "status", date_trunc('month', "created_at") AS "created_at__month", sum(totalprice) AS "revenue"
+///   FROM "orders" GROUP BY "status", date_trunc('month', "created_at")
+/// ) AS m
+/// ```
+///
+/// `time_grain(m.created_at, 'month')` is the same as `m.created_at__month`. The truncation is
+/// unparsed by [crate::mdl::dialect::WrenDialect] for the data source.
+///
+/// A derived metric is computed from the metrics it depends on at the same grain. A cumulative or
+/// period-over-period metric is computed along the time grain of its window, which should be queried.
+pub(crate) fn expand_metrics(
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+) -> Result<()> {
+    if wren_mdl.metrics().is_empty() {
+        return Ok(());
+    }
+    let mut expander = MetricExpander {
+        wren_mdl,
+        error: None,
+    };
+    let _ = statement.visit(&mut expander);
+    match expander.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Return the name of the column for the time unit of the time grain
+pub(crate) fn time_grain_column(time_grain: &str, unit: &TimeUnit) -> String {
+    format!("{time_grain}__{unit}")
+}
+
+/// Return the SQL expression truncating the ref column of the time grain to the time unit
+pub(crate) fn truncate_time(time_grain: &TimeGrain, unit: &TimeUnit) -> Result<String> {
+    truncate(
+        &quoted(&time_grain.ref_column),
+        unit,
+        time_grain.fiscal_year_start_month(),
+    )
+}
+
+pub(crate) fn truncate(
+    column: &str,
+    unit: &TimeUnit,
+    fiscal_year_start_month: u32,
+) -> Result<String> {
+    let truncated = match unit {
+        TimeUnit::Year
+        | TimeUnit::Quarter
+        | TimeUnit::Month
+        | TimeUnit::Day
+        | TimeUnit::Hour
+        | TimeUnit::Minute
+        | TimeUnit::Second => format!("date_trunc('{unit}', {column})"),
+        // The week of date_trunc starts on Monday like ISO 8601
+        TimeUnit::IsoWeek => format!("date_trunc('week', {column})"),
+        TimeUnit::Week => {
+            format!("date_trunc('week', {column} + INTERVAL '1' DAY) - INTERVAL '1' DAY")
+        }
+        TimeUnit::FiscalYear => match fiscal_year_start_month {
+            1 => format!("date_trunc('year', {column})"),
+            month @ 2..=12 => {
+                let offset = month - 1;
+                format!(
+                    "date_trunc('year', {column} - INTERVAL '{offset}' MONTH) + INTERVAL '{offset}' MONTH"
+                )
+            }
+            month => {
+                return plan_err!("Invalid start month {} of the fiscal year", month)
+            }
+        },
+    };
+    Ok(truncated)
+}
+
+/// Return the interval unit and the number of the interval units of a period of the time unit
+pub(crate) fn time_unit_interval(unit: &TimeUnit) -> (&'static str, u32) {
+    match unit {
+        TimeUnit::Year | TimeUnit::FiscalYear => ("YEAR", 1),
+        TimeUnit::Quarter => ("MONTH", 3),
+        TimeUnit::Month => ("MONTH", 1),
+        TimeUnit::Week | TimeUnit::IsoWeek => ("DAY", 7),
+        TimeUnit::Day => ("DAY", 1),
+        TimeUnit::Hour => ("HOUR", 1),
+        TimeUnit::Minute => ("MINUTE", 1),
+        TimeUnit::Second => ("SECOND", 1),
+    }
+}
+
+/// Return the SQL generating a row for each period of the time unit between the start and the
+/// end, e.g. `SELECT UNNEST(generate_series(start, end, INTERVAL '1' MONTH)) AS "column"`.
+/// Both of the start and the end are truncated to the time unit and included in the spine.
+///
+/// `generate_series` is unparsed to the dialect-specific SQL by the inner dialect.
+pub(crate) fn date_spine(
+    column: &str,
+    unit: &TimeUnit,
+    fiscal_year_start_month: u32,
+    start: &str,
+    end: &str,
+) -> Result<String> {
+    let (interval_unit, periods) = time_unit_interval(unit);
+    Ok(format!(
+        "SELECT UNNEST(generate_series({}, {}, INTERVAL '{periods}' {interval_unit})) AS {}",
+        truncate_timestamp(start, unit, fiscal_year_start_month)?,
+        truncate_timestamp(end, unit, fiscal_year_start_month)?,
+        quoted(column)
+    ))
+}
+
+/// Whether `generate_series` of the date spine can be unparsed for the data source. The file
+/// sources are executed by DataFusion.
+pub(crate) fn supports_date_spine(data_source: &DataSource) -> bool {
+    matches!(
+        data_source,
+        DataSource::Datafusion
+            | DataSource::DuckDB
+            | DataSource::Postgres
+            | DataSource::BigQuery
+            | DataSource::LocalFile
+            | DataSource::S3File
+            | DataSource::GcsFile
+            | DataSource::MinioFile
+    )
+}
+
+/// Return the SQL expression truncating the timestamp string to the time unit
+pub(crate) fn truncate_timestamp(
+    value: &str,
+    unit: &TimeUnit,
+    fiscal_year_start_month: u32,
+) -> Result<String> {
+    let timestamp = format!("CAST('{}' AS TIMESTAMP)", value.replace('\'', "''"));
+    truncate(&timestamp, unit, fiscal_year_start_month)
+}
+
+struct MetricExpander<'a> {
+    wren_mdl: &'a WrenMDL,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for MetricExpander<'_> {
+    type Break = ();
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.expand_query(query) {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// The metric relation used in the FROM clause
+struct MetricRelation {
+    qualifier: String,
+    metric: Arc<Metric>,
+}
+
+impl MetricExpander<'_> {
+    fn expand_query(&self, query: &mut Query) -> Result<()> {
+        let Query { body, order_by, .. } = query;
+        match body.as_mut() {
+            SetExpr::Select(select) => self.expand_select(select, order_by),
+            set_expr => self.expand_set_expr(set_expr),
+        }
+    }
+
+    fn expand_set_expr(&self, set_expr: &mut SetExpr) -> Result<()> {
+        match set_expr {
+            SetExpr::Select(select) => self.expand_select(select, &mut None),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.expand_set_expr(left)?;
+                self.expand_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn expand_select(
+        &self,
+        select: &mut Select,
+        order_by: &mut Option<OrderBy>,
+    ) -> Result<()> {
+        let relations = select
+            .from
+            .iter()
+            .flat_map(|t| {
+                std::iter::once(&t.relation).chain(t.joins.iter().map(|j| &j.relation))
+            })
+            .filter_map(|relation| self.metric_relation(relation))
+            .collect::<Vec<_>>();
+        if relations.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = None;
+        let mut rewrite =
+            |expr: &mut Expr| match rewrite_time_grain_function(expr, &relations) {
+                Ok(_) => ControlFlow::Continue(()),
+                Err(e) => {
+                    error = Some(e);
+                    ControlFlow::Break(())
+                }
+            };
+        let _ = visit_expressions_mut(select, &mut rewrite);
+        let _ = visit_expressions_mut(order_by, &mut rewrite);
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        let qualifiers = relations
+            .iter()
+            .map(|r| r.qualifier.as_str())
+            .collect::<Vec<_>>();
+        let mut references = collect_references(select, order_by, &qualifiers);
+        let mut filters = take_metric_filters(select, &relations, &mut references);
+        let wildcards = select_wildcards(select);
+
+        let empty = HashSet::new();
+        for relation in select.from.iter_mut().flat_map(|t| {
+            let TableWithJoins { relation, joins } = t;
+            std::iter::once(relation).chain(joins.iter_mut().map(|j| &mut j.relation))
+        }) {
+            let Some(MetricRelation { qualifier, metric }) =
+                self.metric_relation(relation)
+            else {
+                continue;
+            };
+            let all_dimensions = selects_all_columns(&wildcards, &qualifier);
+            let referenced = references.get(qualifier.as_str()).unwrap_or(&empty);
+            let sql = metric_query(
+                self.wren_mdl,
+                &metric,
+                referenced,
+                all_dimensions,
+                &filters.remove(&qualifier).unwrap_or_default(),
+                &mut vec![],
+            )?;
+            *relation = Parser::new(&GenericDialect {})
+                .try_with_sql(&format!("({sql}) AS {}", quoted(&qualifier)))?
+                .parse_table_factor()?;
+        }
+        Ok(())
+    }
+
+    fn metric_relation(&self, relation: &TableFactor) -> Option<MetricRelation> {
+        let TableFactor::Table { name, alias, .. } = relation else {
+            return None;
+        };
+        let metric = mdl_table_name(self.wren_mdl, name)
+            .and_then(|name| self.wren_mdl.get_metric(&name))?;
+        let qualifier = alias
+            .as_ref()
+            .map(|a| normalize_ident(&a.name))
+            .unwrap_or_else(|| metric.name().to_string());
+        Some(MetricRelation { qualifier, metric })
+    }
+}
+
+/// Collect the column names referenced by the expressions of the select and the order for each
+/// qualifier. An unqualified column could refer to any of the qualifiers. The WHERE clause isn't
+/// collected, because the filters on the dimensions are applied below the aggregation of the
+/// metrics and don't change the grain. See [take_metric_filters].
+pub(crate) fn collect_references(
+    select: &Select,
+    order_by: &Option<OrderBy>,
+    qualifiers: &[&str],
+) -> HashMap<String, HashSet<String>> {
+    let mut references: HashMap<String, HashSet<String>> = HashMap::new();
+    let unfiltered = Select {
+        selection: None,
+        ..select.clone()
+    };
+    collect_node_references(&unfiltered, qualifiers, &mut references);
+    collect_node_references(order_by, qualifiers, &mut references);
+    references
+}
+
+/// Collect the column names referenced by the expressions of the node for each qualifier
+pub(crate) fn collect_node_references<V: Visit>(
+    node: &V,
+    qualifiers: &[&str],
+    references: &mut HashMap<String, HashSet<String>>,
+) {
+    let _ = visit_expressions(node, |expr| {
+        match expr {
+            Expr::Identifier(ident) => {
+                for qualifier in qualifiers {
+                    references
+                        .entry(qualifier.to_string())
+                        .or_default()
+                        .insert(normalize_ident(ident));
+                }
+            }
+            Expr::CompoundIdentifier(idents) => {
+                if let [qualifier, column, ..] = idents.as_slice() {
+                    let qualifier = normalize_ident(qualifier);
+                    if qualifiers.contains(&qualifier.as_str()) {
+                        references
+                            .entry(qualifier)
+                            .or_default()
+                            .insert(normalize_ident(column));
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+/// Take the conjuncts of the WHERE clause which only refer to the dimensions and the time grain
+/// columns of a base metric, and return them for each qualifier. They're applied below the
+/// aggregation, so a dimension only used in the filters isn't grouped. The columns are
+/// unqualified in the returned filters.
+///
+/// The other conjuncts are kept and applied to the aggregated metrics, so their references are
+/// added to the grain. The filters on the time grains of a cumulative or period-over-period
+/// metric are kept as well, because the previous periods are required by the window.
+fn take_metric_filters(
+    select: &mut Select,
+    relations: &[MetricRelation],
+    references: &mut HashMap<String, HashSet<String>>,
+) -> HashMap<String, Vec<Expr>> {
+    let mut filters: HashMap<String, Vec<Expr>> = HashMap::new();
+    let Some(selection) = select.selection.take() else {
+        return filters;
+    };
+    let relation_count = select.from.iter().map(|t| t.joins.len() + 1).sum::<usize>();
+    let qualifiers = relations
+        .iter()
+        .map(|r| r.qualifier.as_str())
+        .collect::<Vec<_>>();
+    let mut remaining = vec![];
+    for mut conjunct in split_conjunction(selection) {
+        match pushdown_relation(&conjunct, relations, relation_count) {
+            Some(relation) => {
+                let _ = visit_expressions_mut(&mut conjunct, |expr| {
+                    let column = match expr {
+                        Expr::Identifier(ident) => normalize_ident(ident),
+                        Expr::CompoundIdentifier(idents) => normalize_ident(&idents[1]),
+                        _ => return ControlFlow::<()>::Continue(()),
+                    };
+                    *expr = Expr::Identifier(Ident::with_quote('"', column));
+                    ControlFlow::Continue(())
+                });
+                filters
+                    .entry(relation.qualifier.clone())
+                    .or_default()
+                    .push(conjunct);
+            }
+            None => {
+                collect_node_references(&conjunct, &qualifiers, references);
+                remaining.push(conjunct);
+            }
+        }
+    }
+    select.selection = remaining.into_iter().reduce(|left, right| Expr::BinaryOp {
+        left: Box::new(left),
+        op: BinaryOperator::And,
+        right: Box::new(right),
+    });
+    filters
+}
+
+/// Split the expression by the top-level AND
+fn split_conjunction(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut conjuncts = split_conjunction(*left);
+            conjuncts.extend(split_conjunction(*right));
+            conjuncts
+        }
+        Expr::Nested(nested)
+            if matches!(
+                nested.as_ref(),
+                Expr::BinaryOp {
+                    op: BinaryOperator::And,
+                    ..
+                }
+            ) =>
+        {
+            split_conjunction(*nested)
+        }
+        expr => vec![expr],
+    }
+}
+
+/// Return the metric relation which the filter can be applied below its aggregation
+fn pushdown_relation<'a>(
+    filter: &Expr,
+    relations: &'a [MetricRelation],
+    relation_count: usize,
+) -> Option<&'a MetricRelation> {
+    let mut columns = vec![];
+    let mut qualifiers = HashSet::new();
+    let mut pushable = true;
+    let _ = visit_expressions(filter, |expr| {
+        match expr {
+            Expr::Identifier(ident) => {
+                // The unqualified columns should refer to the only relation
+                pushable &= relation_count == 1;
+                columns.push(normalize_ident(ident));
+            }
+            Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+                [qualifier, column] => {
+                    qualifiers.insert(normalize_ident(qualifier));
+                    columns.push(normalize_ident(column));
+                }
+                _ => pushable = false,
+            },
+            Expr::Subquery(_) | Expr::Exists { .. } | Expr::InSubquery { .. } => {
+                pushable = false
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    if !pushable || columns.is_empty() || qualifiers.len() > 1 {
+        return None;
+    }
+    let relation = match qualifiers.into_iter().next() {
+        Some(qualifier) => relations.iter().find(|r| r.qualifier == qualifier)?,
+        None => relations.first()?,
+    };
+    let metric = &relation.metric;
+    if metric.is_derived() {
+        return None;
+    }
+    let is_windowed = metric.cumulative.is_some() || metric.period_offset.is_some();
+    columns
+        .iter()
+        .all(|column| {
+            has_grain_column(metric, column)
+                && (!is_windowed || metric.get_dimension(column).is_some())
+        })
+        .then_some(relation)
+}
+/// Return the qualifiers of the wildcards in the projection. `None` is the unqualified wildcard.
+pub(crate) fn select_wildcards(select: &Select) -> Vec<Option<String>> {
+    select
+        .projection
+        .iter()
+        .filter_map(|item| match item {
+            SelectItem::Wildcard(_) => Some(None),
+            SelectItem::QualifiedWildcard(name, _) => {
+                Some(name.0.last().map(normalize_ident))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub(crate) fn selects_all_columns(wildcards: &[Option<String>], qualifier: &str) -> bool {
+    wildcards
+        .iter()
+        .any(|w| w.as_ref().map(|w| w == qualifier).unwrap_or(true))
+}
+
+/// A dimension or a time grain column which the metric is aggregated by.
+/// The expression is evaluated on the base object.
+struct GrainColumn {
+    name: String,
+    expr: String,
+}
+
+/// Build the query of the metric at the grain of the referenced dimensions and time grains.
+/// `filters` refer to the grain columns and are applied before the aggregation of a base metric.
+/// `visiting` is the metrics being planned, used to detect the cyclic dependency of the derived metrics.
+fn metric_query(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    referenced: &HashSet<String>,
+    all_dimensions: bool,
+    filters: &[Expr],
+    visiting: &mut Vec<String>,
+) -> Result<String> {
+    if visiting.iter().any(|name| name == metric.name()) {
+        return plan_err!(
+            "cyclic dependency detected in metric: {} -> {}",
+            visiting.join(" -> "),
+            metric.name()
+        );
+    }
+    let grain = resolve_grain(metric, referenced, all_dimensions)?;
+    visiting.push(metric.name().to_string());
+    let sql = if metric.is_derived() {
+        derived_metric_query(wren_mdl, metric, &grain, visiting)
+    } else {
+        base_metric_query(wren_mdl, metric, &grain, filters)
+    };
+    visiting.pop();
+    match (&metric.cumulative, &metric.period_offset) {
+        (Some(_), Some(_)) => plan_err!(
+            "Metric {} can't be both cumulative and period-over-period",
+            metric.name()
+        ),
+        (Some(cumulative), None) => cumulative_query(metric, cumulative, &grain, &sql?),
+        (None, Some(period_offset)) => {
+            period_offset_query(metric, period_offset, &grain, &sql?)
+        }
+        (None, None) => sql,
+    }
+}
+
+fn resolve_grain(
+    metric: &Metric,
+    referenced: &HashSet<String>,
+    all_dimensions: bool,
+) -> Result<Vec<GrainColumn>> {
+    let mut grain = vec![];
+    for dimension in metric.dimension.iter() {
+        if !all_dimensions && !referenced.contains(dimension.name()) {
+            continue;
+        }
+        let expr = dimension
+            .expression()
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| quoted(dimension.name()));
+        grain.push(GrainColumn {
+            name: dimension.name().to_string(),
+            expr,
+        });
+    }
+    for time_grain in metric.time_grain.iter() {
+        for unit in TimeUnit::all() {
+            let column = time_grain_column(time_grain.name(), &unit);
+            if !referenced.contains(&column) {
+                continue;
+            }
+            if !time_grain.date_parts.contains(&unit) {
+                return plan_err!(
+                    "Time grain {} of metric {} doesn't support the time unit {}",
+                    time_grain.name(),
+                    metric.name(),
+                    unit
+                );
+            }
+            grain.push(GrainColumn {
+                expr: truncate_time(time_grain, &unit)?,
+                name: column,
+            });
+        }
+    }
+    Ok(grain)
+}
+
+/// Aggregate the measures on the base object by the grain
+fn base_metric_query(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    grain: &[GrainColumn],
+    filters: &[Expr],
+) -> Result<String> {
+    let mut exprs = grain
+        .iter()
+        .map(|column| column.expr.clone())
+        .collect::<Vec<_>>();
+    for measure in metric.measure.iter() {
+        let Some(expr) = measure.expression() else {
+            return plan_err!(
+                "Measure {} of metric {} should have an expression",
+                measure.name(),
+                metric.name()
+            );
+        };
+        exprs.push(expr.to_string());
+    }
+    if exprs.is_empty() {
+        return plan_err!("Metric {} doesn't have any column", metric.name());
+    }
+    let filter_start = exprs.len();
+    for filter in filters {
+        exprs.push(grain_column_filter(metric, filter)?);
+    }
+    let snapshot = semi_additive_window(metric)?;
+    // The filters and the time of the snapshots are rewritten with the other expressions
+    if let Some((time_grain, _)) = snapshot {
+        exprs.push(quoted(&time_grain.ref_column));
+    }
+    let from = match wren_mdl.get_model(metric.base_object()) {
+        Some(model) => {
+            let measures = grain.len()..grain.len() + metric.measure.len();
+            join_related_models(wren_mdl, &model, &mut exprs, measures)?
+        }
+        // the measures of a metric are aggregated again by a derived metric instead
+        None if wren_mdl.get_metric(metric.base_object()).is_some() => {
+            return plan_err!(
+                "Metric {} can't be aggregated from metric {}. Define it as a derived metric of the measures instead",
+                metric.name(),
+                metric.base_object()
+            );
+        }
+        None => quoted(metric.base_object()),
+    };
+    let snapshot_time = snapshot.and_then(|_| exprs.pop());
+    let filters = exprs.split_off(filter_start);
+
+    let names = grain
+        .iter()
+        .map(|column| column.name.as_str())
+        .chain(metric.measure.iter().map(|measure| measure.name()))
+        .collect::<Vec<_>>();
+    let mut projection = exprs
+        .iter()
+        .zip(names.iter())
+        .map(|(expr, name)| format!("{expr} AS {}", quoted(name)))
+        .collect::<Vec<_>>();
+    let mut group_by = exprs[..grain.len()].to_vec();
+    if let Some(snapshot_time) = &snapshot_time {
+        projection.push(format!(
+            "{snapshot_time} AS {}",
+            quoted(SNAPSHOT_TIME_ALIAS)
+        ));
+        group_by.push(snapshot_time.clone());
+    }
+    let mut sql = format!("SELECT {} FROM {from}", projection.join(", "));
+    if !filters.is_empty() {
+        sql.push_str(&format!(" WHERE {}", filters.join(" AND ")));
+    }
+    if !group_by.is_empty() {
+        sql.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+    }
+
+    let Some((_, window)) = snapshot else {
+        return Ok(sql);
+    };
+    // Keep the aggregation of the first or the last snapshot in each group of the grain
+    let function = match window {
+        NonAdditiveWindow::First => "min",
+        NonAdditiveWindow::Last => "max",
+    };
+    let partition = if grain.is_empty() {
+        String::new()
+    } else {
+        let columns = grain
+            .iter()
+            .map(|column| qualified(WINDOW_BASE_ALIAS, &column.name))
+            .collect::<Vec<_>>();
+        format!("PARTITION BY {}", columns.join(", "))
+    };
+    let projection = names
+        .iter()
+        .map(|name| qualified(WINDOW_BASE_ALIAS, name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(format!(
+        "SELECT {projection} FROM (SELECT {alias}.*, {function}({time}) OVER ({partition}) AS {snapshot} FROM ({sql}) AS {alias}) AS {alias} WHERE {time} = {snapshot_column}",
+        alias = quoted(WINDOW_BASE_ALIAS),
+        time = qualified(WINDOW_BASE_ALIAS, SNAPSHOT_TIME_ALIAS),
+        snapshot = quoted(SNAPSHOT_ALIAS),
+        snapshot_column = qualified(WINDOW_BASE_ALIAS, SNAPSHOT_ALIAS),
+    ))
+}
+
+fn qualified(qualifier: &str, name: &str) -> String {
+    format!("{}.{}", quoted(qualifier), quoted(name))
+}
+
+/// Return the time grain and the window of the semi-additive measures of the metric.
+/// The measures of a metric are aggregated together, so a semi-additive measure can't be mixed
+/// with the measures of other additivity.
+fn semi_additive_window(
+    metric: &Metric,
+) -> Result<Option<(&TimeGrain, NonAdditiveWindow)>> {
+    let mut windows = vec![];
+    for measure in metric.measure.iter() {
+        if measure.additivity != Some(Additivity::SemiAdditive) {
+            windows.push(None);
+            continue;
+        }
+        let Some(dimension) = &measure.non_additive_dimension else {
+            return plan_err!(
+                "Semi-additive measure {} of metric {} should declare the non-additive dimension",
+                measure.name(),
+                metric.name()
+            );
+        };
+        let Some(time_grain) = metric.get_time_grain(&dimension.time_grain) else {
+            return plan_err!(
+                "Time grain {} of measure {} not found in metric {}",
+                dimension.time_grain,
+                measure.name(),
+                metric.name()
+            );
+        };
+        windows.push(Some((time_grain, dimension.window)));
+    }
+    match windows.split_first() {
+        None => Ok(None),
+        Some((first, rest)) => {
+            if rest.iter().any(|window| window != first) {
+                return plan_err!(
+                    "Measures of metric {} should have the same non-additive dimension. Define the semi-additive measures in another metric",
+                    metric.name()
+                );
+            }
+            Ok(*first)
+        }
+    }
+}
+
+/// Return the function to aggregate the aggregated measure again, e.g. along the time in a
+/// cumulative metric or from a finer materialization. `None` if the measure can't be rolled up.
+///
+/// The declared additivity is respected. Otherwise, the function is inferred from the
+/// aggregation of the measure.
+pub(crate) fn rollup_function(measure: &Column) -> Option<&'static str> {
+    let inferred = measure.expression().and_then(infer_rollup_function);
+    match measure.additivity {
+        Some(Additivity::Additive) => inferred.or(Some("sum")),
+        Some(Additivity::SemiAdditive) | Some(Additivity::NonAdditive) => None,
+        None => inferred,
+    }
+}
+
+/// Infer the rollup function from the aggregation. A distinct count can't be rolled up.
+pub(crate) fn infer_rollup_function(expression: &str) -> Option<&'static str> {
+    let expr = Parser::new(&GenericDialect {})
+        .try_with_sql(expression)
+        .and_then(|mut parser| parser.parse_expr())
+        .ok()?;
+    let Expr::Function(function) = expr else {
+        return None;
+    };
+    if let FunctionArguments::List(list) = &function.args {
+        if list.duplicate_treatment == Some(DuplicateTreatment::Distinct) {
+            return None;
+        }
+    }
+    if function.over.is_some() || function.filter.is_some() {
+        return None;
+    }
+    match function.name.to_string().to_lowercase().as_str() {
+        "sum" | "count" => Some("sum"),
+        "min" => Some("min"),
+        "max" => Some("max"),
+        _ => None,
+    }
+}
+
+/// Join the related models referenced by the expressions through the relationship columns of
+/// the base model, e.g. `customer.c_name`, and return the FROM clause. The expressions are
+/// rewritten to refer to the joined models.
+///
+/// The rows of the base model must not be duplicated by the join, otherwise the measures are
+/// counted more than once. A to-one related model is joined directly. A to-many related model
+/// is pre-aggregated to the distinct join keys and the required columns before joining, so
+/// every row of the base model is counted once for each value of the dimensions. The measures
+/// in the `measures` range of the expressions can't refer to a to-many related model, because
+/// its equal values would be collapsed by the pre-aggregation.
+fn join_related_models(
+    wren_mdl: &WrenMDL,
+    model: &Model,
+    exprs: &mut [String],
+    measures: Range<usize>,
+) -> Result<String> {
+    let is_relationship = |ident: &Ident| {
+        model
+            .get_column(&normalize_ident(ident))
+            .map(|c| c.relationship.is_some())
+            .unwrap_or(false)
+    };
+    let mut parsed = exprs
+        .iter()
+        .map(|expr| {
+            Parser::new(&GenericDialect {})
+                .try_with_sql(expr)?
+                .parse_expr()
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    // The relationship columns and the columns required from the related models
+    let mut required: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    // The relationship columns referred by the measures
+    let mut measure_relationships = BTreeSet::new();
+    for (index, expr) in parsed.iter().enumerate() {
+        let _ = visit_expressions(expr, |expr| {
+            if let Expr::CompoundIdentifier(idents) = expr {
+                if let [relationship, column] = idents.as_slice() {
+                    if is_relationship(relationship) {
+                        let relationship = normalize_ident(relationship);
+                        if measures.contains(&index) {
+                            measure_relationships.insert(relationship.clone());
+                        }
+                        required
+                            .entry(relationship)
+                            .or_default()
+                            .insert(normalize_ident(column));
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    if required.is_empty() {
+        return Ok(quoted(model.name()));
+    }
+
+    let _ = visit_expressions_mut(&mut parsed, |expr| {
+        match expr {
+            Expr::CompoundIdentifier(idents) => {
+                if let [relationship, _] = idents.as_mut_slice() {
+                    if is_relationship(relationship) {
+                        *relationship =
+                            Ident::with_quote('"', normalize_ident(relationship));
+                    }
+                }
+            }
+            Expr::Identifier(ident) => {
+                *expr = Expr::CompoundIdentifier(vec![
+                    Ident::with_quote('"', model.name()),
+                    ident.clone(),
+                ]);
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    for (expr, parsed) in exprs.iter_mut().zip(parsed) {
+        *expr = parsed.to_string();
+    }
+
+    let mut from = quoted(model.name());
+    for (column_name, columns) in required {
+        let Some(relationship) = model
+            .get_column(&column_name)
+            .and_then(|c| c.relationship.clone())
+            .and_then(|name| wren_mdl.get_relationship(&name))
+        else {
+            return plan_err!(
+                "Relationship of {}.{} not found",
+                model.name(),
+                column_name
+            );
+        };
+        let Some(index) = relationship.models.iter().position(|m| m == model.name())
+        else {
+            return plan_err!(
+                "Model {} isn't a part of relationship {}",
+                model.name(),
+                relationship.name
+            );
+        };
+        let target = &relationship.models[1 - index];
+        if target == model.name() {
+            return not_impl_err!(
+                "Metric dimension through the self relationship {} isn't supported",
+                relationship.name
+            );
+        }
+        // The join type is declared from the first model to the second model.
+        let join_type = if index == 0 {
+            relationship.join_type
+        } else {
+            match relationship.join_type {
+                JoinType::OneToMany => JoinType::ManyToOne,
+                JoinType::ManyToOne => JoinType::OneToMany,
+                _ => relationship.join_type,
+            }
+        };
+        let condition = requalify_condition(
+            &relationship.condition,
+            &[
+                (model.name(), model.name()),
+                (target.as_str(), column_name.as_str()),
+            ],
+        )?;
+        let relation = if join_type.is_to_one() {
+            quoted(target)
+        } else if measure_relationships.contains(&column_name) {
+            return plan_err!(
+                "Measure can't refer to the to-many relationship {}.{}. Define the measure on a metric of {} instead",
+                model.name(),
+                column_name,
+                target
+            );
+        } else {
+            // The identifiers are unqualified columns named like `model.column`
+            let join_keys = collect_identifiers(&relationship.condition)?
+                .into_iter()
+                .filter_map(|c| {
+                    c.name
+                        .rsplit_once('.')
+                        .filter(|(relation, _)| relation == target)
+                        .map(|(_, name)| name.to_string())
+                })
+                .collect::<BTreeSet<_>>();
+            let columns = join_keys
+                .union(&columns)
+                .map(|c| quoted(c))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("(SELECT DISTINCT {columns} FROM {})", quoted(target))
+        };
+        from.push_str(&format!(
+            " LEFT JOIN {relation} AS {} ON {condition}",
+            quoted(&column_name)
+        ));
+    }
+    Ok(from)
+}
+
+/// Compute the measures of the derived metric over the metrics it depends on.
+///
+/// The measures refer to the measures of other metrics as `metric.measure`. Every dependency is
+/// planned at the same grain and joined by the grain columns, so the expressions are evaluated
+/// on the aggregated values. e.g. `revenue.total / orders.cnt`
+fn derived_metric_query(
+    wren_mdl: &WrenMDL,
+    metric: &Metric,
+    grain: &[GrainColumn],
+    visiting: &mut Vec<String>,
+) -> Result<String> {
+    let mut dependencies: Vec<Arc<Metric>> = vec![];
+    let mut measures = vec![];
+    for measure in metric.measure.iter() {
+        let Some(expr) = measure.expression() else {
+            return plan_err!(
+                "Measure {} of metric {} should have an expression",
+                measure.name(),
+                metric.name()
+            );
+        };
+        let mut expr = Parser::new(&GenericDialect {})
+            .try_with_sql(expr)?
+            .parse_expr()?;
+        let _ = visit_expressions_mut(&mut expr, |expr| {
+            if let Expr::CompoundIdentifier(idents) = expr {
+                if let [qualifier, _] = idents.as_mut_slice() {
+                    if let Some(dependency) =
+                        wren_mdl.get_metric(&normalize_ident(qualifier))
+                    {
+                        *qualifier = Ident::with_quote('"', dependency.name());
+                        if !dependencies.iter().any(|d| d.name() == dependency.name()) {
+                            dependencies.push(dependency);
+                        }
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        measures.push(format!("{expr} AS {}", quoted(measure.name())));
+    }
+    if dependencies.is_empty() {
+        return plan_err!(
+            "Derived metric {} should refer to the measures of other metrics like metric.measure",
+            metric.name()
+        );
+    }
+
+    let grain_names = grain
+        .iter()
+        .map(|column| column.name.clone())
+        .collect::<HashSet<_>>();
+    let mut from = vec![];
+    for (index, dependency) in dependencies.iter().enumerate() {
+        if let Some(column) = grain
+            .iter()
+            .find(|column| !has_grain_column(dependency, &column.name))
+        {
+            return plan_err!(
+                "Metric {} doesn't have {} required by derived metric {}",
+                dependency.name(),
+                column.name,
+                metric.name()
+            );
+        }
+        let sql = metric_query(wren_mdl, dependency, &grain_names, false, &[], visiting)?;
+        let relation = format!("({sql}) AS {}", quoted(dependency.name()));
+        if index == 0 {
+            from.push(relation);
+        } else if grain.is_empty() {
+            from.push(format!("CROSS JOIN {relation}"));
+        } else {
+            let condition = grain
+                .iter()
+                .map(|column| {
+                    format!(
+                        "{} = {}.{}",
+                        coalesce_grain_column(&dependencies[..index], &column.name),
+                        quoted(dependency.name()),
+                        quoted(&column.name)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" AND ");
+            from.push(format!("FULL JOIN {relation} ON {condition}"));
+        }
+    }
+    let projection = grain
+        .iter()
+        .map(|column| {
+            format!(
+                "{} AS {}",
+                coalesce_grain_column(&dependencies, &column.name),
+                quoted(&column.name)
+            )
+        })
+        .chain(measures)
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(format!("SELECT {projection} FROM {}", from.join(" ")))
+}
+
+/// Accumulate the aggregated measures along the time grain of the window.
+/// The accumulation is partitioned by the other grain columns and the reset period.
+fn cumulative_query(
+    metric: &Metric,
+    cumulative: &CumulativeWindow,
+    grain: &[GrainColumn],
+    sql: &str,
+) -> Result<String> {
+    let (order_column, _) = window_grain_column(metric, &cumulative.time_grain, grain)?;
+    let order_column = quoted(&order_column.name);
+    let mut partition = grain
+        .iter()
+        .map(|column| quoted(&column.name))
+        .filter(|column| column != &order_column)
+        .collect::<Vec<_>>();
+    if let Some(reset) = &cumulative.reset {
+        let fiscal_year_start_month = metric
+            .get_time_grain(&cumulative.time_grain)
+            .map(|time_grain| time_grain.fiscal_year_start_month())
+            .unwrap_or(1);
+        partition.push(truncate(&order_column, reset, fiscal_year_start_month)?);
+    }
+    let partition = if partition.is_empty() {
+        String::new()
+    } else {
+        format!("PARTITION BY {} ", partition.join(", "))
+    };
+    let projection = grain
+        .iter()
+        .map(|column| quoted(&column.name))
+        .map(Ok)
+        .chain(metric.measure.iter().map(|measure| {
+            let Some(function) = rollup_function(measure) else {
+                return plan_err!(
+                    "Measure {} of metric {} can't be accumulated because it isn't additive",
+                    measure.name(),
+                    metric.name()
+                );
+            };
+            Ok(format!(
+                "{function}({measure}) OVER ({partition}ORDER BY {order_column} ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) AS {measure}",
+                measure = quoted(measure.name()),
+            ))
+        }))
+        .collect::<Result<Vec<_>>>()?
+        .join(", ");
+    Ok(format!(
+        "SELECT {projection} FROM ({sql}) AS {}",
+        quoted(WINDOW_BASE_ALIAS)
+    ))
+}
+
+/// Compare with the aggregated measures of the offset period before. The measures of the current
+/// period are kept, and the measures of the previous period are suffixed by `_previous`. The
+/// previous period is joined by the shifted time grain instead of `lag`, so the missing periods
+/// are null.
+fn period_offset_query(
+    metric: &Metric,
+    period_offset: &PeriodOffset,
+    grain: &[GrainColumn],
+    sql: &str,
+) -> Result<String> {
+    if period_offset.offset == 0 {
+        return plan_err!(
+            "The period offset of metric {} should be positive",
+            metric.name()
+        );
+    }
+    let (order_column, unit) =
+        window_grain_column(metric, &period_offset.time_grain, grain)?;
+    let (interval_unit, periods) = time_unit_interval(&unit);
+    let current = quoted(CURRENT_PERIOD_ALIAS);
+    let previous = quoted(PREVIOUS_PERIOD_ALIAS);
+    let condition = grain
+        .iter()
+        .map(|column| {
+            let name = quoted(&column.name);
+            if column.name == order_column.name {
+                format!(
+                    "{previous}.{name} = {current}.{name} - INTERVAL '{}' {interval_unit}",
+                    periods * period_offset.offset
+                )
+            } else {
+                format!("{previous}.{name} = {current}.{name}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let projection = grain
+        .iter()
+        .map(|column| format!("{current}.{name} AS {name}", name = quoted(&column.name)))
+        .chain(metric.measure.iter().flat_map(|measure| {
+            let name = quoted(measure.name());
+            let previous_name =
+                quoted(&format!("{}{PREVIOUS_MEASURE_SUFFIX}", measure.name()));
+            [
+                format!("{current}.{name} AS {name}"),
+                format!("{previous}.{name} AS {previous_name}"),
+            ]
+        }))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(format!(
+        "SELECT {projection} FROM ({sql}) AS {current} LEFT JOIN ({sql}) AS {previous} ON {condition}"
+    ))
+}
+
+/// Find the grain column of the time grain which the window is ordered by
+fn window_grain_column<'a>(
+    metric: &Metric,
+    time_grain: &str,
+    grain: &'a [GrainColumn],
+) -> Result<(&'a GrainColumn, TimeUnit)> {
+    grain
+        .iter()
+        .find_map(|column| {
+            TimeUnit::all()
+                .into_iter()
+                .find(|unit| time_grain_column(time_grain, unit) == column.name)
+                .map(|unit| (column, unit))
+        })
+        .ok_or_else(|| {
+            plan_datafusion_err!(
+                "Metric {} should be queried by a time unit of time grain {}. e.g. {}",
+                metric.name(),
+                time_grain,
+                time_grain_column(time_grain, &TimeUnit::Month)
+            )
+        })
+}
+
+/// Replace the grain columns in the filter with their expressions on the base object
+fn grain_column_filter(metric: &Metric, filter: &Expr) -> Result<String> {
+    let mut filter = filter.clone();
+    let mut error = None;
+    let _ = visit_expressions_mut(&mut filter, |expr| {
+        let Expr::Identifier(ident) = expr else {
+            return ControlFlow::Continue(());
+        };
+        match grain_column_expr(metric, &ident.value).and_then(|column| {
+            Ok(Parser::new(&GenericDialect {})
+                .try_with_sql(&column)?
+                .parse_expr()?)
+        }) {
+            Ok(column) => {
+                *expr = Expr::Nested(Box::new(column));
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(filter.to_string()),
+    }
+}
+
+/// Return the expression of the dimension or the time grain column on the base object
+fn grain_column_expr(metric: &Metric, name: &str) -> Result<String> {
+    if let Some(dimension) = metric.get_dimension(name) {
+        return Ok(dimension
+            .expression()
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| quoted(dimension.name())));
+    }
+    for time_grain in metric.time_grain.iter() {
+        for unit in time_grain.date_parts.iter() {
+            if time_grain_column(time_grain.name(), unit) == name {
+                return truncate_time(time_grain, unit);
+            }
+        }
+    }
+    plan_err!("Column {} of metric {} not found", name, metric.name())
+}
+
+fn has_grain_column(metric: &Metric, name: &str) -> bool {
+    metric.get_dimension(name).is_some()
+        || metric.time_grain.iter().any(|time_grain| {
+            time_grain
+                .date_parts
+                .iter()
+                .any(|unit| time_grain_column(time_grain.name(), unit) == name)
+        })
+}
+
+/// The grain column of the joined metrics. The value could be null in some metrics of the full join.
+fn coalesce_grain_column(metrics: &[Arc<Metric>], name: &str) -> String {
+    let columns = metrics
+        .iter()
+        .map(|metric| format!("{}.{}", quoted(metric.name()), quoted(name)))
+        .collect::<Vec<_>>();
+    match columns.as_slice() {
+        [column] => column.clone(),
+        _ => format!("coalesce({})", columns.join(", ")),
+    }
+}
+
+/// Rewrite `time_grain(column, 'unit')` to the time grain column `column__unit`
+fn rewrite_time_grain_function(
+    expr: &mut Expr,
+    relations: &[MetricRelation],
+) -> Result<()> {
+    let Expr::Function(function) = expr else {
+        return Ok(());
+    };
+    let [name] = function.name.0.as_slice() else {
+        return Ok(());
+    };
+    if !name.value.eq_ignore_ascii_case(TIME_GRAIN_FUNCTION) {
+        return Ok(());
+    }
+    let args = match &function.args {
+        FunctionArguments::List(list) => list
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(expr),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+    let Some([column, Expr::Value(Value::SingleQuotedString(unit))]) = args.as_deref()
+    else {
+        return plan_err!(
+            "{} should be called with a time grain and a time unit. e.g. time_grain(created_at, 'month')",
+            TIME_GRAIN_FUNCTION
+        );
+    };
+    let Some(unit) = TimeUnit::all()
+        .into_iter()
+        .find(|u| u.to_string() == unit.to_lowercase())
+    else {
+        return plan_err!("Unknown time unit {}", unit);
+    };
+
+    let (qualifier, time_grain) = match column {
+        Expr::Identifier(ident) => (None, ident),
+        Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [qualifier, time_grain] => (Some(qualifier), time_grain),
+            _ => return plan_err!("Invalid time grain {}", column),
+        },
+        _ => return plan_err!("Invalid time grain {}", column),
+    };
+    let time_grain_name = normalize_ident(time_grain);
+    let found = relations.iter().any(|r| {
+        qualifier
+            .map(|q| normalize_ident(q) == r.qualifier)
+            .unwrap_or(true)
+            && r.metric.get_time_grain(&time_grain_name).is_some()
+    });
+    if !found {
+        return plan_err!("Time grain {} not found", column);
+    }
+
+    let column_ident = Ident::with_quote('"', time_grain_column(&time_grain_name, &unit));
+    *expr = match qualifier {
+        Some(qualifier) => {
+            Expr::CompoundIdentifier(vec![qualifier.clone(), column_ident])
+        }
+        None => Expr::Identifier(column_ident),
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::ColumnBuilder;
+    use crate::mdl::manifest::Additivity;
+    use crate::mdl::metric::{infer_rollup_function, rollup_function};
+
+    #[test]
+    fn test_roll_up() {
+        assert_eq!(infer_rollup_function("sum(o_totalprice)"), Some("sum"));
+        assert_eq!(infer_rollup_function("COUNT(*)"), Some("sum"));
+        assert_eq!(infer_rollup_function("count(DISTINCT o_custkey)"), None);
+        assert_eq!(infer_rollup_function("avg(o_totalprice)"), None);
+        assert_eq!(infer_rollup_function("sum(a) / count(b)"), None);
+
+        let measure = |expression: &str, additivity: Option<Additivity>| {
+            let mut builder = ColumnBuilder::new("m", "int").expression(expression);
+            if let Some(additivity) = additivity {
+                builder = builder.additivity(additivity);
+            }
+            builder.build()
+        };
+        assert_eq!(rollup_function(&measure("max(a)", None)), Some("max"));
+        assert_eq!(
+            rollup_function(&measure("sum(a) + sum(b)", Some(Additivity::Additive))),
+            Some("sum")
+        );
+        assert_eq!(
+            rollup_function(&measure("sum(a)", Some(Additivity::SemiAdditive))),
+            None
+        );
+        assert_eq!(
+            rollup_function(&measure("count(a)", Some(Additivity::NonAdditive))),
+            None
+        );
+    }
+}
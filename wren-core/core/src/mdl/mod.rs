@@ -1,23 +1,33 @@
 use crate::logical_plan::analyze::access_control::validate_clac_rule;
-use crate::logical_plan::utils::{from_qualified_name_str, try_map_data_type};
+use crate::logical_plan::utils::{
+    from_qualified_name_str, try_map_data_type, try_map_data_type_by_data_source,
+};
 use crate::mdl::builder::ManifestBuilder;
-use crate::mdl::context::{create_ctx_with_mdl, WrenDataSource};
+use crate::mdl::connector::Connector;
+use crate::mdl::context::{
+    create_ctx_and_mdl, create_ctx_with_mdl, WrenConfig, WrenDataSource,
+};
+use crate::mdl::federation::SqlExecutor;
 use crate::mdl::function::{
     ByPassAggregateUDF, ByPassScalarUDF, ByPassWindowFunction, FunctionType,
     RemoteFunction,
 };
 use crate::mdl::manifest::{Column, Manifest, Metric, Model, View};
-use crate::mdl::utils::to_field;
 use crate::DataFusionError;
 use context::SessionPropertiesRef;
 use datafusion::arrow::datatypes::Field;
-use datafusion::common::internal_datafusion_err;
-use datafusion::datasource::TableProvider;
+use datafusion::common::tree_node::TreeNodeRecursion;
+use datafusion::common::{internal_datafusion_err, plan_err};
+use datafusion::datasource::{source_as_provider, TableProvider};
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
-use datafusion::logical_expr::{AggregateUDF, ScalarUDF, WindowUDF};
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::logical_expr::{
+    AggregateUDF, LogicalPlan, LogicalPlanBuilder, ScalarUDF, WindowUDF,
+};
+use datafusion::physical_planner::{DefaultPhysicalPlanner, PhysicalPlanner};
 use datafusion::prelude::SessionContext;
-use datafusion::sql::parser::DFParser;
+use datafusion::sql::parser::{DFParser, Statement as DFStatement};
 use datafusion::sql::sqlparser::ast::{Expr, ExprWithAlias, Ident};
 use datafusion::sql::sqlparser::dialect::dialect_from_str;
 use datafusion::sql::unparser::Unparser;
@@ -26,7 +36,10 @@ pub use dataset::Dataset;
 use dialect::WrenDialect;
 use log::{debug, info};
 use manifest::Relationship;
+use materialization::Materialization;
 use parking_lot::RwLock;
+use semantic_query::SemanticQuery;
+use std::collections::HashSet;
 use std::hash::Hash;
 use std::{collections::HashMap, sync::Arc};
 use wren_core_base::mdl::DataSource;
@@ -34,11 +47,22 @@ use wren_core_base::mdl::DataSource;
 pub mod builder {
     pub use wren_core_base::mdl::builder::*;
 }
+pub mod connector;
 pub mod context;
+mod cte;
 pub(crate) mod dataset;
 mod dialect;
+pub mod drift;
+pub mod federation;
+pub mod file_source;
 pub mod function;
+pub mod inference;
+mod join_inference;
 pub mod lineage;
+pub mod materialization;
+mod metric;
+mod relationship_path;
+pub mod semantic_query;
 pub mod manifest {
     pub use wren_core_base::mdl::manifest::*;
 }
@@ -93,6 +117,40 @@ impl AnalyzedWrenMDL {
         })
     }
 
+    /// Analyze the manifest whose models are scanned through the connector, so the queries
+    /// across the models of different data sources can be executed by the local runtime.
+    pub fn analyze_with_connector(
+        manifest: Manifest,
+        properties: SessionPropertiesRef,
+        connector: Arc<dyn Connector>,
+    ) -> Result<Self> {
+        let wren_mdl = Arc::new(WrenMDL::infer_and_register_remote_table_with_connector(
+            manifest,
+            properties,
+            Some(connector),
+        )?);
+        let lineage = Arc::new(lineage::Lineage::new(&wren_mdl)?);
+        Ok(AnalyzedWrenMDL { wren_mdl, lineage })
+    }
+
+    /// Analyze the manifest with the materializations of the cached metrics and models
+    pub fn analyze_with_materializations(
+        manifest: Manifest,
+        properties: SessionPropertiesRef,
+        materializations: Vec<Materialization>,
+    ) -> Result<Self> {
+        let mut wren_mdl =
+            WrenMDL::infer_and_register_remote_table(manifest, properties)?;
+        for materialization in materializations {
+            wren_mdl.register_materialization(materialization);
+        }
+        let lineage = lineage::Lineage::new(&wren_mdl)?;
+        Ok(AnalyzedWrenMDL {
+            wren_mdl: Arc::new(wren_mdl),
+            lineage: Arc::new(lineage),
+        })
+    }
+
     pub fn wren_mdl(&self) -> Arc<WrenMDL> {
         Arc::clone(&self.wren_mdl)
     }
@@ -109,6 +167,8 @@ pub struct WrenMDL {
     pub qualified_references: HashMap<datafusion::common::Column, ColumnReference>,
     pub register_tables: RegisterTables,
     pub catalog_schema_prefix: String,
+    /// The materializations of the cached metrics and models keyed by the object name
+    pub materializations: HashMap<String, Vec<Arc<Materialization>>>,
 }
 
 impl Hash for WrenMDL {
@@ -172,6 +232,7 @@ impl WrenMDL {
             manifest,
             qualified_references: qualifed_references,
             register_tables: HashMap::new(),
+            materializations: HashMap::new(),
         }
     }
 
@@ -184,6 +245,15 @@ impl WrenMDL {
     pub fn infer_and_register_remote_table(
         manifest: Manifest,
         properties: SessionPropertiesRef,
+    ) -> Result<Self> {
+        Self::infer_and_register_remote_table_with_connector(manifest, properties, None)
+    }
+
+    /// Register the remote tables which are scanned through the connector if it's given.
+    pub fn infer_and_register_remote_table_with_connector(
+        manifest: Manifest,
+        properties: SessionPropertiesRef,
+        connector: Option<Arc<dyn Connector>>,
     ) -> Result<Self> {
         let mut mdl = WrenMDL::new(manifest);
         let sources: Vec<_> = mdl
@@ -191,6 +261,7 @@ impl WrenMDL {
             .iter()
             .map(|model| {
                 let name = TableReference::from(model.table_reference());
+                let data_source = mdl.model_type_source(model);
                 let available_columns = model
                     .columns
                     .iter()
@@ -206,11 +277,17 @@ impl WrenMDL {
                     .into_iter()
                     .filter(|c| c.is_some())
                     .filter_map(|column| {
-                        Self::infer_source_column(&column.unwrap()).ok().flatten()
+                        Self::infer_source_column(&column.unwrap(), &data_source)
+                            .ok()
+                            .flatten()
                     })
                     .collect();
                 let schema = Arc::new(datafusion::arrow::datatypes::Schema::new(fields));
-                let datasource = WrenDataSource::new_with_schema(schema);
+                let datasource = match &connector {
+                    Some(connector) => WrenDataSource::new_with_schema(schema)
+                        .with_connector(Arc::clone(connector), name.clone()),
+                    None => WrenDataSource::new_with_schema(schema),
+                };
                 Ok((name.to_quoted_string(), Arc::new(datasource)))
             })
             .collect::<Result<Vec<_>>>()?;
@@ -227,8 +304,12 @@ impl WrenMDL {
     /// If the column has an expression, it will try to infer the source column from the expression.
     /// If the expression is a simple column reference, it's the source column name.
     /// If the expression is a complex expression, it can't be inferred.
+    /// The type of the column is mapped by the type names of the data source.
     ///
-    fn infer_source_column(column: &Column) -> Result<Option<Field>> {
+    pub(crate) fn infer_source_column(
+        column: &Column,
+        data_source: &DataSource,
+    ) -> Result<Option<Field>> {
         if column.is_calculated || column.relationship.is_some() {
             return Ok(None);
         }
@@ -239,14 +320,18 @@ impl WrenMDL {
             if let Some(name) = Self::collect_one_column(&expr) {
                 Ok(Some(Field::new(
                     alias.map(|a| a.value).unwrap_or_else(|| name.value.clone()),
-                    try_map_data_type(&column.r#type)?,
+                    try_map_data_type_by_data_source(&column.r#type, data_source)?,
                     column.not_null,
                 )))
             } else {
                 Ok(None)
             }
         } else {
-            Ok(Some(to_field(column)?))
+            Ok(Some(Field::new(
+                &column.name,
+                try_map_data_type_by_data_source(&column.r#type, data_source)?,
+                column.not_null,
+            )))
         }
     }
 
@@ -279,6 +364,21 @@ impl WrenMDL {
         self.register_tables.get(name).cloned()
     }
 
+    pub fn register_materialization(&mut self, materialization: Materialization) {
+        self.materializations
+            .entry(materialization.object.clone())
+            .or_default()
+            .push(Arc::new(materialization));
+    }
+
+    /// Get the materializations of the metric or the model in the registered order
+    pub fn get_materializations(&self, object: &str) -> &[Arc<Materialization>] {
+        self.materializations
+            .get(object)
+            .map(|m| m.as_slice())
+            .unwrap_or_default()
+    }
+
     pub fn get_register_tables(&self) -> &RegisterTables {
         &self.register_tables
     }
@@ -311,6 +411,34 @@ impl WrenMDL {
         self.manifest.data_source
     }
 
+    /// Return the data source of the model, which falls back to the data source of the manifest
+    pub fn model_data_source(&self, model: &Model) -> Option<DataSource> {
+        model.data_source().or(self.manifest.data_source)
+    }
+
+    /// Return the data source whose type names are used by the columns of the model. The
+    /// default data source is used if neither the model nor the manifest has one.
+    pub fn model_type_source(&self, model: &Model) -> DataSource {
+        self.model_data_source(model).unwrap_or_default()
+    }
+
+    /// Return the data source of the model the metric is aggregated from. The base metrics are
+    /// followed until the model, and it falls back to the data source of the manifest.
+    pub fn metric_data_source(&self, metric: &Metric) -> Option<DataSource> {
+        let mut base_object = metric.base_object().to_string();
+        // the number of the metrics bounds the walk in case the base metrics are cyclic
+        for _ in 0..self.manifest.metrics.len() {
+            if let Some(model) = self.get_model(&base_object) {
+                return self.model_data_source(&model);
+            }
+            let Some(base_metric) = self.get_metric(&base_object) else {
+                break;
+            };
+            base_object = base_metric.base_object().to_string();
+        }
+        self.manifest.data_source
+    }
+
     pub fn get_model(&self, name: &str) -> Option<Arc<Model>> {
         self.manifest
             .models
@@ -327,6 +455,14 @@ impl WrenMDL {
             .cloned()
     }
 
+    pub fn get_metric(&self, name: &str) -> Option<Arc<Metric>> {
+        self.manifest
+            .metrics
+            .iter()
+            .find(|metric| metric.name == name)
+            .cloned()
+    }
+
     pub fn get_relationship(&self, name: &str) -> Option<Arc<Relationship>> {
         self.manifest
             .relationships
@@ -375,6 +511,164 @@ pub async fn transform_sql_with_ctx(
     sql: &str,
 ) -> Result<String> {
     info!("wren-core received SQL: {}", sql);
+    let (ctx, analyzed) = plan_sql_with_ctx(
+        ctx,
+        Arc::clone(&analyzed_mdl),
+        remote_functions,
+        properties,
+        sql,
+    )
+    .await?;
+
+    let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
+    let wren_dialect = WrenDialect::new(&data_source);
+    let unparser = Unparser::new(&wren_dialect).with_pretty(true);
+    // show the planned sql
+    let sql = if WrenConfig::from_session(&ctx).enable_model_cte {
+        let wren_mdl = analyzed_mdl.wren_mdl();
+        let model_names = wren_mdl
+            .models()
+            .iter()
+            .map(|model| model.name())
+            .collect::<HashSet<_>>();
+        cte::unparse_with_reused_models(&unparser, &analyzed, &model_names)?
+    } else {
+        unparser.plan_to_sql(&analyzed)?
+    };
+    // TODO: workaround to remove unnecessary catalog and schema of mdl
+    let replaced = sql
+        .to_string()
+        .replace(analyzed_mdl.wren_mdl().catalog_schema_prefix(), "");
+    info!("wren-core planned SQL: {}", replaced);
+    Ok(replaced)
+}
+
+/// Transform the semantic query to the planned SQL based on the MDL with the SessionContext
+pub async fn transform_semantic_query_with_ctx(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    query: &SemanticQuery,
+) -> Result<String> {
+    let sql = query.to_sql(&analyzed_mdl.wren_mdl())?;
+    transform_sql_with_ctx(ctx, analyzed_mdl, remote_functions, properties, &sql).await
+}
+
+/// Plan the semantic query to the optimized LogicalPlan based on the MDL with the SessionContext
+pub async fn semantic_query_to_plan(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    query: &SemanticQuery,
+) -> Result<LogicalPlan> {
+    let sql = query.to_sql(&analyzed_mdl.wren_mdl())?;
+    info!("wren-core compiled semantic query: {}", sql);
+    let (_, plan) =
+        plan_sql_with_ctx(ctx, analyzed_mdl, remote_functions, properties, &sql).await?;
+    Ok(plan)
+}
+
+/// Plan the SQL with the rules of the local runtime and execute it in the SessionContext.
+///
+/// The result is streamed as Arrow record batches and stops after `limit` rows if it's given.
+/// Dropping the stream cancels the execution.
+pub async fn execute_sql(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    properties: SessionPropertiesRef,
+    sql: &str,
+    limit: Option<usize>,
+) -> Result<SendableRecordBatchStream> {
+    info!("wren-core executes SQL: {}", sql);
+    let ctx =
+        create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), properties, true).await?;
+    let mut plan = create_logical_plan(&ctx, &analyzed_mdl.wren_mdl(), sql).await?;
+    if limit.is_some() {
+        plan = LogicalPlanBuilder::from(plan).limit(0, limit)?.build()?;
+    }
+    let state = ctx.state();
+    let optimized = state.optimize(&plan)?;
+    check_executable(&optimized)?;
+    let physical = state
+        .query_planner()
+        .create_physical_plan(&optimized, &state)
+        .await?;
+    datafusion::physical_plan::execute_stream(physical, ctx.task_ctx())
+}
+
+/// Return an error if the plan scans a model without the source table, e.g. a model of a
+/// remote data source whose table or connector isn't registered.
+fn check_executable(plan: &LogicalPlan) -> Result<()> {
+    plan.apply_with_subqueries(|plan| {
+        if let LogicalPlan::TableScan(scan) = plan {
+            let source = source_as_provider(&scan.source)?;
+            if source
+                .as_any()
+                .downcast_ref::<WrenDataSource>()
+                .is_some_and(|source| !source.is_executable())
+            {
+                return plan_err!(
+                    "Table {} can't be executed locally. Register the table or a connector of its data source",
+                    scan.table_name
+                );
+            }
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(())
+}
+
+/// Execute the SQL whose models are stored in different data sources.
+///
+/// The SQL is planned with the rules for unparsing. The sub-plans of each remote data
+/// source are unparsed by its dialect and executed by the [SqlExecutor]. Their results
+/// are joined by the local runtime, which only optimizes the merged plan.
+pub async fn execute_federated_sql(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+    limit: Option<usize>,
+    executor: Arc<dyn SqlExecutor>,
+) -> Result<SendableRecordBatchStream> {
+    info!("wren-core executes federated SQL: {}", sql);
+    // the file sources are read by the local runtime, so they're registered before planning
+    let (local_ctx, analyzed_mdl) =
+        create_ctx_and_mdl(ctx, analyzed_mdl, Arc::clone(&properties), true).await?;
+    let (_, mut plan) = plan_sql_with_ctx(
+        &local_ctx,
+        Arc::clone(&analyzed_mdl),
+        remote_functions,
+        properties,
+        sql,
+    )
+    .await?;
+    if limit.is_some() {
+        plan = LogicalPlanBuilder::from(plan).limit(0, limit)?.build()?;
+    }
+    let federated = federation::federate(&analyzed_mdl.wren_mdl(), plan, executor)?;
+    debug!("wren-core federated plan:\n {federated}");
+    check_executable(&federated)?;
+    // the plan has been analyzed, so only the optimizer rules of the local runtime are applied
+    let state = local_ctx.state();
+    let optimized = state.optimizer().optimize(federated, &state, |_, _| {})?;
+    let physical = DefaultPhysicalPlanner::default()
+        .create_physical_plan(&optimized, &state)
+        .await?;
+    datafusion::physical_plan::execute_stream(physical, local_ctx.task_ctx())
+}
+
+/// Plan and optimize the SQL in the SessionContext registered with the MDL
+async fn plan_sql_with_ctx(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    remote_functions: &[RemoteFunction],
+    properties: SessionPropertiesRef,
+    sql: &str,
+) -> Result<(SessionContext, LogicalPlan)> {
     remote_functions.iter().try_for_each(|remote_function| {
         debug!("Registering remote function: {:?}", remote_function);
         register_remote_function(ctx, remote_function)?;
@@ -382,26 +676,36 @@ pub async fn transform_sql_with_ctx(
     })?;
     let ctx =
         create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), properties, false).await?;
-    let plan = ctx.state().create_logical_plan(sql).await?;
+    let plan = create_logical_plan(&ctx, &analyzed_mdl.wren_mdl(), sql).await?;
     debug!("wren-core original plan:\n {plan}");
     let analyzed = ctx.state().optimize(&plan)?;
     debug!("wren-core final planned:\n {analyzed}");
+    Ok((ctx, analyzed))
+}
 
-    let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
-    let wren_dialect = WrenDialect::new(&data_source);
-    let unparser = Unparser::new(&wren_dialect).with_pretty(true);
-    // show the planned sql
-    match unparser.plan_to_sql(&analyzed) {
-        Ok(sql) => {
-            // TODO: workaround to remove unnecessary catalog and schema of mdl
-            let replaced = sql
-                .to_string()
-                .replace(analyzed_mdl.wren_mdl().catalog_schema_prefix(), "");
-            info!("wren-core planned SQL: {}", replaced);
-            Ok(replaced)
+/// Parse the SQL and expand the relationship paths before planning it.
+async fn create_logical_plan(
+    ctx: &SessionContext,
+    wren_mdl: &WrenMDL,
+    sql: &str,
+) -> Result<LogicalPlan> {
+    let state = ctx.state();
+    let dialect = state.config_options().sql_parser.dialect.clone();
+    let mut statement = state.sql_to_statement(sql, &dialect)?;
+    if let DFStatement::Statement(statement) = &mut statement {
+        let config = WrenConfig::from_session(ctx);
+        materialization::rewrite_materialized_relations(
+            wren_mdl,
+            statement.as_mut(),
+            config.current_time()?,
+        )?;
+        metric::expand_metrics(wren_mdl, statement.as_mut())?;
+        relationship_path::expand_relationship_paths(wren_mdl, statement.as_mut())?;
+        if config.infer_join_path {
+            join_inference::infer_join_conditions(wren_mdl, statement.as_mut())?;
         }
-        Err(e) => Err(e),
     }
+    state.statement_to_plan(statement).await
 }
 
 fn register_remote_function(
@@ -457,26 +761,49 @@ mod test {
     use std::fs;
     use std::path::PathBuf;
     use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
 
     use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
-    use crate::mdl::context::create_ctx_with_mdl;
+    use crate::mdl::connector::{Connector, TableProviderConnector};
+    use crate::mdl::context::{create_ctx_with_mdl, WrenConfig};
+    use crate::mdl::federation::SqlExecutor;
     use crate::mdl::function::RemoteFunction;
     use crate::mdl::manifest::DataSource::MySQL;
     use crate::mdl::manifest::Manifest;
-    use crate::mdl::{self, transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::mdl::materialization::Materialization;
+    use crate::mdl::semantic_query::SemanticQuery;
+    use crate::mdl::{
+        self, semantic_query_to_plan, transform_semantic_query_with_ctx,
+        transform_sql_with_ctx, AnalyzedWrenMDL,
+    };
+    use async_trait::async_trait;
     use datafusion::arrow::array::{
         ArrayRef, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
     };
+    use datafusion::arrow::datatypes::{DataType, SchemaRef};
     use datafusion::arrow::util::pretty::pretty_format_batches_with_options;
+    use datafusion::catalog::Session;
     use datafusion::common::format::DEFAULT_FORMAT_OPTIONS;
     use datafusion::common::not_impl_err;
+    use datafusion::common::plan_err;
     use datafusion::common::Result;
     use datafusion::config::ConfigOptions;
+    use datafusion::datasource::{MemTable, TableProvider};
+    use datafusion::execution::object_store::ObjectStoreUrl;
+    use datafusion::execution::SendableRecordBatchStream;
+    use datafusion::logical_expr::{Expr, TableProviderFilterPushDown};
+    use datafusion::physical_plan::common::collect;
+    use datafusion::physical_plan::ExecutionPlan;
     use datafusion::prelude::{SessionConfig, SessionContext};
     use datafusion::sql::unparser::plan_to_sql;
+    use datafusion::sql::TableReference;
     use insta::assert_snapshot;
+    use object_store::memory::InMemory;
+    use object_store::path::Path;
+    use object_store::{ObjectStore, PutPayload};
     use wren_core_base::mdl::{
-        ColumnLevelOperator, DataSource, JoinType, RelationshipBuilder, SessionProperty,
+        ColumnLevelOperator, DataSource, JoinType, MetricBuilder, NonAdditiveWindow,
+        RelationshipBuilder, SessionProperty, TimeGrainBuilder, TimeUnit,
     };
 
     #[test]
@@ -609,7 +936,7 @@ mod test {
         )
         .await?;
         assert_snapshot!(result,
-          @r#"SELECT "profile".totalcost FROM (SELECT __relation__1.p_sex, __relation__1.totalcost FROM (SELECT totalcost.p_custkey, "profile".p_sex, totalcost.totalcost FROM (SELECT __relation__2.p_custkey AS p_custkey, sum(CAST(__relation__2.o_totalprice AS BIGINT)) AS totalcost FROM (SELECT __relation__1.c_custkey, orders.o_custkey, orders.o_totalprice, __relation__1.p_custkey FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_orderkey AS o_orderkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders) AS orders RIGHT JOIN (SELECT customer.c_custkey, "profile".p_custkey FROM (SELECT customer.c_custkey FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source) AS customer) AS customer) AS customer RIGHT JOIN (SELECT __source.p_custkey AS p_custkey FROM "profile" AS __source) AS "profile" ON customer.c_custkey = "profile".p_custkey) AS __relation__1 ON orders.o_custkey = __relation__1.c_custkey) AS __relation__2 GROUP BY __relation__2.p_custkey) AS totalcost RIGHT JOIN (SELECT __source.p_custkey AS p_custkey, __source.p_sex AS p_sex FROM "profile" AS __source) AS "profile" ON totalcost.p_custkey = "profile".p_custkey) AS __relation__1) AS "profile" WHERE "profile".p_sex = 'M'"#);
+          @r#"SELECT "profile".totalcost FROM (SELECT __relation__1.p_sex, __relation__1.totalcost FROM (SELECT totalcost.p_custkey, "profile".p_sex, totalcost.totalcost FROM (SELECT __relation__2.p_custkey AS p_custkey, sum(CAST(__relation__2.o_totalprice AS BIGINT)) AS totalcost FROM (SELECT __relation__1.c_custkey, orders.o_custkey, orders.o_totalprice, __relation__1.p_custkey, __relation__1.p_sex FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT orders.o_custkey, orders.o_totalprice FROM (SELECT __source.o_custkey AS o_custkey, __source.o_orderkey AS o_orderkey, __source.o_totalprice AS o_totalprice FROM orders AS __source) AS orders) AS orders) AS orders RIGHT JOIN (SELECT customer.c_custkey, "profile".p_custkey, "profile".p_sex FROM (SELECT customer.c_custkey FROM (SELECT customer.c_custkey FROM (SELECT __source.c_custkey AS c_custkey FROM customer AS __source) AS customer) AS customer) AS customer RIGHT JOIN (SELECT __source.p_custkey AS p_custkey, __source.p_sex AS p_sex FROM "profile" AS __source) AS "profile" ON customer.c_custkey = "profile".p_custkey WHERE "profile".p_sex = 'M') AS __relation__1 ON orders.o_custkey = __relation__1.c_custkey) AS __relation__2 GROUP BY __relation__2.p_custkey) AS totalcost RIGHT JOIN (SELECT __source.p_custkey AS p_custkey, __source.p_sex AS p_sex FROM "profile" AS __source) AS "profile" ON totalcost.p_custkey = "profile".p_custkey) AS __relation__1) AS "profile" WHERE "profile".p_sex = 'M'"#);
         Ok(())
     }
 
@@ -1009,6 +1336,53 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_reused_model_as_cte() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        let sql = "SELECT o1.o_orderkey FROM orders o1 JOIN orders o2 ON o1.o_orderkey = o2.o_orderkey";
+
+        let actual = transform_sql_with_ctx(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.starts_with("WITH"));
+        assert_eq!(actual.matches("FROM orders AS __source").count(), 2);
+
+        let mut wren_config = WrenConfig::default();
+        wren_config.enable_model_cte = true;
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.starts_with("WITH __cte_1 AS ("));
+        assert_eq!(actual.matches("FROM orders AS __source").count(), 1);
+        assert_sql_valid_executable(&actual).await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_mysql_style_interval() -> Result<()> {
         let ctx = SessionContext::new();
@@ -1274,109 +1648,470 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_coercion_timestamptz() -> Result<()> {
-        let ctx = SessionContext::new();
-        ctx.register_batch("timestamp_table", timestamp_table())?;
-        for timezone_type in [
-            "timestamptz",
-            "timestamp_with_timezone",
-            "timestamp_with_time_zone",
-        ] {
-            let manifest = ManifestBuilder::new()
-                .catalog("wren")
-                .schema("test")
-                .model(
-                    ModelBuilder::new("timestamp_table")
-                        .table_reference("datafusion.public.timestamp_table")
-                        .column(ColumnBuilder::new("timestamp_col", "timestamp").build())
-                        .column(
-                            ColumnBuilder::new("timestamptz_col", timezone_type).build(),
-                        )
-                        .build(),
-                )
-                .build();
-            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
-                manifest,
-                Arc::new(HashMap::default()),
-            )?);
-            let sql = r#"select timestamp_col = timestamptz_col from wren.test.timestamp_table"#;
-            let actual = transform_sql_with_ctx(
-                &SessionContext::new(),
-                Arc::clone(&analyzed_mdl),
-                &[],
-                Arc::new(HashMap::new()),
-                sql,
-            )
-            .await?;
-            assert_eq!(actual,
-                       "SELECT CAST(timestamp_table.timestamp_col AS TIMESTAMP WITH TIME ZONE) = timestamp_table.timestamptz_col \
-                       FROM (SELECT timestamp_table.timestamp_col, timestamp_table.timestamptz_col FROM \
-                       (SELECT __source.timestamp_col AS timestamp_col, __source.timestamptz_col AS timestamptz_col \
-                       FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
-
-            let sql = r#"select timestamptz_col > cast('2011-01-01 18:00:00' as TIMESTAMP WITH TIME ZONE) from wren.test.timestamp_table"#;
-            let actual = transform_sql_with_ctx(
-                &SessionContext::new(),
-                Arc::clone(&analyzed_mdl),
-                &[],
-                Arc::new(HashMap::new()),
-                sql,
-            )
-            .await?;
-            // assert the simplified literal will be casted to the timestamp tz
-            assert_eq!(actual,
-              "SELECT timestamp_table.timestamptz_col > CAST(CAST('2011-01-01 18:00:00' AS TIMESTAMP) AS TIMESTAMP WITH TIME ZONE) FROM (SELECT timestamp_table.timestamptz_col FROM (SELECT __source.timestamptz_col AS timestamptz_col FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table"
-);
-
-            let sql = r#"select timestamptz_col > '2011-01-01 18:00:00' from wren.test.timestamp_table"#;
-            let actual = transform_sql_with_ctx(
-                &SessionContext::new(),
-                Arc::clone(&analyzed_mdl),
-                &[],
-                Arc::new(HashMap::new()),
-                sql,
-            )
-            .await?;
-            // assert the string literal will be casted to the timestamp tz
-            assert_eq!(actual,
-                       "SELECT timestamp_table.timestamptz_col > CAST('2011-01-01 18:00:00' AS TIMESTAMP WITH TIME ZONE) \
-                       FROM (SELECT timestamp_table.timestamptz_col FROM (SELECT __source.timestamptz_col AS timestamptz_col \
-                       FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
-
-            let sql = r#"select timestamp_col > cast('2011-01-01 18:00:00' as TIMESTAMP WITH TIME ZONE) from wren.test.timestamp_table"#;
-            let actual = transform_sql_with_ctx(
-                &SessionContext::new(),
-                Arc::clone(&analyzed_mdl),
-                &[],
-                Arc::new(HashMap::new()),
-                sql,
-            )
-            .await?;
-            // assert the simplified literal won't be casted to the timestamp tz
-            assert_eq!(actual,
-                "SELECT timestamp_table.timestamp_col > CAST('2011-01-01 18:00:00' AS TIMESTAMP) \
-                FROM (SELECT timestamp_table.timestamp_col FROM (SELECT __source.timestamp_col AS timestamp_col \
-                FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
-        }
-        Ok(())
-    }
-
-    #[tokio::test]
-    async fn test_list() -> Result<()> {
+    async fn test_execute_sql() -> Result<()> {
         let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.customer".to_string(), provider);
         let manifest = ManifestBuilder::new()
             .catalog("wren")
             .schema("test")
             .model(
-                ModelBuilder::new("list_table")
-                    .table_reference("list_table")
-                    .column(ColumnBuilder::new("list_col", "array<int>").build())
-                    .build(),
-            )
-            .build();
-        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
-            manifest,
-            Arc::new(HashMap::default()),
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .column(
+                        ColumnBuilder::new("c_name_upper", "varchar")
+                            .calculated(true)
+                            .expression("upper(c_name)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+
+        let sql = "SELECT c_custkey, c_name_upper FROM customer ORDER BY c_custkey";
+        let stream = mdl::execute_sql(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            sql,
+            Some(2),
+        )
+        .await?;
+        let batches = collect(stream).await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-----------+--------------+
+        | c_custkey | c_name_upper |
+        +-----------+--------------+
+        | 1         | GURA         |
+        | 2         | AZKI         |
+        +-----------+--------------+
+        ");
+
+        let stream = mdl::execute_sql(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            sql,
+            None,
+        )
+        .await?;
+        assert_eq!(stream.schema().fields().len(), 2);
+        let rows: usize = collect(stream).await?.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 3);
+
+        // the source table of the model isn't registered
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::new()),
+        )?);
+        match mdl::execute_sql(
+            &ctx,
+            analyzed_mdl,
+            Arc::new(HashMap::new()),
+            "SELECT o_orderkey FROM orders",
+            None,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("the model without the source table can't be executed"),
+            Err(e) => assert!(e.to_string().contains("can't be executed locally")),
+        }
+        Ok(())
+    }
+
+    /// Record the scans pushed down to the connector
+    #[derive(Debug)]
+    struct RecordingConnector {
+        inner: TableProviderConnector,
+        scans: parking_lot::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Connector for RecordingConnector {
+        async fn scan(
+            &self,
+            state: &dyn Session,
+            table_reference: &TableReference,
+            schema: &SchemaRef,
+            projection: Option<&Vec<usize>>,
+            filters: &[Expr],
+            limit: Option<usize>,
+        ) -> Result<Arc<dyn ExecutionPlan>> {
+            let columns = projection.map(|projection| {
+                projection
+                    .iter()
+                    .map(|i| schema.field(*i).name().as_str())
+                    .collect::<Vec<_>>()
+            });
+            self.scans.lock().push(format!(
+                "{table_reference}: projection={columns:?}, filters={}, limit={limit:?}",
+                filters.len()
+            ));
+            self.inner
+                .scan(state, table_reference, schema, projection, filters, limit)
+                .await
+        }
+
+        fn supports_filters_pushdown(
+            &self,
+            _table_reference: &TableReference,
+            filters: &[&Expr],
+        ) -> Result<Vec<TableProviderFilterPushDown>> {
+            Ok(vec![TableProviderFilterPushDown::Inexact; filters.len()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connector() -> Result<()> {
+        let customer: Arc<dyn TableProvider> = Arc::new(MemTable::try_new(
+            customer().schema(),
+            vec![vec![customer()]],
+        )?);
+        let orders: Arc<dyn TableProvider> =
+            Arc::new(MemTable::try_new(orders().schema(), vec![vec![orders()]])?);
+        // the models are stored in two different sources
+        let connector = Arc::new(RecordingConnector {
+            inner: TableProviderConnector::new(move |table_reference| {
+                match table_reference.catalog() {
+                    Some("pg") => Ok(Arc::clone(&customer)),
+                    Some("mysql") => Ok(Arc::clone(&orders)),
+                    _ => plan_err!("Unknown source of {table_reference}"),
+                }
+            }),
+            scans: parking_lot::Mutex::new(vec![]),
+        });
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("pg.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("mysql.shop.orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_connector(
+            manifest,
+            Arc::new(HashMap::new()),
+            Arc::clone(&connector) as Arc<dyn Connector>,
+        )?);
+
+        let sql = "SELECT c.c_name, o.o_totalprice FROM orders o JOIN customer c \
+            ON o.o_custkey = c.c_custkey WHERE o.o_totalprice > 100 ORDER BY 1";
+        let stream = mdl::execute_sql(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            sql,
+            None,
+        )
+        .await?;
+        assert_snapshot!(batches_to_string(&collect(stream).await?), @r"
+        +--------+--------------+
+        | c_name | o_totalprice |
+        +--------+--------------+
+        | Azki   | 200          |
+        | Ina    | 300          |
+        +--------+--------------+
+        ");
+        let scans = std::mem::take(&mut *connector.scans.lock());
+        let orders_scan = scans.iter().find(|s| s.starts_with("mysql")).unwrap();
+        assert!(!orders_scan.contains("o_orderkey"));
+        assert!(orders_scan.contains("filters=1"));
+        let customer_scan = scans.iter().find(|s| s.starts_with("pg")).unwrap();
+        assert!(customer_scan.contains(r#"["c_custkey", "c_name"]"#));
+
+        let stream = mdl::execute_sql(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            "SELECT c_name FROM customer LIMIT 1",
+            None,
+        )
+        .await?;
+        assert_eq!(collect(stream).await?[0].num_rows(), 1);
+        assert_snapshot!(connector.scans.lock().join("\n"), @r#"pg.public.customer: projection=Some(["c_name"]), filters=0, limit=Some(1)"#);
+        Ok(())
+    }
+
+    /// Execute the SQL planned for the data sources in a SessionContext with their tables
+    #[derive(Debug)]
+    struct RecordingExecutor {
+        ctx: SessionContext,
+        queries: parking_lot::Mutex<Vec<(DataSource, String)>>,
+    }
+
+    impl RecordingExecutor {
+        async fn try_new() -> Result<Self> {
+            let ctx = SessionContext::new();
+            for sql in [
+                "CREATE DATABASE pg",
+                "CREATE SCHEMA pg.public",
+                "CREATE DATABASE mysql",
+                "CREATE SCHEMA mysql.shop",
+            ] {
+                ctx.sql(sql).await?.collect().await?;
+            }
+            ctx.register_batch("pg.public.customer", customer())?;
+            ctx.register_batch("pg.public.orders", orders())?;
+            ctx.register_batch("mysql.shop.orders", orders())?;
+            Ok(Self {
+                ctx,
+                queries: parking_lot::Mutex::new(vec![]),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl SqlExecutor for RecordingExecutor {
+        async fn execute(
+            &self,
+            data_source: DataSource,
+            sql: &str,
+        ) -> Result<SendableRecordBatchStream> {
+            self.queries.lock().push((data_source, sql.to_string()));
+            self.ctx.sql(sql).await?.execute_stream().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_federated_query() -> Result<()> {
+        let manifest = |orders_source: &str, orders_data_source: DataSource| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .data_source(DataSource::Postgres)
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("pg.public.customer")
+                        .column(ColumnBuilder::new("c_custkey", "int").build())
+                        .column(ColumnBuilder::new("c_name", "varchar").build())
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference(orders_source)
+                        .data_source(orders_data_source)
+                        .column(ColumnBuilder::new("o_orderkey", "int").build())
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_totalprice", "int").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT c.c_name, o.o_totalprice FROM orders o JOIN customer c \
+            ON o.o_custkey = c.c_custkey WHERE o.o_totalprice > 100 ORDER BY 1";
+
+        // the models are stored in two different data sources
+        let executor = Arc::new(RecordingExecutor::try_new().await?);
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest("mysql.shop.orders", DataSource::MySQL),
+            Arc::new(HashMap::new()),
+        )?);
+        let stream = mdl::execute_federated_sql(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+            None,
+            Arc::clone(&executor) as Arc<dyn SqlExecutor>,
+        )
+        .await?;
+        assert_snapshot!(batches_to_string(&collect(stream).await?), @r"
+        +--------+--------------+
+        | c_name | o_totalprice |
+        +--------+--------------+
+        | Azki   | 200          |
+        | Ina    | 300          |
+        +--------+--------------+
+        ");
+        let queries = std::mem::take(&mut *executor.queries.lock());
+        assert_eq!(queries.len(), 2);
+        let (_, orders_sql) = queries
+            .iter()
+            .find(|(data_source, _)| *data_source == DataSource::MySQL)
+            .unwrap();
+        assert!(orders_sql.contains("orders"));
+        assert!(!orders_sql.contains("customer"));
+        let (_, customer_sql) = queries
+            .iter()
+            .find(|(data_source, _)| *data_source == DataSource::Postgres)
+            .unwrap();
+        assert!(customer_sql.contains("customer"));
+        assert!(!customer_sql.contains("orders"));
+
+        // the join is pushed down if the models are stored in the same data source
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest("pg.public.orders", DataSource::Postgres),
+            Arc::new(HashMap::new()),
+        )?);
+        let stream = mdl::execute_federated_sql(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+            Some(1),
+            Arc::clone(&executor) as Arc<dyn SqlExecutor>,
+        )
+        .await?;
+        // the SQL is executed when the stream is polled
+        assert!(executor.queries.lock().is_empty());
+        assert_snapshot!(batches_to_string(&collect(stream).await?), @r"
+        +--------+--------------+
+        | c_name | o_totalprice |
+        +--------+--------------+
+        | Azki   | 200          |
+        +--------+--------------+
+        ");
+        let queries = executor.queries.lock();
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].0, DataSource::Postgres);
+        assert!(queries[0].1.contains("JOIN"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_coercion_timestamptz() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("timestamp_table", timestamp_table())?;
+        for timezone_type in [
+            "timestamptz",
+            "timestamp_with_timezone",
+            "timestamp_with_time_zone",
+        ] {
+            let manifest = ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("timestamp_table")
+                        .table_reference("datafusion.public.timestamp_table")
+                        .column(ColumnBuilder::new("timestamp_col", "timestamp").build())
+                        .column(
+                            ColumnBuilder::new("timestamptz_col", timezone_type).build(),
+                        )
+                        .build(),
+                )
+                .build();
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest,
+                Arc::new(HashMap::default()),
+            )?);
+            let sql = r#"select timestamp_col = timestamptz_col from wren.test.timestamp_table"#;
+            let actual = transform_sql_with_ctx(
+                &SessionContext::new(),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            assert_eq!(actual,
+                       "SELECT CAST(timestamp_table.timestamp_col AS TIMESTAMP WITH TIME ZONE) = timestamp_table.timestamptz_col \
+                       FROM (SELECT timestamp_table.timestamp_col, timestamp_table.timestamptz_col FROM \
+                       (SELECT __source.timestamp_col AS timestamp_col, __source.timestamptz_col AS timestamptz_col \
+                       FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
+
+            let sql = r#"select timestamptz_col > cast('2011-01-01 18:00:00' as TIMESTAMP WITH TIME ZONE) from wren.test.timestamp_table"#;
+            let actual = transform_sql_with_ctx(
+                &SessionContext::new(),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            // assert the simplified literal will be casted to the timestamp tz
+            assert_eq!(actual,
+              "SELECT timestamp_table.timestamptz_col > CAST(CAST('2011-01-01 18:00:00' AS TIMESTAMP) AS TIMESTAMP WITH TIME ZONE) FROM (SELECT timestamp_table.timestamptz_col FROM (SELECT __source.timestamptz_col AS timestamptz_col FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table"
+);
+
+            let sql = r#"select timestamptz_col > '2011-01-01 18:00:00' from wren.test.timestamp_table"#;
+            let actual = transform_sql_with_ctx(
+                &SessionContext::new(),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            // assert the string literal will be casted to the timestamp tz
+            assert_eq!(actual,
+                       "SELECT timestamp_table.timestamptz_col > CAST('2011-01-01 18:00:00' AS TIMESTAMP WITH TIME ZONE) \
+                       FROM (SELECT timestamp_table.timestamptz_col FROM (SELECT __source.timestamptz_col AS timestamptz_col \
+                       FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
+
+            let sql = r#"select timestamp_col > cast('2011-01-01 18:00:00' as TIMESTAMP WITH TIME ZONE) from wren.test.timestamp_table"#;
+            let actual = transform_sql_with_ctx(
+                &SessionContext::new(),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            // assert the simplified literal won't be casted to the timestamp tz
+            assert_eq!(actual,
+                "SELECT timestamp_table.timestamp_col > CAST('2011-01-01 18:00:00' AS TIMESTAMP) \
+                FROM (SELECT timestamp_table.timestamp_col FROM (SELECT __source.timestamp_col AS timestamp_col \
+                FROM datafusion.\"public\".timestamp_table AS __source) AS timestamp_table) AS timestamp_table");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list() -> Result<()> {
+        let ctx = SessionContext::new();
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("list_table")
+                    .table_reference("list_table")
+                    .column(ColumnBuilder::new("list_col", "array<int>").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
         )?);
         let sql = "select list_col[1] from wren.test.list_table";
         let actual = transform_sql_with_ctx(
@@ -2163,6 +2898,1726 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_eliminate_to_one_join() -> Result<()> {
+        let mut wren_config = WrenConfig::default();
+        wren_config.eliminate_to_one_join = true;
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("customer_key", "int")
+                            .expression("customer.c_custkey")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("customer_name", "string")
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        // the primary key of customer is satisfied by the foreign key of orders
+        let sql = "SELECT o_orderkey, customer_key FROM orders";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.contains("JOIN"), "unexpected join: {actual}");
+        assert!(actual.contains("AS customer_key"));
+        assert_sql_valid_executable(&actual).await?;
+
+        // the non-key column of customer requires the join
+        let sql = "SELECT o_orderkey, customer_key, customer_name FROM orders";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("RIGHT JOIN"), "expected join: {actual}");
+        assert_sql_valid_executable(&actual).await?;
+
+        // the join is kept if the elimination isn't enabled
+        let sql = "SELECT o_orderkey, customer_key FROM orders";
+        let actual = transform_sql_with_ctx(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("RIGHT JOIN"), "expected join: {actual}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pushdown_filter_into_calculation() -> Result<()> {
+        let ctx = SessionContext::new();
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("totalprice", "int")
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        let sql = "SELECT c_name, totalprice FROM customer WHERE c_name = 'Gura'";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        // the filter is applied to both the source of the calculation and the outer query
+        assert_eq!(actual.matches("customer.c_name = 'Gura'").count(), 2);
+        assert_sql_valid_executable(&actual).await?;
+
+        // the filter can't be identified if the model is used twice in the same scope
+        let sql = "SELECT c_name, totalprice FROM customer WHERE c_name = 'Gura' \
+            UNION ALL SELECT c_name, totalprice FROM customer";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_eq!(actual.matches("customer.c_name = 'Gura'").count(), 1);
+        assert_sql_valid_executable(&actual).await?;
+
+        // the filter outside the window isn't pushed down, otherwise the other customers lose
+        // their total price and the row number changes
+        let sql = "SELECT c_name, rn FROM (SELECT c_name, \
+            row_number() OVER (ORDER BY totalprice) AS rn FROM customer) AS customer \
+            WHERE c_name = 'Ina'";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_eq!(actual.matches("customer.c_name = 'Ina'").count(), 1);
+        let exec_ctx = SessionContext::new();
+        exec_ctx.register_batch("orders", orders())?;
+        exec_ctx.register_batch("customer", customer())?;
+        let batches = exec_ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +--------+----+
+        | c_name | rn |
+        +--------+----+
+        | Ina    | 3  |
+        +--------+----+
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relationship_path() -> Result<()> {
+        let ctx = SessionContext::new();
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        let sql = "SELECT o.o_orderkey, o.customer.c_name FROM orders o \
+            WHERE o.customer.c_name = 'Gura' ORDER BY o.customer.c_name";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        // the same path is joined only once
+        assert_eq!(actual.matches("LEFT JOIN").count(), 1);
+        assert!(actual.contains("o__customer.c_name = 'Gura'"));
+        assert_sql_valid_executable(&actual).await?;
+
+        // the path used in a join condition is joined before the join
+        let sql = "SELECT o.o_orderkey, c.c_name FROM orders o \
+            JOIN customer c ON c.c_name = o.customer.c_name";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert_eq!(actual.matches("LEFT JOIN").count(), 1);
+        assert!(actual.contains("c.c_name = o__customer.c_name"));
+        assert_sql_valid_executable(&actual).await?;
+
+        let sql = "SELECT c.orders.o_orderkey FROM customer c";
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("to-many relationship path should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Relationship path c.orders is a to-many relationship. Use a calculated field with aggregation instead"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_infer_join_path() -> Result<()> {
+        let manifest_builder = || {
+            ManifestBuilder::new()
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", "int").build())
+                        .column(ColumnBuilder::new("c_name", "string").build())
+                        .column(ColumnBuilder::new("c_nationkey", "int").build())
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("nation")
+                        .table_reference("nation")
+                        .column(ColumnBuilder::new("n_nationkey", "int").build())
+                        .column(ColumnBuilder::new("n_name", "string").build())
+                        .primary_key("n_nationkey")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "int").build())
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_clerkkey", "int").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_orders")
+                        .model("customer")
+                        .model("orders")
+                        .join_type(JoinType::OneToMany)
+                        .condition("customer.c_custkey = orders.o_custkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_nation")
+                        .model("customer")
+                        .model("nation")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("customer.c_nationkey = nation.n_nationkey")
+                        .build(),
+                )
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest_builder().build(),
+            Arc::new(HashMap::default()),
+        )?);
+
+        let sql = "SELECT o.o_orderkey, c.c_name FROM orders o, customer c";
+        let mut wren_config = WrenConfig::default();
+        wren_config.infer_join_path = true;
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.contains("CROSS JOIN"));
+        assert!(actual.contains("c.c_custkey = o.o_custkey"));
+        assert_sql_valid_executable(&actual).await?;
+
+        let sql = "SELECT o.o_orderkey, c.c_name FROM orders o JOIN customer c";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("c.c_custkey = o.o_custkey"));
+        assert_sql_valid_executable(&actual).await?;
+
+        // the model in the middle of the path is joined as well
+        let multi_hop_sql = "SELECT o.o_orderkey, n.n_name FROM orders o JOIN nation n";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            multi_hop_sql,
+        )
+        .await?;
+        assert!(actual.contains("n__customer.c_custkey = o.o_custkey"));
+        assert!(actual.contains("n__customer.c_nationkey = n.n_nationkey"));
+        assert_sql_valid_executable(&actual).await?;
+
+        let manifest = manifest_builder()
+            .relationship(
+                RelationshipBuilder::new("clerk_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_clerkkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("ambiguous join path should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Ambiguous join path for c: o (one_to_many ON customer.c_custkey = orders.o_clerkkey), o (one_to_many ON customer.c_custkey = orders.o_custkey). Please specify the join condition"
+            ),
+        }
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            multi_hop_sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("ambiguous multi-hop join path should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Ambiguous join path for n: o (many_to_one ON customer.c_nationkey = nation.n_nationkey, one_to_many ON customer.c_custkey = orders.o_clerkkey), o (many_to_one ON customer.c_nationkey = nation.n_nationkey, one_to_many ON customer.c_custkey = orders.o_custkey). Please specify the join condition"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metric_time_grain() -> Result<()> {
+        let ctx = SessionContext::new();
+        let manifest_builder = |data_source: DataSource| {
+            ManifestBuilder::new()
+                .data_source(data_source)
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "int").build())
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_totalprice", "int").build())
+                        .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .metric(
+                    MetricBuilder::new("revenue")
+                        .base_object("orders")
+                        .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                        .measure(
+                            ColumnBuilder::new("total", "int")
+                                .expression("sum(o_totalprice)")
+                                .build(),
+                        )
+                        .time_grain(
+                            TimeGrainBuilder::new("order_date")
+                                .ref_column("o_orderdate")
+                                .date_part(TimeUnit::Month)
+                                .date_part(TimeUnit::Year)
+                                .date_part(TimeUnit::Quarter)
+                                .date_part(TimeUnit::Week)
+                                .date_part(TimeUnit::Second)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest_builder(DataSource::Datafusion),
+            Arc::new(HashMap::default()),
+        )?);
+
+        let sql = "SELECT r.o_custkey, r.total FROM revenue r";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("sum("));
+        assert!(actual.contains("GROUP BY"));
+        assert_sql_valid_executable(&actual).await?;
+
+        for sql in [
+            "SELECT r.order_date__month, r.total FROM revenue r",
+            "SELECT time_grain(r.order_date, 'month'), r.total FROM revenue r",
+        ] {
+            let actual = transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            assert!(actual.contains("date_trunc('month',"));
+            assert!(!actual.contains("o_custkey"));
+        }
+
+        let sql = "SELECT r.order_date__day, r.total FROM revenue r";
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("unsupported time unit should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Time grain order_date of metric revenue doesn't support the time unit day"
+            ),
+        }
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest_builder(DataSource::BigQuery),
+            Arc::new(HashMap::default()),
+        )?);
+        let sql = "SELECT r.order_date__year, r.total FROM revenue r";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("TIMESTAMP_TRUNC("));
+        assert!(actual.contains(", YEAR)"));
+
+        // the units without a date_trunc equivalent are rewritten
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest_builder(DataSource::MySQL),
+            Arc::new(HashMap::default()),
+        )?);
+        for (sql, expected) in [
+            (
+                "SELECT r.order_date__quarter, r.total FROM revenue r",
+                "MAKEDATE(",
+            ),
+            (
+                "SELECT r.order_date__week, r.total FROM revenue r",
+                "WEEKDAY(",
+            ),
+        ] {
+            let actual = transform_sql_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                sql,
+            )
+            .await?;
+            assert!(actual.contains(expected));
+            assert!(!actual.contains("date_trunc"));
+        }
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest_builder(DataSource::Oracle),
+            Arc::new(HashMap::default()),
+        )?);
+        let sql = "SELECT r.order_date__second, r.total FROM revenue r";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("CAST(CAST("));
+        assert!(!actual.contains("date_trunc"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_derived_metric() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orders_model = ModelBuilder::new("orders")
+            .table_reference("orders")
+            .column(ColumnBuilder::new("o_orderkey", "int").build())
+            .column(ColumnBuilder::new("o_custkey", "int").build())
+            .column(ColumnBuilder::new("o_totalprice", "int").build())
+            .primary_key("o_orderkey")
+            .build();
+        let base_metric = |name: &str, measure: &str, expression: &str| {
+            MetricBuilder::new(name)
+                .base_object("orders")
+                .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                .measure(
+                    ColumnBuilder::new(measure, "int")
+                        .expression(expression)
+                        .build(),
+                )
+                .build()
+        };
+        let manifest = ManifestBuilder::new()
+            .model(Arc::clone(&orders_model))
+            .metric(base_metric("revenue", "total", "sum(o_totalprice)"))
+            .metric(base_metric("order_count", "cnt", "count(*)"))
+            .metric(
+                MetricBuilder::new("avg_order_value")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("aov", "double")
+                            .expression("revenue.total / order_count.cnt")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        assert_eq!(
+            analyzed_mdl.lineage().metric_dependencies["avg_order_value"].len(),
+            2
+        );
+
+        let sql = "SELECT a.o_custkey, a.aov FROM avg_order_value a";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("FULL JOIN"));
+        assert_sql_valid_executable(&actual).await?;
+
+        let sql = "SELECT a.aov FROM avg_order_value a";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("CROSS JOIN"));
+        assert_sql_valid_executable(&actual).await?;
+
+        let manifest = ManifestBuilder::new()
+            .model(Arc::clone(&orders_model))
+            .metric(
+                MetricBuilder::new("recursive")
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("recursive.total + 1")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        match AnalyzedWrenMDL::analyze(manifest, Arc::new(HashMap::default())) {
+            Ok(_) => unreachable!("cyclic metric should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: cyclic dependency detected in metric: recursive"
+            ),
+        }
+
+        // a metric based on another metric isn't expanded
+        let sql = "SELECT r.o_custkey, r.total FROM revenue_of_revenue r";
+        let manifest = ManifestBuilder::new()
+            .model(orders_model)
+            .metric(base_metric("revenue", "total", "sum(o_totalprice)"))
+            .metric(
+                MetricBuilder::new("revenue_of_revenue")
+                    .base_object("revenue")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("sum(total)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("the metric based on a metric should be rejected"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Metric revenue_of_revenue can't be aggregated from metric revenue. Define it as a derived metric of the measures instead"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cumulative_and_period_offset_metric() -> Result<()> {
+        let ctx = SessionContext::new();
+        let metric = |name: &str| {
+            MetricBuilder::new(name)
+                .base_object("orders")
+                .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                .measure(
+                    ColumnBuilder::new("total", "int")
+                        .expression("sum(o_totalprice)")
+                        .build(),
+                )
+                .time_grain(
+                    TimeGrainBuilder::new("order_date")
+                        .ref_column("o_orderdate")
+                        .date_part(TimeUnit::Month)
+                        .date_part(TimeUnit::Day)
+                        .build(),
+                )
+        };
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .metric(
+                metric("revenue_mtd")
+                    .cumulative("order_date", Some(TimeUnit::Month))
+                    .build(),
+            )
+            .metric(
+                metric("revenue_last_month")
+                    .period_offset("order_date", 1)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        let sql = "SELECT m.order_date__day, m.total FROM revenue_mtd m";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW"));
+        assert!(actual.contains("PARTITION BY date_trunc('month',"));
+
+        let sql = "SELECT m.o_custkey, m.order_date__month, m.total, m.total_previous \
+            FROM revenue_last_month m";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("LEFT JOIN"));
+        assert!(actual.contains("INTERVAL 1 MONTH"));
+        // both the current and the previous measures are projected
+        assert!(actual.contains("__current.total"));
+        assert!(actual.contains("__previous.total AS total_previous"));
+
+        let sql = "SELECT m.total FROM revenue_mtd m";
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => {
+                unreachable!("cumulative metric should be queried by the time grain")
+            }
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Metric revenue_mtd should be queried by a time unit of time grain order_date. e.g. order_date__month"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_metric_across_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 1, 1, 2]));
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3, 4]));
+        let status: ArrayRef =
+            Arc::new(StringArray::from_iter_values(["F", "F", "O", "F"]));
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_custkey", custkey),
+                ("o_orderstatus", status),
+            ])?,
+        )?;
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "string").build())
+                    .column(
+                        ColumnBuilder::new("orders", "orders")
+                            .relationship("customer_orders")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_orderstatus", "string").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("customer_count")
+                    .base_object("customer")
+                    .dimension(
+                        ColumnBuilder::new("order_status", "string")
+                            .expression("orders.o_orderstatus")
+                            .build(),
+                    )
+                    .measure(
+                        ColumnBuilder::new("cnt", "int")
+                            .expression("count(c_custkey)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("customer_orders")
+                    .base_object("customer")
+                    .dimension(ColumnBuilder::new("c_name", "string").build())
+                    .measure(
+                        ColumnBuilder::new("order_cnt", "int")
+                            .expression("count(orders.o_orderkey)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        // a customer with many orders of the same status is counted once
+        let sql = "SELECT m.order_status, m.cnt FROM customer_count m \
+            ORDER BY m.order_status NULLS LAST";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +--------------+-----+
+        | order_status | cnt |
+        +--------------+-----+
+        | F            | 2   |
+        | O            | 1   |
+        |              | 1   |
+        +--------------+-----+
+        ");
+
+        // the equal values of a to-many related model would be collapsed
+        let sql = "SELECT m.c_name, m.order_cnt FROM customer_orders m";
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => {
+                unreachable!("measure over a to-many relationship should be rejected")
+            }
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Measure can't refer to the to-many relationship customer.orders. Define the measure on a metric of orders instead"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semantic_query() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("orders", orders())?;
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("revenue")
+                    .base_object("orders")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("sum(o_totalprice)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "dimensions": ["revenue.o_custkey"],
+                "filters": [{"member": "revenue.o_custkey", "operator": "gt", "values": [1]}],
+                "order": [{"member": "revenue.total", "direction": "desc"}],
+                "limit": 2
+            }"#,
+        )?;
+        let actual = transform_semantic_query_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            &query,
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-----------+-------+
+        | o_custkey | total |
+        +-----------+-------+
+        | 3         | 300   |
+        | 2         | 200   |
+        +-----------+-------+
+        ");
+
+        let plan = semantic_query_to_plan(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            &query,
+        )
+        .await?;
+        let fields = plan
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["o_custkey", "total"]);
+
+        // the dimension only used in the filter isn't grouped
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "filters": [{"member": "revenue.o_custkey", "operator": "gt", "values": [1]}]
+            }"#,
+        )?;
+        let actual = transform_semantic_query_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            &query,
+        )
+        .await?;
+        assert!(!actual.contains("GROUP BY"));
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-------+
+        | total |
+        +-------+
+        | 500   |
+        +-------+
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semantic_query_fill_gaps() -> Result<()> {
+        let ctx = SessionContext::new();
+        let seconds = [1704067200, 1705276800, 1709251200];
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 1]));
+        let order_date: ArrayRef = Arc::new(TimestampNanosecondArray::from(
+            seconds
+                .iter()
+                .map(|s| s * 1_000_000_000)
+                .collect::<Vec<_>>(),
+        ));
+        let totalprice: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_custkey", custkey),
+                ("o_orderdate", order_date),
+                ("o_totalprice", totalprice),
+            ])?,
+        )?;
+        let manifest = |data_source: DataSource| {
+            ManifestBuilder::new()
+                .data_source(data_source)
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "int").build())
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                        .column(ColumnBuilder::new("o_totalprice", "int").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .metric(
+                    MetricBuilder::new("revenue")
+                        .base_object("orders")
+                        .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                        .measure(
+                            ColumnBuilder::new("total", "int")
+                                .expression("sum(o_totalprice)")
+                                .build(),
+                        )
+                        .time_grain(
+                            TimeGrainBuilder::new("order_date")
+                                .ref_column("o_orderdate")
+                                .date_part(TimeUnit::Month)
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build()
+        };
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "timeDimensions": [{
+                    "dimension": "revenue.order_date",
+                    "granularity": "month",
+                    "dateRange": ["2024-01-01", "2024-04-30"],
+                    "fillGaps": true
+                }],
+                "order": [{"member": "revenue.order_date"}]
+            }"#,
+        )?;
+
+        // the months without orders are kept
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest(DataSource::Datafusion),
+            Arc::new(HashMap::default()),
+        )?);
+        let actual = transform_semantic_query_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            &query,
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +---------------------+-------+
+        | order_date__month   | total |
+        +---------------------+-------+
+        | 2024-01-01T00:00:00 | 300   |
+        | 2024-02-01T00:00:00 |       |
+        | 2024-03-01T00:00:00 | 300   |
+        | 2024-04-01T00:00:00 |       |
+        +---------------------+-------+
+        ");
+
+        // the gaps of every customer are filled
+        let by_customer = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "dimensions": ["revenue.o_custkey"],
+                "timeDimensions": [{
+                    "dimension": "revenue.order_date",
+                    "granularity": "month",
+                    "dateRange": ["2024-01-01", "2024-03-31"],
+                    "fillGaps": true
+                }],
+                "order": [{"member": "revenue.o_custkey"}, {"member": "revenue.order_date"}]
+            }"#,
+        )?;
+        let actual = transform_semantic_query_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            &by_customer,
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-----------+---------------------+-------+
+        | o_custkey | order_date__month   | total |
+        +-----------+---------------------+-------+
+        | 1         | 2024-01-01T00:00:00 | 100   |
+        | 1         | 2024-02-01T00:00:00 |       |
+        | 1         | 2024-03-01T00:00:00 | 300   |
+        | 2         | 2024-01-01T00:00:00 | 200   |
+        | 2         | 2024-02-01T00:00:00 |       |
+        | 2         | 2024-03-01T00:00:00 |       |
+        +-----------+---------------------+-------+
+        ");
+
+        for (data_source, function) in [
+            (DataSource::BigQuery, "GENERATE_DATE_ARRAY("),
+            (DataSource::Postgres, "generate_series("),
+        ] {
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+                manifest(data_source),
+                Arc::new(HashMap::default()),
+            )?);
+            let actual = transform_semantic_query_with_ctx(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                &[],
+                Arc::new(HashMap::new()),
+                &query,
+            )
+            .await?;
+            assert!(actual.contains(function));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_materialized_metric() -> Result<()> {
+        let mut wren_config = WrenConfig::default();
+        wren_config.current_time = Some("2024-02-01T00:00:00Z".to_string());
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+        // 2024-02-01T00:00:00Z
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1706745600);
+        let metric = |refresh_time: &str| {
+            MetricBuilder::new("revenue")
+                .base_object("orders")
+                .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                .measure(
+                    ColumnBuilder::new("total", "int")
+                        .expression("sum(o_totalprice)")
+                        .build(),
+                )
+                .time_grain(
+                    TimeGrainBuilder::new("order_date")
+                        .ref_column("o_orderdate")
+                        .date_part(TimeUnit::Day)
+                        .date_part(TimeUnit::Month)
+                        .build(),
+                )
+                .cached(true)
+                .refresh_time(refresh_time)
+                .build()
+        };
+        let manifest = |refresh_time: &str| {
+            ManifestBuilder::new()
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "int").build())
+                        .column(ColumnBuilder::new("o_custkey", "int").build())
+                        .column(ColumnBuilder::new("o_totalprice", "int").build())
+                        .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .metric(metric(refresh_time))
+                .build()
+        };
+        let materialization = || {
+            Materialization::new("revenue_by_day", "revenue")
+                .dimension("o_custkey")
+                .time_grain("order_date", TimeUnit::Day)
+                .measure("total")
+        };
+
+        // the daily revenue is rolled up to the monthly revenue
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest("30m"),
+            Arc::new(HashMap::default()),
+            vec![materialization().refreshed_at(now - Duration::from_secs(600))],
+        )?);
+        let sql = "SELECT r.order_date__month, r.total FROM revenue r ORDER BY 1";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("revenue_by_day"));
+        assert!(!actual.contains("o_totalprice"));
+
+        let seconds = [1704067200, 1705276800, 1706745600];
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 1]));
+        let order_date: ArrayRef = Arc::new(TimestampNanosecondArray::from(
+            seconds
+                .iter()
+                .map(|s| s * 1_000_000_000)
+                .collect::<Vec<_>>(),
+        ));
+        let total: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
+        let execution_ctx = SessionContext::new();
+        execution_ctx.register_batch(
+            "revenue_by_day",
+            RecordBatch::try_from_iter(vec![
+                ("o_custkey", custkey),
+                ("order_date__day", order_date),
+                ("total", total),
+            ])?,
+        )?;
+        let batches = execution_ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +---------------------+-------+
+        | order_date__month   | total |
+        +---------------------+-------+
+        | 2024-01-01T00:00:00 | 300   |
+        | 2024-02-01T00:00:00 | 300   |
+        +---------------------+-------+
+        ");
+
+        // the stale materialization falls back to the metric
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest("30m"),
+            Arc::new(HashMap::default()),
+            vec![materialization().refreshed_at(now - Duration::from_secs(3600))],
+        )?);
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.contains("revenue_by_day"));
+        assert!(actual.contains("o_totalprice"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_materialized_model() -> Result<()> {
+        let mut wren_config = WrenConfig::default();
+        wren_config.current_time = Some("2024-02-01T00:00:00Z".to_string());
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+        // 2024-02-01T00:00:00Z
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1706745600);
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .primary_key("o_orderkey")
+                    .cached(true)
+                    .refresh_time("30m")
+                    .build(),
+            )
+            .build();
+        let materialization = |refreshed_at: SystemTime| {
+            Materialization::new("orders_copy", "orders")
+                .dimension("o_orderkey")
+                .dimension("o_custkey")
+                .refreshed_at(refreshed_at)
+        };
+        let sql = "SELECT o.o_custkey FROM orders o WHERE o.o_orderkey > 1";
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest.clone(),
+            Arc::new(HashMap::default()),
+            vec![materialization(now - Duration::from_secs(600))],
+        )?);
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("orders_copy"));
+
+        // the stale materialization falls back to the model
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest.clone(),
+            Arc::new(HashMap::default()),
+            vec![materialization(now - Duration::from_secs(3600))],
+        )?);
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.contains("orders_copy"));
+
+        // the materialization doesn't cover o_totalprice
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest.clone(),
+            Arc::new(HashMap::default()),
+            vec![materialization(now - Duration::from_secs(600))],
+        )?);
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            "SELECT o.o_totalprice FROM orders o",
+        )
+        .await?;
+        assert!(!actual.contains("orders_copy"));
+
+        // the freshness can't be checked without the current time
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest,
+            Arc::new(HashMap::default()),
+            vec![materialization(now - Duration::from_secs(600))],
+        )?);
+        let actual = transform_sql_with_ctx(
+            &SessionContext::new(),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        assert!(!actual.contains("orders_copy"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_schema_by_data_source() -> Result<()> {
+        let ctx = SessionContext::new();
+        // the date of Oracle has the time part
+        let manifest = ManifestBuilder::new()
+            .data_source(DataSource::Postgres)
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_orderdate", "DATE").build())
+                    .primary_key("o_orderkey")
+                    .data_source(DataSource::Oracle)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_materializations(
+            manifest,
+            Arc::new(HashMap::default()),
+            vec![Materialization::new("orders_copy", "orders").dimension("o_orderdate")],
+        )?);
+        let ctx = create_ctx_with_mdl(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            true,
+        )
+        .await?;
+        let timestamp =
+            DataType::Timestamp(datafusion::arrow::datatypes::TimeUnit::Nanosecond, None);
+        let orders = ctx
+            .table_provider(TableReference::full(
+                analyzed_mdl.wren_mdl().catalog(),
+                analyzed_mdl.wren_mdl().schema(),
+                "orders",
+            ))
+            .await?;
+        assert_eq!(
+            orders.schema().field_with_name("o_orderdate")?.data_type(),
+            &timestamp
+        );
+        let orders_copy = ctx.table_provider("orders_copy").await?;
+        assert_eq!(
+            orders_copy
+                .schema()
+                .field_with_name("o_orderdate")?
+                .data_type(),
+            &timestamp
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semi_additive_metric() -> Result<()> {
+        let ctx = SessionContext::new();
+        let seconds = [1704067200, 1704067200, 1705276800, 1705276800, 1706745600];
+        let account: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 1, 2, 1]));
+        let snapshot_date: ArrayRef = Arc::new(TimestampNanosecondArray::from(
+            seconds
+                .iter()
+                .map(|s| s * 1_000_000_000)
+                .collect::<Vec<_>>(),
+        ));
+        let amount: ArrayRef = Arc::new(Int64Array::from(vec![100, 50, 150, 70, 200]));
+        ctx.register_batch(
+            "balances",
+            RecordBatch::try_from_iter(vec![
+                ("account", account),
+                ("snapshot_date", snapshot_date),
+                ("amount", amount),
+            ])?,
+        )?;
+        let time_grain = || {
+            TimeGrainBuilder::new("snapshot_date")
+                .ref_column("snapshot_date")
+                .date_part(TimeUnit::Month)
+                .build()
+        };
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("balances")
+                    .table_reference("balances")
+                    .column(ColumnBuilder::new("account", "int").build())
+                    .column(ColumnBuilder::new("snapshot_date", "timestamp").build())
+                    .column(ColumnBuilder::new("amount", "int").build())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("balance")
+                    .base_object("balances")
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("sum(amount)")
+                            .non_additive_dimension(
+                                "snapshot_date",
+                                NonAdditiveWindow::Last,
+                            )
+                            .build(),
+                    )
+                    .time_grain(time_grain())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("accounts_to_date")
+                    .base_object("balances")
+                    .measure(
+                        ColumnBuilder::new("cnt", "int")
+                            .expression("count(DISTINCT account)")
+                            .build(),
+                    )
+                    .time_grain(time_grain())
+                    .cumulative("snapshot_date", None)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+
+        // the balance of a month is the sum of the last snapshot in the month
+        let sql = "SELECT b.snapshot_date__month, b.total FROM balance b ORDER BY 1";
+        let actual = transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +----------------------+-------+
+        | snapshot_date__month | total |
+        +----------------------+-------+
+        | 2024-01-01T00:00:00  | 220   |
+        | 2024-02-01T00:00:00  | 200   |
+        +----------------------+-------+
+        ");
+
+        // a distinct count can't be accumulated
+        let sql = "SELECT a.snapshot_date__month, a.cnt FROM accounts_to_date a";
+        match transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            Arc::new(HashMap::new()),
+            sql,
+        )
+        .await
+        {
+            Ok(_) => unreachable!("distinct count should not be accumulated"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Measure cnt of metric accounts_to_date can't be accumulated because it isn't additive"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_file_data_source() -> Result<()> {
+        let manifest = |table_reference: &str| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .data_source(DataSource::LocalFile)
+                .model(
+                    ModelBuilder::new("customers")
+                        .table_reference(table_reference)
+                        .column(ColumnBuilder::new("id", "varchar").build())
+                        .column(ColumnBuilder::new("city", "varchar").build())
+                        .column(ColumnBuilder::new("state", "varchar").build())
+                        .build(),
+                )
+                .build()
+        };
+        let root: PathBuf = [
+            env!("CARGO_MANIFEST_DIR"),
+            "..",
+            "sqllogictest",
+            "tests",
+            "resources",
+        ]
+        .iter()
+        .collect();
+        let mut wren_config = WrenConfig::default();
+        wren_config.file_root = root.canonicalize()?.to_string_lossy().to_string();
+        let ctx = SessionContext::new_with_config(
+            SessionConfig::new().with_option_extension(wren_config),
+        );
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest("ecommerce/customers.csv"),
+            Arc::new(HashMap::default()),
+        )?);
+        let local_ctx = create_ctx_with_mdl(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            Arc::new(HashMap::new()),
+            true,
+        )
+        .await?;
+        let sql = "SELECT count(*) AS total, count(DISTINCT city) AS cities FROM wren.test.customers";
+        let batches = local_ctx.sql(sql).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-------+--------+
+        | total | cities |
+        +-------+--------+
+        | 307   | 11     |
+        +-------+--------+
+        ");
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest("ecommerce/customers"),
+            Arc::new(HashMap::default()),
+        )?);
+        match create_ctx_with_mdl(&ctx, analyzed_mdl, Arc::new(HashMap::new()), true)
+            .await
+        {
+            Ok(_) => unreachable!("the format of the files can't be inferred"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: Can't infer the file format of model customers from ecommerce/customers. Set the format property or wren.file_format to the format of the files"
+            ),
+        }
+
+        // the data source of the model is used instead of the data source of the manifest
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(DataSource::Postgres)
+            .model(
+                ModelBuilder::new("customers")
+                    .table_reference("ecommerce/customers.csv")
+                    .data_source(DataSource::LocalFile)
+                    .column(ColumnBuilder::new("id", "varchar").build())
+                    .column(ColumnBuilder::new("city", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        let local_ctx =
+            create_ctx_with_mdl(&ctx, analyzed_mdl, Arc::new(HashMap::new()), true)
+                .await?;
+        let sql = "SELECT count(*) AS total FROM wren.test.customers";
+        let batches = local_ctx.sql(sql).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +-------+
+        | total |
+        +-------+
+        | 307   |
+        +-------+
+        ");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_object_store_data_source() -> Result<()> {
+        let store = InMemory::new();
+        store
+            .put(
+                &Path::from("tpch/customer.csv"),
+                PutPayload::from_static(b"c_custkey,c_name\n1,Alice\n2,Bob\n3,Carol\n"),
+            )
+            .await?;
+        let ctx = SessionContext::new();
+        ctx.register_object_store(
+            ObjectStoreUrl::parse("s3://wren")?.as_ref(),
+            Arc::new(store),
+        );
+        let manifest = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(DataSource::S3File)
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer.csv")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(
+            manifest,
+            Arc::new(HashMap::default()),
+        )?);
+        let properties = Arc::new(HashMap::from([
+            ("bucket".to_string(), Some("wren".to_string())),
+            ("url".to_string(), Some("/tpch".to_string())),
+        ]));
+        let local_ctx =
+            create_ctx_with_mdl(&ctx, Arc::clone(&analyzed_mdl), properties, true)
+                .await?;
+        let sql = "SELECT c_name FROM wren.test.customer WHERE c_custkey > 1 ORDER BY 1";
+        let batches = local_ctx.sql(sql).await?.collect().await?;
+        assert_snapshot!(batches_to_string(&batches), @r"
+        +--------+
+        | c_name |
+        +--------+
+        | Bob    |
+        | Carol  |
+        +--------+
+        ");
+
+        match create_ctx_with_mdl(&ctx, analyzed_mdl, Arc::new(HashMap::new()), true)
+            .await
+        {
+            Ok(_) => unreachable!("the bucket is required"),
+            Err(e) => assert_snapshot!(
+                e.to_string(),
+                @"Error during planning: The bucket property is required by S3_FILE"
+            ),
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rlac_on_calculated_field() -> Result<()> {
         let ctx = SessionContext::new();
@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use crate::mdl::manifest::{JoinType, Model};
+use crate::mdl::utils::quoted;
+use crate::mdl::WrenMDL;
+use datafusion::common::{not_impl_err, plan_datafusion_err, plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, Expr, Ident, Join, ObjectName, OrderBy, Query, Select,
+    SetExpr, Statement, TableFactor, TableWithJoins, VisitMut, VisitorMut,
+};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+
+/// Expand the relationship paths used in the query to the joins of the related models.
+///
+/// A relationship path is a compound identifier starting with a model relation in the FROM clause,
+/// followed by the relationship columns and ending with a column of the last related model. e.g.
+///
+/// ```sql
+/// SELECT o.customer.c_name FROM orders o
+/// ```
+///
+/// is expanded to
+///
+/// ```sql
+/// SELECT o__customer.c_name FROM orders o LEFT JOIN customer AS o__customer ON o__customer.c_custkey = o.o_custkey
+/// ```
+///
+/// The joined models are analyzed by [crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule]
+/// like the other models. The paths are expanded on the AST instead of in the analyzer because
+/// the SQL planner resolves `o.customer.c_name` as a field of the column `customer` and fails
+/// before any analyzer rule runs. Only the to-one relationships can be traversed because a
+/// to-many relationship would change the number of the rows.
+pub(crate) fn expand_relationship_paths(
+    wren_mdl: &WrenMDL,
+    statement: &mut Statement,
+) -> Result<()> {
+    if wren_mdl.relationships().is_empty() {
+        return Ok(());
+    }
+    let mut expander = RelationshipPathExpander {
+        wren_mdl,
+        error: None,
+    };
+    let _ = statement.visit(&mut expander);
+    match expander.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+struct RelationshipPathExpander<'a> {
+    wren_mdl: &'a WrenMDL,
+    error: Option<DataFusionError>,
+}
+
+impl VisitorMut for RelationshipPathExpander<'_> {
+    type Break = ();
+
+    /// The inner queries are visited first, so a path is resolved in the closest scope.
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        match self.expand_query(query) {
+            Ok(_) => ControlFlow::Continue(()),
+            Err(e) => {
+                self.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+impl RelationshipPathExpander<'_> {
+    fn expand_query(&self, query: &mut Query) -> Result<()> {
+        let Query { body, order_by, .. } = query;
+        match body.as_mut() {
+            SetExpr::Select(select) => self.expand_select(select, Some(order_by)),
+            set_expr => self.expand_set_expr(set_expr),
+        }
+    }
+
+    /// The ORDER BY of a set operation refers to its output, so only the selects are expanded.
+    fn expand_set_expr(&self, set_expr: &mut SetExpr) -> Result<()> {
+        match set_expr {
+            SetExpr::Select(select) => self.expand_select(select, None),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.expand_set_expr(left)?;
+                self.expand_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn expand_select(
+        &self,
+        select: &mut Select,
+        order_by: Option<&mut Option<OrderBy>>,
+    ) -> Result<()> {
+        let relations = self.collect_model_relations(&select.from);
+        if relations.is_empty() {
+            return Ok(());
+        }
+
+        let mut joins: Vec<JoinedPath> = vec![];
+        let mut error = None;
+        let mut rewrite = |expr: &mut Expr| {
+            if let Expr::CompoundIdentifier(idents) = expr {
+                match self.resolve_path(idents, &relations, &mut joins) {
+                    Ok(Some(resolved)) => *idents = resolved,
+                    Ok(None) => {}
+                    Err(e) => {
+                        error = Some(e);
+                        return ControlFlow::Break(());
+                    }
+                }
+            }
+            ControlFlow::Continue(())
+        };
+        let _ = visit_expressions_mut(select, &mut rewrite);
+        if let Some(order_by) = order_by {
+            let _ = visit_expressions_mut(order_by, &mut rewrite);
+        }
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        for table_with_joins in select.from.iter_mut() {
+            let original = std::mem::take(&mut table_with_joins.joins);
+            let mut result = vec![];
+            push_joined_paths(
+                relation_qualifier(&table_with_joins.relation),
+                &mut joins,
+                &mut result,
+            );
+            for join in original {
+                let qualifier = relation_qualifier(&join.relation);
+                result.push(join);
+                push_joined_paths(qualifier, &mut joins, &mut result);
+            }
+            table_with_joins.joins = result;
+        }
+        if let Some(joined) = joins.first() {
+            return plan_err!("Relation {} not found", joined.source_alias);
+        }
+        Ok(())
+    }
+
+    /// Collect the relations of the models in the FROM clause. The key is the qualifier used in the query.
+    fn collect_model_relations(
+        &self,
+        from: &[TableWithJoins],
+    ) -> HashMap<String, Arc<Model>> {
+        from.iter()
+            .flat_map(|t| {
+                std::iter::once(&t.relation).chain(t.joins.iter().map(|j| &j.relation))
+            })
+            .filter_map(|relation| {
+                let TableFactor::Table { name, alias, .. } = relation else {
+                    return None;
+                };
+                let model = try_get_model(self.wren_mdl, name)?;
+                let qualifier = alias
+                    .as_ref()
+                    .map(|a| normalize_ident(&a.name))
+                    .unwrap_or_else(|| model.name().to_string());
+                Some((qualifier, model))
+            })
+            .collect()
+    }
+
+    /// Resolve the relationship path and return the rewritten identifiers.
+    /// Return None if the identifiers aren't a relationship path.
+    fn resolve_path(
+        &self,
+        idents: &[Ident],
+        relations: &HashMap<String, Arc<Model>>,
+        joins: &mut Vec<JoinedPath>,
+    ) -> Result<Option<Vec<Ident>>> {
+        if idents.len() < 3 {
+            return Ok(None);
+        }
+        let qualifier = normalize_ident(&idents[0]);
+        let Some(model) = relations.get(&qualifier) else {
+            return Ok(None);
+        };
+        let is_relationship = |model: &Model, ident: &Ident| {
+            model
+                .get_column(&normalize_ident(ident))
+                .map(|c| c.relationship.is_some())
+                .unwrap_or(false)
+        };
+        if !is_relationship(model, &idents[1]) {
+            return Ok(None);
+        }
+
+        let mut current_model = Arc::clone(model);
+        let mut current_alias = qualifier;
+        let mut rest = &idents[1..];
+        while let Some((ident, remaining)) = rest.split_first() {
+            if remaining.is_empty() || !is_relationship(&current_model, ident) {
+                break;
+            }
+            let column_name = normalize_ident(ident);
+            let alias = format!("{current_alias}__{column_name}");
+            let target = match joins.iter().find(|j| j.alias == alias) {
+                Some(joined) => Arc::clone(&joined.model),
+                None => {
+                    let joined = self.join_relationship(
+                        &current_model,
+                        &current_alias,
+                        &column_name,
+                        &alias,
+                    )?;
+                    let target = Arc::clone(&joined.model);
+                    joins.push(joined);
+                    target
+                }
+            };
+            current_model = target;
+            current_alias = alias;
+            rest = remaining;
+        }
+        let mut resolved = vec![Ident::with_quote('"', current_alias)];
+        resolved.extend(rest.iter().cloned());
+        Ok(Some(resolved))
+    }
+
+    fn join_relationship(
+        &self,
+        model: &Model,
+        model_alias: &str,
+        column_name: &str,
+        alias: &str,
+    ) -> Result<JoinedPath> {
+        let Some(relationship) = model
+            .get_column(column_name)
+            .and_then(|c| c.relationship.clone())
+            .and_then(|name| self.wren_mdl.get_relationship(&name))
+        else {
+            return plan_err!(
+                "Relationship of {}.{} not found",
+                model.name(),
+                column_name
+            );
+        };
+        let Some(index) = relationship.models.iter().position(|m| m == model.name())
+        else {
+            return plan_err!(
+                "Model {} isn't a part of relationship {}",
+                model.name(),
+                relationship.name
+            );
+        };
+        let target_name = &relationship.models[1 - index];
+        if target_name == model.name() {
+            return not_impl_err!(
+                "Relationship path through the self relationship {} isn't supported",
+                relationship.name
+            );
+        }
+        // The join type is declared from the first model to the second model.
+        let join_type = if index == 0 {
+            relationship.join_type
+        } else {
+            match relationship.join_type {
+                JoinType::OneToMany => JoinType::ManyToOne,
+                JoinType::ManyToOne => JoinType::OneToMany,
+                _ => relationship.join_type,
+            }
+        };
+        if !join_type.is_to_one() {
+            return plan_err!(
+                "Relationship path {}.{} is a to-many relationship. Use a calculated field with aggregation instead",
+                model_alias,
+                column_name
+            );
+        }
+        let Some(target) = self.wren_mdl.get_model(target_name) else {
+            return plan_err!("Model {} not found", target_name);
+        };
+
+        let condition = requalify_condition(
+            &relationship.condition,
+            &[(model.name(), model_alias), (target_name.as_str(), alias)],
+        )?;
+        let sql = format!(
+            "SELECT 1 FROM {} LEFT JOIN {} AS {} ON {}",
+            quoted(model_alias),
+            quoted(target.name()),
+            quoted(alias),
+            condition
+        );
+        let join = Parser::parse_sql(&GenericDialect {}, &sql)?
+            .into_iter()
+            .next()
+            .and_then(|statement| match statement {
+                Statement::Query(query) => match *query.body {
+                    SetExpr::Select(mut select) => {
+                        select.from.pop().and_then(|mut t| t.joins.pop())
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .ok_or_else(|| {
+                plan_datafusion_err!("Failed to build the join for {}", alias)
+            })?;
+        Ok(JoinedPath {
+            source_alias: model_alias.to_string(),
+            alias: alias.to_string(),
+            model: target,
+            join,
+        })
+    }
+}
+
+/// The join generated for a relationship path
+struct JoinedPath {
+    /// The qualifier of the model where the relationship comes from
+    source_alias: String,
+    /// The alias of the joined model
+    alias: String,
+    model: Arc<Model>,
+    join: Join,
+}
+
+/// Push the joins of the paths from the relation right after it, so the paths used in the
+/// conditions of the following joins are already joined.
+fn push_joined_paths(
+    qualifier: Option<String>,
+    joins: &mut Vec<JoinedPath>,
+    result: &mut Vec<Join>,
+) {
+    let Some(qualifier) = qualifier else {
+        return;
+    };
+    let (matched, rest): (Vec<_>, Vec<_>) = std::mem::take(joins)
+        .into_iter()
+        .partition(|joined| joined.source_alias == qualifier);
+    *joins = rest;
+    for joined in matched {
+        result.push(joined.join);
+        push_joined_paths(Some(joined.alias), joins, result);
+    }
+}
+
+/// The qualifier of the relation used in the query
+fn relation_qualifier(relation: &TableFactor) -> Option<String> {
+    match relation {
+        TableFactor::Table { name, alias, .. } => alias
+            .as_ref()
+            .map(|a| &a.name)
+            .or_else(|| name.0.last())
+            .map(normalize_ident),
+        _ => None,
+    }
+}
+
+/// Parse the condition of a relationship and replace the model names with the qualifiers used in the query.
+/// `qualifiers` is the pairs of the model name and its qualifier.
+pub(crate) fn requalify_condition(
+    condition: &str,
+    qualifiers: &[(&str, &str)],
+) -> Result<Expr> {
+    let mut condition = Parser::new(&GenericDialect {})
+        .try_with_sql(condition)?
+        .parse_expr()?;
+    let _ = visit_expressions_mut(&mut condition, |expr| {
+        if let Expr::CompoundIdentifier(idents) = expr {
+            if let [qualifier, _] = idents.as_mut_slice() {
+                let name = normalize_ident(qualifier);
+                if let Some((_, alias)) = qualifiers.iter().find(|(m, _)| *m == name) {
+                    *qualifier = Ident::with_quote('"', *alias);
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(condition)
+}
+
+/// Get the model referenced by the table name in the query
+pub(crate) fn try_get_model(wren_mdl: &WrenMDL, name: &ObjectName) -> Option<Arc<Model>> {
+    mdl_table_name(wren_mdl, name).and_then(|table| wren_mdl.get_model(&table))
+}
+
+/// Return the name of the MDL object if the table name refers to the catalog and schema of the MDL
+pub(crate) fn mdl_table_name(wren_mdl: &WrenMDL, name: &ObjectName) -> Option<String> {
+    let mut parts = name.0.iter().map(normalize_ident).collect::<Vec<_>>();
+    let is_mdl_table = match parts.as_slice() {
+        [_] => true,
+        [schema, _] => schema == wren_mdl.schema(),
+        [catalog, schema, _] => {
+            catalog == wren_mdl.catalog() && schema == wren_mdl.schema()
+        }
+        _ => false,
+    };
+    if is_mdl_table {
+        parts.pop()
+    } else {
+        None
+    }
+}
+
+/// The unquoted identifier is normalized to lowercase like DataFusion does.
+pub(crate) fn normalize_ident(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
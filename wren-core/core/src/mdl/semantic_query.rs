@@ -0,0 +1,901 @@
+use std::sync::Arc;
+
+use crate::mdl::manifest::{Hierarchy, TimeGrain, TimeUnit};
+use crate::mdl::metric::{
+    date_spine, supports_date_spine, time_grain_column, truncate_timestamp,
+};
+use crate::mdl::utils::quoted;
+use crate::mdl::WrenMDL;
+use datafusion::common::{not_impl_err, plan_datafusion_err, plan_err, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const SPINE_ALIAS: &str = "__spine";
+const QUERY_ALIAS: &str = "__query";
+const DIMENSIONS_ALIAS: &str = "__dimensions";
+
+/// A structured query of the measures and dimensions of a metric or a model.
+///
+/// The members are referred by `{object}.{name}`, e.g. `revenue.total`, and all of them should
+/// belong to the same metric or model. The measures are aggregated by the dimensions and the
+/// time dimensions like querying the metric in SQL. A model can only be queried by its columns
+/// as the dimensions. e.g.
+///
+/// ```json
+/// {
+///   "measures": ["revenue.total"],
+///   "dimensions": ["revenue.o_custkey"],
+///   "timeDimensions": [{"dimension": "revenue.order_date", "granularity": "month"}],
+///   "filters": [{"member": "revenue.o_custkey", "operator": "in", "values": [1, 2]}],
+///   "order": [{"member": "revenue.total", "direction": "desc"}],
+///   "limit": 10
+/// }
+/// ```
+///
+/// is compiled to
+///
+/// ```sql
+/// SELECT "revenue"."o_custkey", "revenue"."order_date__month", "revenue"."total" FROM "revenue"
+/// WHERE "revenue"."o_custkey" IN (1, 2) ORDER BY "revenue"."total" DESC LIMIT 10
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticQuery {
+    #[serde(default)]
+    pub measures: Vec<String>,
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    #[serde(default)]
+    pub time_dimensions: Vec<TimeDimension>,
+    #[serde(default)]
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub order: Vec<Order>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// A time grain of the metric truncated by the granularity.
+///
+/// The periods are filtered by the inclusive `dateRange`, e.g. `["2024-01-01", "2024-03-31"]`.
+/// If `fillGaps` is set, the results are joined onto a date spine of the range, so the periods
+/// without data are kept with null measures. The spine is crossed with the values of the other
+/// dimensions, so the gaps of every value are filled. It's only supported by the data sources
+/// generating the spine, e.g. DataFusion, DuckDB, PostgreSQL and BigQuery.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeDimension {
+    pub dimension: String,
+    pub granularity: TimeUnit,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_range: Option<(String, String)>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fill_gaps: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Filter {
+    pub member: String,
+    pub operator: FilterOperator,
+    #[serde(default)]
+    pub values: Vec<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterOperator {
+    Equals,
+    NotEquals,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    Contains,
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+    pub member: String,
+    #[serde(default)]
+    pub direction: OrderDirection,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SemanticQuery {
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| plan_datafusion_err!("Invalid semantic query: {e}"))
+    }
+
+    /// Compile the semantic query to the Wren SQL
+    pub fn to_sql(&self, wren_mdl: &WrenMDL) -> Result<String> {
+        let object = self.object_name()?;
+        let resolver = MemberResolver::try_new(wren_mdl, object, &self.time_dimensions)?;
+        self.validate_hierarchies(wren_mdl, object)?;
+
+        let mut columns = vec![];
+        for dimension in self.dimensions.iter() {
+            columns.push(resolver.dimension(dimension)?);
+        }
+        for time_dimension in self.time_dimensions.iter() {
+            columns.push(resolver.time_dimension(time_dimension)?);
+        }
+        let dimension_count = columns.len();
+        for measure in self.measures.iter() {
+            columns.push(resolver.measure(measure)?);
+        }
+        if columns.is_empty() {
+            return plan_err!(
+                "Semantic query should have at least one measure or dimension"
+            );
+        }
+
+        let mut filters = vec![];
+        for filter in self.filters.iter() {
+            let column = qualified(object, &resolver.member(&filter.member)?);
+            filters.push(filter_to_sql(&column, filter)?);
+        }
+        for time_dimension in self.time_dimensions.iter() {
+            if let Some((start, end)) = resolver.date_range(time_dimension)? {
+                let column = qualified(object, &resolver.time_dimension(time_dimension)?);
+                filters.push(format!("{column} >= {start} AND {column} <= {end}"));
+            }
+        }
+
+        // The dimensions of a model are distinct like the dimensions of a metric
+        let distinct = if resolver.is_metric { "" } else { "DISTINCT " };
+        let projection = |qualifier: &str| {
+            columns
+                .iter()
+                .map(|column| qualified(qualifier, column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut sql = format!(
+            "SELECT {distinct}{} FROM {}",
+            projection(object),
+            quoted(object)
+        );
+        if !filters.is_empty() {
+            sql.push_str(&format!(" WHERE {}", filters.join(" AND ")));
+        }
+
+        // The results are joined onto the date spine, so the other columns are qualified by
+        // the query instead of the object.
+        let mut spine_columns = None;
+        if let Some((time_dimension, spine)) = self.date_spine(&resolver)? {
+            let time_column = resolver.time_dimension(time_dimension)?;
+            let dimensions = columns[..dimension_count]
+                .iter()
+                .filter(|column| **column != time_column)
+                .cloned()
+                .collect();
+            let spine_joined = SpineColumns {
+                time_column,
+                dimensions,
+            };
+            sql = spine_joined.join(&spine, &sql, &columns);
+            spine_columns = Some(spine_joined);
+        }
+
+        if !self.order.is_empty() {
+            let order = self
+                .order
+                .iter()
+                .map(|order| {
+                    let direction = match order.direction {
+                        OrderDirection::Asc => "ASC",
+                        OrderDirection::Desc => "DESC",
+                    };
+                    let column = resolver.member(&order.member)?;
+                    let qualifier = match &spine_columns {
+                        Some(spine_columns) => spine_columns.qualifier(&column),
+                        None => object,
+                    };
+                    Ok(format!("{} {direction}", qualified(qualifier, &column)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        Ok(sql)
+    }
+
+    /// Return the time dimension filling the gaps and the SQL of its date spine
+    fn date_spine<'a>(
+        &'a self,
+        resolver: &MemberResolver,
+    ) -> Result<Option<(&'a TimeDimension, String)>> {
+        let mut filled = self.time_dimensions.iter().filter(|t| t.fill_gaps);
+        let Some(time_dimension) = filled.next() else {
+            return Ok(None);
+        };
+        if filled.next().is_some() {
+            return not_impl_err!(
+                "Filling the gaps of multiple time dimensions isn't supported"
+            );
+        }
+        let Some((start, end)) = &time_dimension.date_range else {
+            return plan_err!(
+                "Time dimension {} should have the date range to fill the gaps",
+                time_dimension.dimension
+            );
+        };
+        let data_source = match resolver.wren_mdl.get_metric(resolver.object) {
+            Some(metric) => resolver.wren_mdl.metric_data_source(&metric),
+            None => resolver.wren_mdl.data_source(),
+        }
+        .unwrap_or_default();
+        if !supports_date_spine(&data_source) {
+            return plan_err!("Filling the gaps isn't supported by {data_source}");
+        }
+        let time_grain = resolver.time_grain(&time_dimension.dimension)?;
+        let spine = date_spine(
+            &resolver.time_dimension(time_dimension)?,
+            &time_dimension.granularity,
+            time_grain.fiscal_year_start_month(),
+            start,
+            end,
+        )?;
+        Ok(Some((time_dimension, spine)))
+    }
+
+    /// Drill down the hierarchy, e.g. `orders.geo`, by grouping the level below the finest
+    /// level in the dimensions. The coarsest level is grouped if no level is in the dimensions.
+    pub fn drill_down(&self, wren_mdl: &WrenMDL, hierarchy: &str) -> Result<Self> {
+        let (object, name) = split_member(hierarchy)?;
+        let hierarchy = find_hierarchy(wren_mdl, object, name)?;
+        let level = match self.finest_level(object, &hierarchy) {
+            Some(level) => hierarchy.drill_down(level).ok_or_else(|| {
+                plan_datafusion_err!(
+                    "Hierarchy {object}.{name} is already at the finest level {level}"
+                )
+            })?,
+            None => hierarchy.levels[0].as_str(),
+        };
+        let mut query = self.clone();
+        query.dimensions.push(format!("{object}.{level}"));
+        Ok(query)
+    }
+
+    /// Drill up the hierarchy, e.g. `orders.geo`, by removing the finest level from the
+    /// dimensions and the order. The filters of the level are kept.
+    pub fn drill_up(&self, wren_mdl: &WrenMDL, hierarchy: &str) -> Result<Self> {
+        let (object, name) = split_member(hierarchy)?;
+        let hierarchy = find_hierarchy(wren_mdl, object, name)?;
+        let Some(level) = self.finest_level(object, &hierarchy) else {
+            return plan_err!(
+                "Hierarchy {object}.{name} isn't grouped by the dimensions"
+            );
+        };
+        let member = format!("{object}.{level}");
+        let mut query = self.clone();
+        query.dimensions.retain(|dimension| *dimension != member);
+        query.order.retain(|order| order.member != member);
+        Ok(query)
+    }
+
+    /// Return the finest level of the hierarchy in the dimensions
+    fn finest_level<'a>(
+        &self,
+        object: &str,
+        hierarchy: &'a Hierarchy,
+    ) -> Option<&'a str> {
+        self.dimensions
+            .iter()
+            .filter_map(|dimension| match split_member(dimension) {
+                Ok((o, level)) if o == object => hierarchy.level_index(level),
+                _ => None,
+            })
+            .max()
+            .map(|index| hierarchy.levels[index].as_str())
+    }
+
+    /// A level of a hierarchy should be grouped with all of its coarser levels. e.g. the cities
+    /// of the same name in different regions shouldn't be grouped together.
+    fn validate_hierarchies(&self, wren_mdl: &WrenMDL, object: &str) -> Result<()> {
+        for hierarchy in hierarchies(wren_mdl, object)? {
+            let Some(level) = self.finest_level(object, &hierarchy) else {
+                continue;
+            };
+            let missing = hierarchy
+                .levels
+                .iter()
+                .take_while(|l| *l != level)
+                .filter(|l| !self.dimensions.contains(&format!("{object}.{l}")))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                return plan_err!(
+                    "Dimension {object}.{level} should be grouped with the coarser levels of hierarchy {}: {}",
+                    hierarchy.name(),
+                    missing.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the metric or model queried by the members
+    fn object_name(&self) -> Result<&str> {
+        let members = self
+            .measures
+            .iter()
+            .chain(self.dimensions.iter())
+            .chain(self.time_dimensions.iter().map(|t| &t.dimension))
+            .chain(self.filters.iter().map(|f| &f.member))
+            .chain(self.order.iter().map(|o| &o.member));
+        let mut object = None;
+        for member in members {
+            let (name, _) = split_member(member)?;
+            match object {
+                None => object = Some(name),
+                Some(object) if object == name => {}
+                Some(object) => {
+                    return not_impl_err!(
+                        "Semantic query across multiple objects isn't supported: {object}, {name}"
+                    )
+                }
+            }
+        }
+        match object {
+            Some(object) => Ok(object),
+            None => {
+                plan_err!("Semantic query should have at least one measure or dimension")
+            }
+        }
+    }
+}
+
+fn split_member(member: &str) -> Result<(&str, &str)> {
+    match member.split_once('.') {
+        Some((object, name)) if !object.is_empty() && !name.is_empty() => {
+            Ok((object, name))
+        }
+        _ => plan_err!("Member {member} should be in the form of object.name"),
+    }
+}
+
+/// Return the hierarchies of the model, or the hierarchies of the base model of the metric
+/// whose levels are all the dimensions of the metric
+fn hierarchies(wren_mdl: &WrenMDL, object: &str) -> Result<Vec<Arc<Hierarchy>>> {
+    if let Some(model) = wren_mdl.get_model(object) {
+        for hierarchy in model.hierarchies() {
+            if let Some(level) = hierarchy
+                .levels
+                .iter()
+                .find(|level| model.get_column(level).is_none())
+            {
+                return plan_err!(
+                    "Level {level} of hierarchy {} not found in model {object}",
+                    hierarchy.name()
+                );
+            }
+        }
+        return Ok(model.hierarchies().to_vec());
+    }
+    let Some(metric) = wren_mdl.get_metric(object) else {
+        return Ok(vec![]);
+    };
+    let Some(model) = wren_mdl.get_model(metric.base_object()) else {
+        return Ok(vec![]);
+    };
+    Ok(model
+        .hierarchies()
+        .iter()
+        .filter(|hierarchy| {
+            hierarchy
+                .levels
+                .iter()
+                .all(|level| metric.get_dimension(level).is_some())
+        })
+        .cloned()
+        .collect())
+}
+
+fn find_hierarchy(
+    wren_mdl: &WrenMDL,
+    object: &str,
+    name: &str,
+) -> Result<Arc<Hierarchy>> {
+    hierarchies(wren_mdl, object)?
+        .into_iter()
+        .find(|hierarchy| hierarchy.name() == name && !hierarchy.levels.is_empty())
+        .ok_or_else(|| plan_datafusion_err!("Hierarchy {object}.{name} not found"))
+}
+
+/// Resolve the members to the qualified columns of the queried object
+struct MemberResolver<'a> {
+    wren_mdl: &'a WrenMDL,
+    object: &'a str,
+    is_metric: bool,
+    time_dimensions: &'a [TimeDimension],
+}
+
+impl<'a> MemberResolver<'a> {
+    fn try_new(
+        wren_mdl: &'a WrenMDL,
+        object: &'a str,
+        time_dimensions: &'a [TimeDimension],
+    ) -> Result<Self> {
+        let is_metric = if wren_mdl.get_metric(object).is_some() {
+            true
+        } else if wren_mdl.get_model(object).is_some() {
+            false
+        } else {
+            return plan_err!("Metric or model {object} not found");
+        };
+        Ok(Self {
+            wren_mdl,
+            object,
+            is_metric,
+            time_dimensions,
+        })
+    }
+
+    fn dimension(&self, member: &str) -> Result<String> {
+        let (_, name) = split_member(member)?;
+        let found = match self.wren_mdl.get_metric(self.object) {
+            Some(metric) => metric.get_dimension(name).is_some(),
+            None => self
+                .wren_mdl
+                .get_model(self.object)
+                .and_then(|model| model.get_column(name))
+                .is_some(),
+        };
+        if !found {
+            return plan_err!("Dimension {member} not found");
+        }
+        Ok(name.to_string())
+    }
+
+    fn measure(&self, member: &str) -> Result<String> {
+        let (_, name) = split_member(member)?;
+        let Some(metric) = self.wren_mdl.get_metric(self.object) else {
+            return plan_err!("Measure {member} should be a measure of a metric");
+        };
+        if metric.get_measure(name).is_none() {
+            return plan_err!("Measure {member} not found");
+        }
+        Ok(name.to_string())
+    }
+
+    fn time_grain(&self, dimension: &str) -> Result<TimeGrain> {
+        let (_, name) = split_member(dimension)?;
+        let Some(metric) = self.wren_mdl.get_metric(self.object) else {
+            return plan_err!(
+                "Time dimension {dimension} should be a time grain of a metric"
+            );
+        };
+        match metric.get_time_grain(name) {
+            Some(time_grain) => Ok(time_grain.clone()),
+            None => plan_err!("Time grain {dimension} not found"),
+        }
+    }
+
+    fn time_dimension(&self, time_dimension: &TimeDimension) -> Result<String> {
+        let time_grain = self.time_grain(&time_dimension.dimension)?;
+        Ok(time_grain_column(
+            &time_grain.name,
+            &time_dimension.granularity,
+        ))
+    }
+
+    /// Return the start and the end of the date range truncated to the granularity
+    fn date_range(
+        &self,
+        time_dimension: &TimeDimension,
+    ) -> Result<Option<(String, String)>> {
+        let Some((start, end)) = &time_dimension.date_range else {
+            return Ok(None);
+        };
+        let unit = &time_dimension.granularity;
+        let fiscal_year_start_month = self
+            .time_grain(&time_dimension.dimension)?
+            .fiscal_year_start_month();
+        Ok(Some((
+            truncate_timestamp(start, unit, fiscal_year_start_month)?,
+            truncate_timestamp(end, unit, fiscal_year_start_month)?,
+        )))
+    }
+
+    /// Resolve the member used by the filters and the order. A time grain refers to the
+    /// granularity requested by the time dimensions.
+    fn member(&self, member: &str) -> Result<String> {
+        let (_, name) = split_member(member)?;
+        if let Some(time_dimension) =
+            self.time_dimensions.iter().find(|t| t.dimension == member)
+        {
+            return self.time_dimension(time_dimension);
+        }
+        let is_measure = self
+            .wren_mdl
+            .get_metric(self.object)
+            .map(|metric| metric.get_measure(name).is_some())
+            .unwrap_or(false);
+        if is_measure {
+            self.measure(member)
+        } else {
+            self.dimension(member)
+        }
+    }
+}
+
+/// The columns of the results joined onto the date spine
+struct SpineColumns {
+    /// The time grain column filled by the spine
+    time_column: String,
+    /// The other dimensions of the results
+    dimensions: Vec<String>,
+}
+
+impl SpineColumns {
+    fn qualifier(&self, column: &str) -> &'static str {
+        if column == self.time_column {
+            SPINE_ALIAS
+        } else if self.dimensions.iter().any(|d| d == column) {
+            DIMENSIONS_ALIAS
+        } else {
+            QUERY_ALIAS
+        }
+    }
+
+    /// Join the query onto the spine. If there are other dimensions, the spine is crossed with
+    /// their values in the results, so the gaps of every value are filled.
+    fn join(&self, spine: &str, query: &str, columns: &[String]) -> String {
+        let projection = columns
+            .iter()
+            .map(|column| qualified(self.qualifier(column), column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let condition = format!(
+            "{} = {}",
+            qualified(SPINE_ALIAS, &self.time_column),
+            qualified(QUERY_ALIAS, &self.time_column)
+        );
+        if self.dimensions.is_empty() {
+            return format!(
+                "SELECT {projection} FROM ({spine}) AS {} LEFT JOIN ({query}) AS {} ON {condition}",
+                quoted(SPINE_ALIAS),
+                quoted(QUERY_ALIAS),
+            );
+        }
+        // The null values of the dimensions are matched as well
+        let conditions = self
+            .dimensions
+            .iter()
+            .map(|column| {
+                format!(
+                    " AND {} IS NOT DISTINCT FROM {}",
+                    qualified(DIMENSIONS_ALIAS, column),
+                    qualified(QUERY_ALIAS, column)
+                )
+            })
+            .collect::<String>();
+        let dimensions = self
+            .dimensions
+            .iter()
+            .map(|column| qualified(QUERY_ALIAS, column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "WITH {alias} AS ({query}) SELECT {projection} FROM ({spine}) AS {} CROSS JOIN (SELECT DISTINCT {dimensions} FROM {alias}) AS {} LEFT JOIN {alias} ON {condition}{conditions}",
+            quoted(SPINE_ALIAS),
+            quoted(DIMENSIONS_ALIAS),
+            alias = quoted(QUERY_ALIAS),
+        )
+    }
+}
+
+fn qualified(qualifier: &str, name: &str) -> String {
+    format!("{}.{}", quoted(qualifier), quoted(name))
+}
+
+fn filter_to_sql(column: &str, filter: &Filter) -> Result<String> {
+    let Filter {
+        member,
+        operator,
+        values,
+    } = filter;
+    let single_value = || match values.as_slice() {
+        [value] => literal(value),
+        _ => plan_err!("Filter {operator:?} of {member} requires exactly one value"),
+    };
+    let sql = match operator {
+        FilterOperator::Equals => format!("{column} = {}", single_value()?),
+        FilterOperator::NotEquals => format!("{column} <> {}", single_value()?),
+        FilterOperator::Gt => format!("{column} > {}", single_value()?),
+        FilterOperator::Gte => format!("{column} >= {}", single_value()?),
+        FilterOperator::Lt => format!("{column} < {}", single_value()?),
+        FilterOperator::Lte => format!("{column} <= {}", single_value()?),
+        FilterOperator::In | FilterOperator::NotIn => {
+            if values.is_empty() {
+                return plan_err!(
+                    "Filter {operator:?} of {member} requires at least one value"
+                );
+            }
+            let values = values.iter().map(literal).collect::<Result<Vec<_>>>()?;
+            let not = if *operator == FilterOperator::NotIn {
+                "NOT "
+            } else {
+                ""
+            };
+            format!("{column} {not}IN ({})", values.join(", "))
+        }
+        FilterOperator::Contains => {
+            let [Value::String(value)] = values.as_slice() else {
+                return plan_err!(
+                    "Filter Contains of {member} requires exactly one string"
+                );
+            };
+            let pattern = value
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("{column} LIKE {}", quote_string(&format!("%{pattern}%")))
+        }
+        FilterOperator::IsNull => format!("{column} IS NULL"),
+        FilterOperator::IsNotNull => format!("{column} IS NOT NULL"),
+    };
+    Ok(sql)
+}
+
+fn literal(value: &Value) -> Result<String> {
+    match value {
+        Value::Null => Ok("NULL".to_string()),
+        Value::Bool(b) => Ok(if *b { "TRUE" } else { "FALSE" }.to_string()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::String(s) => Ok(quote_string(s)),
+        _ => plan_err!("Unsupported filter value: {value}"),
+    }
+}
+
+fn quote_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, MetricBuilder, ModelBuilder, TimeGrainBuilder,
+    };
+    use crate::mdl::manifest::{DataSource, TimeUnit};
+    use crate::mdl::semantic_query::SemanticQuery;
+    use crate::mdl::WrenMDL;
+    use datafusion::common::Result;
+    use insta::assert_snapshot;
+
+    fn wren_mdl() -> WrenMDL {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "int").build())
+                    .column(ColumnBuilder::new("o_custkey", "int").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("revenue")
+                    .base_object("orders")
+                    .dimension(ColumnBuilder::new("o_custkey", "int").build())
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("sum(o_totalprice)")
+                            .build(),
+                    )
+                    .time_grain(
+                        TimeGrainBuilder::new("order_date")
+                            .ref_column("o_orderdate")
+                            .date_part(TimeUnit::Month)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        WrenMDL::new(manifest)
+    }
+
+    #[test]
+    fn test_semantic_query_to_sql() -> Result<()> {
+        let wren_mdl = wren_mdl();
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "dimensions": ["revenue.o_custkey"],
+                "timeDimensions": [{"dimension": "revenue.order_date", "granularity": "month"}],
+                "filters": [
+                    {"member": "revenue.o_custkey", "operator": "in", "values": [1, 2]},
+                    {"member": "revenue.total", "operator": "gt", "values": [100]}
+                ],
+                "order": [{"member": "revenue.order_date", "direction": "desc"}],
+                "limit": 10
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl)?,
+            @r#"SELECT "revenue"."o_custkey", "revenue"."order_date__month", "revenue"."total" FROM "revenue" WHERE "revenue"."o_custkey" IN (1, 2) AND "revenue"."total" > 100 ORDER BY "revenue"."order_date__month" DESC LIMIT 10"#
+        );
+
+        let query = SemanticQuery::from_json(
+            r#"{
+                "dimensions": ["orders.o_custkey"],
+                "filters": [{"member": "orders.o_orderkey", "operator": "isNotNull"}]
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl)?,
+            @r#"SELECT DISTINCT "orders"."o_custkey" FROM "orders" WHERE "orders"."o_orderkey" IS NOT NULL"#
+        );
+
+        let query = SemanticQuery::from_json(
+            r#"{"measures": ["revenue.total"], "dimensions": ["orders.o_custkey"]}"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl).unwrap_err().to_string(),
+            @"This feature is not implemented: Semantic query across multiple objects isn't supported: revenue, orders"
+        );
+
+        let query = SemanticQuery::from_json(
+            r#"{"measures": ["revenue.total"], "filters": [{"member": "revenue.o_custkey", "operator": "equals", "values": []}]}"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl).unwrap_err().to_string(),
+            @"Error during planning: Filter Equals of revenue.o_custkey requires exactly one value"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_gaps() -> Result<()> {
+        let wren_mdl = wren_mdl();
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "timeDimensions": [{
+                    "dimension": "revenue.order_date",
+                    "granularity": "month",
+                    "dateRange": ["2024-01-01", "2024-03-31"],
+                    "fillGaps": true
+                }],
+                "order": [{"member": "revenue.order_date"}]
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl)?,
+            @r#"SELECT "__spine"."order_date__month", "__query"."total" FROM (SELECT UNNEST(generate_series(date_trunc('month', CAST('2024-01-01' AS TIMESTAMP)), date_trunc('month', CAST('2024-03-31' AS TIMESTAMP)), INTERVAL '1' MONTH)) AS "order_date__month") AS "__spine" LEFT JOIN (SELECT "revenue"."order_date__month", "revenue"."total" FROM "revenue" WHERE "revenue"."order_date__month" >= date_trunc('month', CAST('2024-01-01' AS TIMESTAMP)) AND "revenue"."order_date__month" <= date_trunc('month', CAST('2024-03-31' AS TIMESTAMP))) AS "__query" ON "__spine"."order_date__month" = "__query"."order_date__month" ORDER BY "__spine"."order_date__month" ASC"#
+        );
+
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "timeDimensions": [{"dimension": "revenue.order_date", "granularity": "month", "fillGaps": true}]
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl).unwrap_err().to_string(),
+            @"Error during planning: Time dimension revenue.order_date should have the date range to fill the gaps"
+        );
+
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "dimensions": ["revenue.o_custkey"],
+                "timeDimensions": [{
+                    "dimension": "revenue.order_date",
+                    "granularity": "month",
+                    "dateRange": ["2024-01-01", "2024-03-31"],
+                    "fillGaps": true
+                }]
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl)?,
+            @r#"WITH "__query" AS (SELECT "revenue"."o_custkey", "revenue"."order_date__month", "revenue"."total" FROM "revenue" WHERE "revenue"."order_date__month" >= date_trunc('month', CAST('2024-01-01' AS TIMESTAMP)) AND "revenue"."order_date__month" <= date_trunc('month', CAST('2024-03-31' AS TIMESTAMP))) SELECT "__dimensions"."o_custkey", "__spine"."order_date__month", "__query"."total" FROM (SELECT UNNEST(generate_series(date_trunc('month', CAST('2024-01-01' AS TIMESTAMP)), date_trunc('month', CAST('2024-03-31' AS TIMESTAMP)), INTERVAL '1' MONTH)) AS "order_date__month") AS "__spine" CROSS JOIN (SELECT DISTINCT "__query"."o_custkey" FROM "__query") AS "__dimensions" LEFT JOIN "__query" ON "__spine"."order_date__month" = "__query"."order_date__month" AND "__dimensions"."o_custkey" IS NOT DISTINCT FROM "__query"."o_custkey""#
+        );
+
+        // the date spine can't be generated by MySQL, which stores the base model of the metric
+        let manifest = ManifestBuilder::new()
+            .data_source(DataSource::Postgres)
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .data_source(DataSource::MySQL)
+                    .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                    .column(ColumnBuilder::new("o_totalprice", "int").build())
+                    .build(),
+            )
+            .metric(
+                MetricBuilder::new("revenue")
+                    .base_object("orders")
+                    .measure(
+                        ColumnBuilder::new("total", "int")
+                            .expression("sum(o_totalprice)")
+                            .build(),
+                    )
+                    .time_grain(
+                        TimeGrainBuilder::new("order_date")
+                            .ref_column("o_orderdate")
+                            .date_part(TimeUnit::Month)
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let query = SemanticQuery::from_json(
+            r#"{
+                "measures": ["revenue.total"],
+                "timeDimensions": [{
+                    "dimension": "revenue.order_date",
+                    "granularity": "month",
+                    "dateRange": ["2024-01-01", "2024-03-31"],
+                    "fillGaps": true
+                }]
+            }"#,
+        )?;
+        assert_snapshot!(
+            query.to_sql(&WrenMDL::new(manifest)).unwrap_err().to_string(),
+            @"Error during planning: Filling the gaps isn't supported by MYSQL"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hierarchy() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("stores")
+                    .table_reference("stores")
+                    .column(ColumnBuilder::new("country", "string").build())
+                    .column(ColumnBuilder::new("region", "string").build())
+                    .column(ColumnBuilder::new("city", "string").build())
+                    .hierarchy("geo", &["country", "region", "city"])
+                    .build(),
+            )
+            .build();
+        let wren_mdl = WrenMDL::new(manifest);
+
+        let query = SemanticQuery::default().drill_down(&wren_mdl, "stores.geo")?;
+        assert_eq!(query.dimensions, vec!["stores.country"]);
+        let query = query.drill_down(&wren_mdl, "stores.geo")?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl)?,
+            @r#"SELECT DISTINCT "stores"."country", "stores"."region" FROM "stores""#
+        );
+        let query = query
+            .drill_down(&wren_mdl, "stores.geo")?
+            .drill_up(&wren_mdl, "stores.geo")?;
+        assert_eq!(query.dimensions, vec!["stores.country", "stores.region"]);
+
+        let query = query
+            .drill_down(&wren_mdl, "stores.geo")?
+            .drill_down(&wren_mdl, "stores.geo");
+        assert_snapshot!(
+            query.unwrap_err().to_string(),
+            @"Error during planning: Hierarchy stores.geo is already at the finest level city"
+        );
+
+        let query = SemanticQuery::from_json(r#"{"dimensions": ["stores.city"]}"#)?;
+        assert_snapshot!(
+            query.to_sql(&wren_mdl).unwrap_err().to_string(),
+            @"Error during planning: Dimension stores.city should be grouped with the coarser levels of hierarchy geo: country, region"
+        );
+        Ok(())
+    }
+}
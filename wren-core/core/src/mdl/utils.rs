@@ -13,8 +13,8 @@ use std::collections::{BTreeSet, VecDeque};
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
-use crate::logical_plan::utils::{from_qualified_name, try_map_data_type};
-use crate::mdl::manifest::Model;
+use crate::logical_plan::utils::{from_qualified_name, try_map_data_type_by_data_source};
+use crate::mdl::manifest::{DataSource, Model};
 use crate::mdl::{AnalyzedWrenMDL, ColumnReference, Dataset, SessionStateRef};
 
 pub fn to_expr_queue(column: Column) -> VecDeque<String> {
@@ -127,10 +127,11 @@ pub fn create_wren_calculated_field_expr(
 
     let Some(schema) = models
         .into_iter()
-        .map(|m| analyzed_wren_mdl.wren_mdl().get_model(&m))
-        .filter(|m| m.is_some())
-        .map(|m| Dataset::Model(m.unwrap()))
-        .map(|m| m.to_qualified_schema())
+        .filter_map(|m| analyzed_wren_mdl.wren_mdl().get_model(&m))
+        .map(|m| {
+            let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&m);
+            Dataset::Model(m).to_qualified_schema(&data_source)
+        })
         .reduce(|acc, schema| acc?.join(&schema?))
         .transpose()?
     else {
@@ -146,9 +147,11 @@ pub(crate) fn create_remote_expr_for_model(
     analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
     session_state: SessionStateRef,
 ) -> Result<Expr> {
+    let data_source = analyzed_wren_mdl.wren_mdl().model_type_source(&model);
     let dataset = Dataset::Model(model);
     let schema = dataset.to_remote_schema(
         Some(analyzed_wren_mdl.wren_mdl().get_register_tables()),
+        &data_source,
         Arc::clone(&session_state),
     )?;
     let session_state = session_state.read();
@@ -160,10 +163,11 @@ pub(crate) fn create_remote_expr_for_model(
 pub(crate) fn create_wren_expr_for_model(
     expr: &str,
     model: Arc<Model>,
+    data_source: &DataSource,
     session_state: SessionStateRef,
 ) -> Result<Expr> {
     let dataset = Dataset::Model(model);
-    let schema = dataset.to_qualified_schema()?;
+    let schema = dataset.to_qualified_schema(data_source)?;
     let session_state = session_state.read();
     session_state.create_logical_expr(
         qualified_expr(expr, &schema, &session_state)?.as_str(),
@@ -210,15 +214,21 @@ pub fn quoted(s: &str) -> String {
     format!("\"{}\"", s)
 }
 
-/// Transform the column to a datafusion field
-pub fn to_field(column: &wren_core_base::mdl::Column) -> Result<Field> {
-    let data_type = try_map_data_type(&column.r#type)?;
+/// Transform the column to a datafusion field. The type of the column is mapped by the type
+/// names of the data source.
+pub fn to_field(
+    column: &wren_core_base::mdl::Column,
+    data_source: &DataSource,
+) -> Result<Field> {
+    let data_type = try_map_data_type_by_data_source(&column.r#type, data_source)?;
     Ok(Field::new(&column.name, data_type, column.not_null))
 }
 
-/// Transform the column to a datafusion field for a remote table
+/// Transform the column to a datafusion field for a remote table. The type of the column is
+/// mapped by the type names of the data source.
 pub fn to_remote_field(
     column: &wren_core_base::mdl::Column,
+    data_source: &DataSource,
     session_state: SessionStateRef,
 ) -> Result<Vec<Field>> {
     if column.expression().is_some() {
@@ -233,13 +243,17 @@ pub fn to_remote_field(
             .map(|c| {
                 Ok(Field::new(
                     c.value,
-                    try_map_data_type(&column.r#type)?,
+                    try_map_data_type_by_data_source(&column.r#type, data_source)?,
                     false,
                 ))
             })
             .collect::<Result<_>>()
     } else {
-        Ok(vec![to_field(column)?])
+        Ok(vec![Field::new(
+            &column.name,
+            try_map_data_type_by_data_source(&column.r#type, data_source)?,
+            column.not_null,
+        )])
     }
 }
 
@@ -364,6 +378,7 @@ mod tests {
         let expr = super::create_wren_expr_for_model(
             "c_name",
             Arc::clone(&model),
+            &super::DataSource::default(),
             ctx.state_ref(),
         )?;
         assert_eq!(expr.to_string(), "customer.c_name");